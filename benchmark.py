@@ -24,7 +24,7 @@ programs = [
     ('`wc -w`', 'wc -w', 'LC_ALL=C wc -w', '`wc` baseline; optimized sets `LC_ALL=C`'),
     ('C', './simple-c', './optimized-c', ''),
     ('Go', './simple-go', './optimized-go', ''),
-    ('Rust', './rust/simple/target/release/countwords', './rust/optimized/target/release/countwords', 'by Andrew Gallant'),
+    ('Rust', './rust/target/release/countwords-simple', './rust/target/release/countwords-optimized', 'by Andrew Gallant'),
     ('C++', './simple-cpp', './optimized-cpp', 'optimized by Jussi P, Adev, Nathan M'),
     ('Python', 'python3 simple.py', 'python3 optimized.py', ''),
     ('Ruby', 'ruby simple.rb', 'ruby optimized.rb', 'by Bill Mill'),
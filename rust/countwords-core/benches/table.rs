@@ -0,0 +1,103 @@
+// Benchmarks `Table` at several load factors. Probing is chosen at compile
+// time, so compare linear probing with Robin Hood hashing by running this
+// twice:
+//
+//     cargo bench -p countwords-core --bench table
+//     cargo bench -p countwords-core --bench table --features robin-hood
+//
+// Each run's results are reported under the name of its probing scheme.
+//
+// Every table has the same number of slots, and is filled to its load factor
+// with random words, which is the worst case before it would grow.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+use countwords_core::Table;
+
+/// The number of slots in every table.
+const SLOTS: usize = 1 << 16;
+const LOAD_FACTORS: [f64; 5] = [0.5, 0.7, 0.8, 0.9, 0.95];
+
+#[cfg(not(feature = "robin-hood"))]
+const PROBING: &str = "linear";
+#[cfg(feature = "robin-hood")]
+const PROBING: &str = "robin-hood";
+
+/// Returns `n` distinct random words made of the letters in `alphabet`.
+fn words(n: usize, seed: u64, alphabet: &[u8]) -> Vec<Vec<u8>> {
+    let mut state = seed;
+    let mut next = move || {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut seen = std::collections::HashSet::new();
+    while seen.len() < n {
+        let len = 3 + (next() % 10) as usize;
+        let word: Vec<u8> = (0..len)
+            .map(|_| alphabet[(next() % alphabet.len() as u64) as usize])
+            .collect();
+        seen.insert(word);
+    }
+    seen.into_iter().collect()
+}
+
+fn table(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("table/{}", PROBING));
+    for &load in LOAD_FACTORS.iter() {
+        let n = (SLOTS as f64 * load) as usize;
+        // The words looked up but never inserted have a 'z', which none of
+        // the inserted words do.
+        let present = words(n, 0x9E3779B97F4A7C15, b"abcdefghijklmnopqrstuvwxy");
+        let missing: Vec<Vec<u8>> = words(n, 0xD1B54A32D192ED03, b"abcdefghijklmnopqrstuvwxy")
+            .into_iter()
+            .map(|mut w| {
+                w.push(b'z');
+                w
+            })
+            .collect();
+
+        let mut full = Table::with_capacity(0).load_factor(load);
+        for word in present.iter() {
+            full.add(word, 1).unwrap();
+        }
+        let mut empty = full.clone();
+        empty.clear();
+
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("insert", load), &load, |b, _| {
+            b.iter_batched_ref(
+                || empty.clone(),
+                |table| {
+                    for word in present.iter() {
+                        let hash = table.hash(word);
+                        *table.entry(word, hash).or_insert(0) += 1;
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("hit", load), &load, |b, _| {
+            b.iter(|| {
+                present
+                    .iter()
+                    .filter_map(|word| full.get(word))
+                    .sum::<u64>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("miss", load), &load, |b, _| {
+            b.iter(|| {
+                missing
+                    .iter()
+                    .filter_map(|word| full.get(word))
+                    .sum::<u64>()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, table);
+criterion_main!(benches);
@@ -0,0 +1,91 @@
+// Benchmarks counting with the dense trie of optimized-trie against the radix
+// trie, on text drawn from vocabularies of a few sizes with a Zipf-like
+// distribution, like natural language:
+//
+//     cargo bench -p countwords-core --bench trie
+//
+// The memory each trie ends up using is printed before the timings, since
+// that's the point of the radix trie.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use countwords_core::{RadixTrieCounter, TrieCounter};
+
+/// The number of distinct words in each benchmark's vocabulary.
+const VOCABULARIES: [usize; 3] = [1_000, 30_000, 300_000];
+/// The number of words in each benchmark's text.
+const WORDS: usize = 1_000_000;
+
+/// Returns a stream of pseudo random numbers.
+fn rng(mut state: u64) -> impl FnMut() -> u64 {
+    move || {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    }
+}
+
+/// Returns `WORDS` words separated by spaces and newlines, drawn from `n`
+/// random words so that the k-th most frequent is about k times rarer than
+/// the most frequent.
+fn text(n: usize) -> Vec<u8> {
+    let mut next = rng(0x9E3779B97F4A7C15);
+    let vocabulary: Vec<Vec<u8>> = (0..n)
+        .map(|_| {
+            let len = 2 + (next() % 10) as usize;
+            (0..len).map(|_| b'a' + (next() % 26) as u8).collect()
+        })
+        .collect();
+    // Picking the k-th word with probability proportional to 1/k is the same
+    // as picking a uniform point on the log scale.
+    let max = (n as f64).ln();
+    let mut text = vec![];
+    for i in 0..WORDS {
+        let u = (next() >> 11) as f64 / (1u64 << 53) as f64;
+        let k = ((u * max).exp() as usize - 1).min(n - 1);
+        text.extend_from_slice(&vocabulary[k]);
+        text.push(if i % 12 == 11 { b'\n' } else { b' ' });
+    }
+    text
+}
+
+fn trie(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trie");
+    group.sample_size(10);
+    for &n in VOCABULARIES.iter() {
+        let text = text(n);
+
+        let mut dense = TrieCounter::new();
+        countwords_core::count(&text[..], &mut dense).unwrap();
+        let mut radix = RadixTrieCounter::new();
+        countwords_core::count(&text[..], &mut radix).unwrap();
+        println!(
+            "trie/{}: dense uses {} KiB, radix uses {} KiB",
+            n,
+            dense.memory_usage() / 1024,
+            radix.memory_usage() / 1024,
+        );
+
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::new("dense", n), &text, |b, text| {
+            b.iter(|| {
+                let mut counter = TrieCounter::new();
+                countwords_core::count(&text[..], &mut counter).unwrap();
+                counter
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("radix", n), &text, |b, text| {
+            b.iter(|| {
+                let mut counter = RadixTrieCounter::new();
+                countwords_core::count(&text[..], &mut counter).unwrap();
+                counter
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, trie);
+criterion_main!(benches);
@@ -0,0 +1,107 @@
+// This version is fast-simple, except that everything it keeps around, the
+// hashmap's table and every distinct word, is allocated in a bumpalo arena.
+// Nothing is ever freed individually, so allocating is just a pointer bump.
+
+use std::io;
+
+use bumpalo::Bump;
+use hashbrown::{DefaultHashBuilder, HashMap};
+
+use crate::{
+    counts,
+    fast_simple::HASHMAP_INITIAL_CAPACITY,
+    lines::{invalid_utf8, LineBuffer},
+    Count, Feed, WordCounter,
+};
+
+/// Counts words separated by ASCII whitespace, lowercased with ASCII rules,
+/// in a hashbrown `HashMap` allocated in a caller supplied arena. The input
+/// must be valid UTF-8.
+#[derive(Debug)]
+pub struct Bumpalo<'b, N = u64> {
+    bump: &'b Bump,
+    /// Each word's count and the order in which it was first seen.
+    counts: HashMap<&'b str, (N, usize), DefaultHashBuilder, &'b Bump>,
+    lines: LineBuffer,
+}
+
+impl<'b> Bumpalo<'b> {
+    /// Create a new empty counter with u64 counts that allocates in the given
+    /// arena.
+    pub fn new(bump: &'b Bump) -> Bumpalo<'b> {
+        Bumpalo::with_counts(bump)
+    }
+}
+
+impl<'b, N: Count> Bumpalo<'b, N> {
+    /// Create a new empty counter with counts of type `N` that allocates in
+    /// the given arena.
+    pub fn with_counts(bump: &'b Bump) -> Bumpalo<'b, N> {
+        Bumpalo {
+            bump,
+            counts: HashMap::with_capacity_in(HASHMAP_INITIAL_CAPACITY, bump),
+            lines: LineBuffer::default(),
+        }
+    }
+}
+
+impl<'b, N: Count> Feed for Bumpalo<'b, N> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let (bump, counts) = (self.bump, &mut self.counts);
+        self.lines.feed(chunk, |bytes_buffer| {
+            count_words(bump, counts, bytes_buffer)
+        })
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let (bump, counts) = (self.bump, &mut self.counts);
+        self.lines
+            .finish(|bytes_buffer| count_words(bump, counts, bytes_buffer))
+    }
+}
+
+impl<'b, N: Count> WordCounter for Bumpalo<'b, N> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        for (word, &(count, seen)) in self.counts.iter() {
+            f(word.as_bytes(), count.get(), seen);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.counts.len()
+    }
+}
+
+fn count_words<'b, N: Count>(
+    bump: &'b Bump,
+    counts: &mut HashMap<&'b str, (N, usize), DefaultHashBuilder, &'b Bump>,
+    bytes_buffer: &mut [u8],
+) -> io::Result<()> {
+    // make_ascii_lowercase on str requires a call to as_bytes(), so we use directly
+    // on bytes here, but there doesn't seem to be a perf advantage
+    bytes_buffer.make_ascii_lowercase();
+
+    std::str::from_utf8(bytes_buffer)
+        .map_err(invalid_utf8)?
+        .split_ascii_whitespace()
+        .try_for_each(|word| increment(bump, counts, word))
+}
+
+fn increment<'b, N: Count>(
+    bump: &'b Bump,
+    counts: &mut HashMap<&'b str, (N, usize), DefaultHashBuilder, &'b Bump>,
+    word: &str,
+) -> io::Result<()> {
+    match counts.get_mut(word) {
+        Some(&mut (ref mut count, _)) => {
+            count.increment()?;
+        }
+        None => {
+            let seen = counts.len();
+            let count = counts::one()?;
+            // SAFETY: we check for the key just above
+            unsafe { counts.insert_unique_unchecked(bump.alloc_str(word), (count, seen)) };
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+// optimized-unsafe stores every distinct word in one arena, and its map's
+// keys point into that arena. That's only sound if bytes never move once
+// they're in the arena.
+//
+// A `ChunkedArena` grows by adding fixed size chunks instead of moving the
+// bytes it already has, so a slice it returns stays put for as long as the
+// arena lives. It only needs a shared borrow to allocate, like `bumpalo`, so
+// that any number of slices can be borrowed from it at once.
+//
+// An `ArenaMap` is a map whose keys are slices of its own arena. Owning the
+// arena and the keys borrowed from it in one value can't be expressed with
+// lifetimes, so this is where the one lifetime extension lives, and nothing
+// borrowed from the arena ever gets out with a lifetime longer than a borrow
+// of the map.
+//
+// Both are exercised under Miri by the tests in tests/chunked.rs.
+
+use std::{cell::RefCell, fmt, ptr::NonNull};
+
+use fxhash::FxHashMap as HashMap;
+
+/// The size of the chunks an arena allocates by default.
+const CHUNK_SIZE: usize = 64 * (1 << 10);
+
+/// An arena of bytes that grows in fixed size chunks, so that nothing in it
+/// ever moves.
+///
+/// A word longer than the chunk size gets a chunk of its own.
+pub struct ChunkedArena {
+    chunk_size: usize,
+    chunks: RefCell<Vec<Chunk>>,
+}
+
+/// One block of an arena, allocated as a `Box<[u8]>` and freed as one when
+/// the arena is dropped. Its bytes are only ever written through 'ptr', and
+/// only past 'len', so that the slices already handed out are never aliased
+/// by a mutable borrow.
+struct Chunk {
+    ptr: NonNull<u8>,
+    cap: usize,
+    len: usize,
+}
+
+// SAFETY: an arena owns its chunks exclusively, just like a `Vec<Box<[u8]>>`
+// would. It isn't `Sync`, since allocating through a shared borrow isn't
+// synchronized.
+unsafe impl Send for ChunkedArena {}
+
+impl Default for ChunkedArena {
+    fn default() -> ChunkedArena {
+        ChunkedArena::new()
+    }
+}
+
+impl ChunkedArena {
+    /// Create a new empty arena, which allocates in chunks of 64 KiB.
+    pub fn new() -> ChunkedArena {
+        ChunkedArena::with_chunk_size(CHUNK_SIZE)
+    }
+
+    /// Create a new empty arena that allocates in chunks of `chunk_size`
+    /// bytes.
+    ///
+    /// # Panics
+    ///
+    /// If `chunk_size` is zero.
+    pub fn with_chunk_size(chunk_size: usize) -> ChunkedArena {
+        assert!(chunk_size > 0, "chunk size must be positive");
+        ChunkedArena {
+            chunk_size,
+            chunks: RefCell::new(vec![]),
+        }
+    }
+
+    /// Copies `bytes` into this arena, returning the copy.
+    pub fn alloc(&self, bytes: &[u8]) -> &[u8] {
+        if bytes.is_empty() {
+            return &[];
+        }
+        let mut chunks = self.chunks.borrow_mut();
+        let fits = chunks.last().is_some_and(|c| c.cap - c.len >= bytes.len());
+        if !fits {
+            let cap = self.chunk_size.max(bytes.len());
+            let block = vec![0u8; cap].into_boxed_slice();
+            // This unwrap is correct because a box is never null.
+            let ptr = NonNull::new(Box::into_raw(block).cast::<u8>()).unwrap();
+            chunks.push(Chunk { ptr, cap, len: 0 });
+        }
+        // This unwrap is correct because there's always a chunk by now.
+        let chunk = chunks.last_mut().unwrap();
+        // SAFETY: the chunk has room for 'bytes' past 'len', which no slice
+        // handed out so far covers, and 'bytes' can't overlap it since it's
+        // borrowed from elsewhere. The slice returned lives no longer than
+        // the borrow of this arena, and the chunk is only freed when the
+        // arena is dropped.
+        unsafe {
+            let dst = chunk.ptr.as_ptr().add(chunk.len);
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), dst, bytes.len());
+            chunk.len += bytes.len();
+            std::slice::from_raw_parts(dst, bytes.len())
+        }
+    }
+
+    /// Returns the number of bytes allocated from this arena.
+    pub fn allocated(&self) -> usize {
+        self.chunks.borrow().iter().map(|c| c.len).sum()
+    }
+
+    /// Returns the number of chunks this arena has allocated.
+    pub fn chunks(&self) -> usize {
+        self.chunks.borrow().len()
+    }
+}
+
+impl Drop for ChunkedArena {
+    fn drop(&mut self) {
+        for chunk in self.chunks.get_mut().drain(..) {
+            let block = std::ptr::slice_from_raw_parts_mut(chunk.ptr.as_ptr(), chunk.cap);
+            // SAFETY: 'block' is exactly the box this chunk was made from, and
+            // nothing borrowed from the arena outlives it.
+            drop(unsafe { Box::from_raw(block) });
+        }
+    }
+}
+
+impl fmt::Debug for ChunkedArena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunkedArena")
+            .field("chunk_size", &self.chunk_size)
+            .field("chunks", &self.chunks())
+            .field("allocated", &self.allocated())
+            .finish()
+    }
+}
+
+/// A hash map, using fxhash, whose keys are copied into a `ChunkedArena` it
+/// owns instead of each being allocated separately.
+///
+/// Keys can't be removed, since their bytes would stay in the arena anyway.
+#[derive(Debug, Default)]
+pub struct ArenaMap<V> {
+    // N.B. This is declared before 'arena' so that it is dropped first, since
+    // its keys borrow from 'arena'.
+    map: HashMap<&'static [u8], V>,
+    arena: ChunkedArena,
+}
+
+impl<V> ArenaMap<V> {
+    /// Create a new empty map.
+    pub fn new() -> ArenaMap<V> {
+        ArenaMap::with_arena(ChunkedArena::new())
+    }
+
+    /// Create a new empty map that copies its keys into `arena`.
+    pub fn with_arena(arena: ChunkedArena) -> ArenaMap<V> {
+        ArenaMap {
+            map: HashMap::default(),
+            arena,
+        }
+    }
+
+    /// Returns the number of keys in this map.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if there are no keys in this map.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the arena this map's keys are stored in.
+    pub fn arena(&self) -> &ChunkedArena {
+        &self.arena
+    }
+
+    /// Returns the value of `key`, if it's in this map.
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    /// Returns a mutable reference to the value of `key`, if it's in this
+    /// map.
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
+        self.map.get_mut(key)
+    }
+
+    /// Sets the value of `key`, returning its old value if it was already in
+    /// this map. The key is only copied into the arena when it's new.
+    pub fn insert(&mut self, key: &[u8], value: V) -> Option<V> {
+        if let Some(old) = self.map.get_mut(key) {
+            return Some(std::mem::replace(old, value));
+        }
+        let key = self.arena.alloc(key);
+        // SAFETY: 'key' points into a chunk of 'self.arena', which never moves
+        // or frees a chunk before it's dropped, and 'self.map' is dropped
+        // before 'self.arena' is. The arena can't be replaced either, since
+        // it's only ever borrowed immutably. Keys only ever leave the map
+        // reborrowed for as long as the map is (see 'iter'), never as
+        // 'static.
+        let key: &'static [u8] = unsafe { std::slice::from_raw_parts(key.as_ptr(), key.len()) };
+        self.map.insert(key, value)
+    }
+
+    /// Returns an iterator over every key in this map and its value, in no
+    /// particular order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&[u8], &V)> + '_ {
+        self.map.iter().map(|(&key, value)| (key, value))
+    }
+}
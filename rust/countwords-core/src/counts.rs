@@ -0,0 +1,182 @@
+// Each engine's counts are a type parameter, which picks both how wide they
+// are and what happens when one would overflow: the plain integers fail with a
+// `CountOverflow`, and `Saturating` ones stop at their maximum. No count ever
+// wraps around.
+//
+// Engines default to u64, except for the tries, which default to u32. That's
+// where narrower counts matter for memory, since there's a count for every
+// node rather than every word.
+
+use std::{fmt, io, num::Saturating, str::FromStr};
+
+use crate::UnknownName;
+
+/// The count of a word, as kept by an engine.
+///
+/// Every engine's counter takes its count type as a parameter, which is u64
+/// unless noted otherwise, and implements `Default` for any `Count`. E.g.,
+/// `Optimized::<Saturating<u32>>::default()`.
+///
+/// This is implemented for `u32` and `u64`, whose increments fail when they
+/// would overflow, and for `Saturating<u32>` and `Saturating<u64>`, whose
+/// increments stop at their maximum instead.
+pub trait Count: Copy + Default + fmt::Debug + Send + Sync + 'static {
+    /// Adds one to this count. If that would overflow, then either an error
+    /// is returned and the count is left alone, or the count stays at its
+    /// maximum, depending on the type.
+    fn increment(&mut self) -> Result<(), CountOverflow>;
+
+    /// Returns this count as a u64.
+    fn get(self) -> u64;
+}
+
+impl Count for u32 {
+    #[inline]
+    fn increment(&mut self) -> Result<(), CountOverflow> {
+        *self = self
+            .checked_add(1)
+            .ok_or_else(|| CountOverflow::new(u64::from(u32::MAX)))?;
+        Ok(())
+    }
+
+    fn get(self) -> u64 {
+        u64::from(self)
+    }
+}
+
+impl Count for u64 {
+    #[inline]
+    fn increment(&mut self) -> Result<(), CountOverflow> {
+        *self = self
+            .checked_add(1)
+            .ok_or_else(|| CountOverflow::new(u64::MAX))?;
+        Ok(())
+    }
+
+    fn get(self) -> u64 {
+        self
+    }
+}
+
+impl Count for Saturating<u32> {
+    #[inline]
+    fn increment(&mut self) -> Result<(), CountOverflow> {
+        *self += 1;
+        Ok(())
+    }
+
+    fn get(self) -> u64 {
+        u64::from(self.0)
+    }
+}
+
+impl Count for Saturating<u64> {
+    #[inline]
+    fn increment(&mut self) -> Result<(), CountOverflow> {
+        *self += 1;
+        Ok(())
+    }
+
+    fn get(self) -> u64 {
+        self.0
+    }
+}
+
+/// Returns a count of one, for a word seen for the first time.
+pub(crate) fn one<N: Count>() -> Result<N, CountOverflow> {
+    let mut count = N::default();
+    count.increment()?;
+    Ok(count)
+}
+
+/// The error returned when a word is counted more times than its `Count` can
+/// hold.
+///
+/// It converts into an `io::Error`, which is how engines return it.
+#[derive(Clone, Debug)]
+pub struct CountOverflow {
+    max: u64,
+}
+
+impl CountOverflow {
+    /// Create an error for a count whose maximum is `max`.
+    pub fn new(max: u64) -> CountOverflow {
+        CountOverflow { max }
+    }
+
+    /// Returns true if `err` was converted from a `CountOverflow`.
+    pub fn is(err: &io::Error) -> bool {
+        err.get_ref().is_some_and(|err| err.is::<CountOverflow>())
+    }
+}
+
+impl fmt::Display for CountOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a word was counted more than {} times, which overflows its count",
+            self.max
+        )
+    }
+}
+
+impl std::error::Error for CountOverflow {}
+
+impl From<CountOverflow> for io::Error {
+    fn from(err: CountOverflow) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// A `Count`, chosen at runtime. See `Engine::counter_with_counts`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CountType {
+    /// `u32`, which fails past 4294967295.
+    U32,
+    /// `u64`, which fails past 18446744073709551615.
+    U64,
+    /// `Saturating<u32>`, which stops at 4294967295.
+    SaturatingU32,
+    /// `Saturating<u64>`, which stops at 18446744073709551615.
+    SaturatingU64,
+}
+
+impl CountType {
+    /// Every count type, in the order they're listed in help output.
+    pub const ALL: [CountType; 4] = [
+        CountType::U32,
+        CountType::U64,
+        CountType::SaturatingU32,
+        CountType::SaturatingU64,
+    ];
+
+    /// Returns the name of this count type, as accepted by
+    /// `CountType::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            CountType::U32 => "u32",
+            CountType::U64 => "u64",
+            CountType::SaturatingU32 => "saturating-u32",
+            CountType::SaturatingU64 => "saturating-u64",
+        }
+    }
+}
+
+impl fmt::Display for CountType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CountType {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<CountType, UnknownName> {
+        let names = CountType::ALL.map(CountType::name);
+        CountType::ALL
+            .iter()
+            .copied()
+            .find(|counts| counts.name() == s)
+            .ok_or_else(|| UnknownName::new("count type", s, &names))
+    }
+}
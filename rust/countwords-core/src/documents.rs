@@ -0,0 +1,162 @@
+// Per-document counts work with any strategy: each document is counted by a
+// fresh counter, which is folded into the merged counts as soon as the document
+// ends. The input is still only read once, and the only thing kept per document
+// is a (document, count) pair for each word that occurs in it. Most words occur
+// in few documents, so this is far smaller than a dense row of counts for every
+// word.
+
+use std::io;
+
+use fxhash::FxHashMap as HashMap;
+
+use crate::{CountOverflow, Feed, WordCounter};
+
+/// A word's total count and its count in each document it occurs in.
+#[derive(Clone, Debug)]
+struct DocumentCounts {
+    total: u64,
+    /// The order in which this word was first seen, across all documents.
+    seen: usize,
+    /// (document index, count) pairs in ascending order of document index.
+    per_document: Vec<(u32, u64)>,
+}
+
+/// Counts words per document (i.e., per input ended by
+/// `Feed::end_input`) as well as in total.
+///
+/// As a `WordCounter`, this reports the totals.
+pub struct PerDocument<C, F> {
+    new_counter: F,
+    /// The counter for the document being fed.
+    current: C,
+    /// Whether anything has been fed to the current document.
+    fed: bool,
+    documents: u32,
+    counts: HashMap<Box<[u8]>, DocumentCounts>,
+}
+
+impl<C, F> std::fmt::Debug for PerDocument<C, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PerDocument")
+            .field("documents", &self.documents)
+            .field("len", &self.counts.len())
+            .finish()
+    }
+}
+
+impl<C: WordCounter, F: FnMut() -> C> PerDocument<C, F> {
+    /// Create a new empty counter that counts each document with a counter
+    /// created by `new_counter`.
+    pub fn new(mut new_counter: F) -> PerDocument<C, F> {
+        let current = new_counter();
+        PerDocument {
+            new_counter,
+            current,
+            fed: false,
+            documents: 0,
+            counts: HashMap::default(),
+        }
+    }
+
+    /// Returns the number of documents that have ended so far.
+    pub fn documents(&self) -> usize {
+        self.documents as usize
+    }
+
+    /// Returns every distinct word, its total count and its count in each
+    /// document it occurs in, in no particular order. The per-document counts
+    /// are (document index, count) pairs in ascending order of document index,
+    /// where documents are indexed in the order they were fed.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], u64, &[(u32, u64)])> + '_ {
+        self.counts
+            .iter()
+            .map(|(word, counts)| (&**word, counts.total, &*counts.per_document))
+    }
+
+    /// Returns the count of `word` in each document it occurs in, as
+    /// (document index, count) pairs in ascending order of document index.
+    /// If it occurs in none, then the slice is empty.
+    pub fn get(&self, word: &[u8]) -> &[(u32, u64)] {
+        match self.counts.get(word) {
+            None => &[],
+            Some(counts) => &counts.per_document,
+        }
+    }
+
+    /// Finishes the current document and folds its counts into ours.
+    ///
+    /// If a word's total would overflow, then an error is returned, and the
+    /// document's counts of it and of the words after it aren't added.
+    fn end_document(&mut self) -> io::Result<()> {
+        let mut counter = std::mem::replace(&mut self.current, (self.new_counter)());
+        counter.finish()?;
+
+        let (counts, document) = (&mut self.counts, self.documents);
+        let (mut new, mut overflow) = (vec![], false);
+        counter.for_each_seen(&mut |word, count, seen| {
+            if overflow {
+                return;
+            }
+            if let Some(entry) = counts.get_mut(word) {
+                match entry.total.checked_add(count) {
+                    Some(total) => {
+                        entry.total = total;
+                        entry.per_document.push((document, count));
+                    }
+                    None => overflow = true,
+                }
+                return;
+            }
+            new.push((seen, Box::from(word), count));
+        });
+        if overflow {
+            return Err(CountOverflow::new(u64::MAX).into());
+        }
+        // Words we haven't seen before were first seen in this document, in
+        // the order it saw them.
+        new.sort_unstable_by_key(|&(seen, _, _)| seen);
+        for (_, word, count) in new {
+            let entry = DocumentCounts {
+                total: count,
+                seen: counts.len(),
+                per_document: vec![(document, count)],
+            };
+            counts.insert(word, entry);
+        }
+        self.documents += 1;
+        self.fed = false;
+        Ok(())
+    }
+}
+
+impl<C: WordCounter, F: FnMut() -> C> Feed for PerDocument<C, F> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.fed = true;
+        self.current.feed(chunk)
+    }
+
+    fn end_input(&mut self) -> io::Result<()> {
+        self.end_document()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        // The last document has usually been ended already. But if nothing
+        // was ever ended, then everything fed is one document.
+        if self.fed || self.documents == 0 {
+            self.end_document()?;
+        }
+        Ok(())
+    }
+}
+
+impl<C: WordCounter, F: FnMut() -> C> WordCounter for PerDocument<C, F> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        for (word, counts) in self.counts.iter() {
+            f(word, counts.total, counts.seen);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.counts.len()
+    }
+}
@@ -0,0 +1,354 @@
+// Every strategy has its own type, which is what library users will usually
+// want. `Engine` is for when the strategy is only known at runtime, e.g., when
+// it comes from a command line flag.
+
+use std::{fmt, hash::BuildHasher, io, num::Saturating, str::FromStr};
+
+use bumpalo::Bump;
+
+use crate::{
+    hash::{Fnv1, Fnv1a, Fx, SipHash},
+    Bumpalo, Count, CountType, FastSimple, Feed, HashFunction, Optimized, RadixTrieCounter, Simple,
+    TableCounter, Threads, TrieCounter, Unicode, UnsafeArena, WellFasterThanC, WordCounter,
+};
+
+/// The number of distinct words a counter from `Engine::document_counter`
+/// makes room for up front. Most documents have far fewer distinct words than
+/// a whole input, and a counter grows past this when one doesn't.
+const DOCUMENT_CAPACITY: usize = 1024;
+
+/// `Engine::auto` only trusts a sample to stand for inputs smaller than this.
+/// Beyond it, an invalid UTF-8 byte that the sample didn't cover would make an
+/// engine that requires UTF-8 fail after a long time.
+const AUTO_UTF8_MAX_SIZE: u64 = 64 * (1 << 20);
+
+/// A word counting strategy, chosen at runtime.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Engine {
+    /// `Simple`, from the simple variant.
+    Simple,
+    /// `Optimized`, from the optimized variant.
+    Optimized,
+    /// `UnsafeArena`, from the optimized-unsafe variant.
+    UnsafeArena,
+    /// `TrieCounter`, from the optimized-trie variant.
+    Trie,
+    /// `RadixTrieCounter`, which counts like `Engine::Trie` in far less
+    /// memory.
+    RadixTrie,
+    /// `TableCounter`, from the optimized-customhashmap variant.
+    CustomTable,
+    /// `Unicode`, from the bonus variant.
+    BonusUnicode,
+    /// `FastSimple`, from the fast-simple variant.
+    FastSimple,
+    /// `Threads`, from the fun-with-threads variant.
+    Threads,
+    /// `Bumpalo`, from the fun-with-bumpalo variant.
+    Bumpalo,
+    /// `WellFasterThanC`, from the well-faster-than-c variant.
+    WellFasterThanC,
+}
+
+impl Engine {
+    /// Every engine, in the order they're listed in help output.
+    pub const ALL: [Engine; 11] = [
+        Engine::Simple,
+        Engine::Optimized,
+        Engine::UnsafeArena,
+        Engine::Trie,
+        Engine::RadixTrie,
+        Engine::CustomTable,
+        Engine::BonusUnicode,
+        Engine::FastSimple,
+        Engine::Threads,
+        Engine::Bumpalo,
+        Engine::WellFasterThanC,
+    ];
+
+    /// Returns the name of this engine, as accepted by `Engine::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Simple => "simple",
+            Engine::Optimized => "optimized",
+            Engine::UnsafeArena => "unsafe-arena",
+            Engine::Trie => "trie",
+            Engine::RadixTrie => "radix-trie",
+            Engine::CustomTable => "custom-table",
+            Engine::BonusUnicode => "bonus-unicode",
+            Engine::FastSimple => "fast-simple",
+            Engine::Threads => "threads",
+            Engine::Bumpalo => "bumpalo",
+            Engine::WellFasterThanC => "well-faster-than-c",
+        }
+    }
+
+    /// Create a new empty counter for this engine. Only `Engine::Bumpalo`
+    /// allocates in `bump`.
+    pub fn counter<'b>(self, bump: &'b Bump) -> Box<dyn WordCounter + 'b> {
+        self.counter_with_hash(bump, HashFunction::default())
+    }
+
+    /// Like `Engine::counter`, but `Engine::CustomTable` hashes words with
+    /// `hash`. No other engine has a choice of hash function, so they ignore
+    /// it.
+    pub fn counter_with_hash<'b>(
+        self,
+        bump: &'b Bump,
+        hash: HashFunction,
+    ) -> Box<dyn WordCounter + 'b> {
+        match self {
+            Engine::Simple => Box::new(Simple::new()),
+            Engine::Optimized => Box::new(Optimized::new()),
+            Engine::UnsafeArena => Box::new(UnsafeArena::new()),
+            Engine::Trie => Box::new(TrieCounter::new()),
+            Engine::RadixTrie => Box::new(RadixTrieCounter::new()),
+            Engine::CustomTable => match hash {
+                HashFunction::Fnv1 => Box::new(TableCounter::with_hasher(Fnv1::default())),
+                HashFunction::Fnv1a => Box::new(TableCounter::with_hasher(Fnv1a::default())),
+                HashFunction::Fx => Box::new(TableCounter::with_hasher(Fx::default())),
+                HashFunction::SipHash => Box::new(TableCounter::with_hasher(SipHash::new())),
+            },
+            Engine::BonusUnicode => Box::new(Unicode::new()),
+            Engine::FastSimple => Box::new(FastSimple::new()),
+            Engine::Threads => Box::new(Threads::new()),
+            Engine::Bumpalo => Box::new(Bumpalo::new(bump)),
+            Engine::WellFasterThanC => Box::new(WellFasterThanC::new()),
+        }
+    }
+
+    /// Like `Engine::counter_with_hash`, but the counter keeps its counts as
+    /// `counts` rather than the engine's default.
+    pub fn counter_with_counts<'b>(
+        self,
+        bump: &'b Bump,
+        hash: HashFunction,
+        counts: CountType,
+    ) -> Box<dyn WordCounter + 'b> {
+        self.counter_with_capacity(bump, hash, counts, None)
+    }
+
+    /// Returns a counter for one document of many, for `PerDocument`. It's a
+    /// counter for `Engine::per_document`, which keeps its counts as `counts`
+    /// if given, and otherwise as the engine's default.
+    ///
+    /// Engines that make room for tens of thousands of words up front, e.g.,
+    /// `Engine::CustomTable`, start much smaller instead, since a new counter
+    /// is created for every document.
+    pub fn document_counter<'b>(
+        self,
+        bump: &'b Bump,
+        hash: HashFunction,
+        counts: Option<CountType>,
+    ) -> Box<dyn WordCounter + 'b> {
+        let engine = self.per_document();
+        let counts = counts.unwrap_or(match engine {
+            Engine::Trie | Engine::RadixTrie => CountType::U32,
+            _ => CountType::U64,
+        });
+        engine.counter_with_capacity(bump, hash, counts, Some(DOCUMENT_CAPACITY))
+    }
+
+    /// Returns a counter that keeps its counts as `counts`. Engines that make
+    /// room for many words up front only make room for `capacity` instead,
+    /// if given.
+    fn counter_with_capacity<'b>(
+        self,
+        bump: &'b Bump,
+        hash: HashFunction,
+        counts: CountType,
+        capacity: Option<usize>,
+    ) -> Box<dyn WordCounter + 'b> {
+        match counts {
+            CountType::U32 => self.counter_of::<u32>(bump, hash, capacity),
+            CountType::U64 => self.counter_of::<u64>(bump, hash, capacity),
+            CountType::SaturatingU32 => self.counter_of::<Saturating<u32>>(bump, hash, capacity),
+            CountType::SaturatingU64 => self.counter_of::<Saturating<u64>>(bump, hash, capacity),
+        }
+    }
+
+    fn counter_of<'b, N: Count>(
+        self,
+        bump: &'b Bump,
+        hash: HashFunction,
+        capacity: Option<usize>,
+    ) -> Box<dyn WordCounter + 'b> {
+        match self {
+            Engine::Simple => Box::new(Simple::<N>::default()),
+            Engine::Optimized => Box::new(Optimized::<N>::default()),
+            Engine::UnsafeArena => Box::new(UnsafeArena::<N>::default()),
+            Engine::Trie => Box::new(TrieCounter::<N>::default()),
+            Engine::RadixTrie => Box::new(RadixTrieCounter::<N>::default()),
+            Engine::CustomTable => match hash {
+                HashFunction::Fnv1 => table_counter::<_, N>(Fnv1::default(), capacity),
+                HashFunction::Fnv1a => table_counter::<_, N>(Fnv1a::default(), capacity),
+                HashFunction::Fx => table_counter::<_, N>(Fx::default(), capacity),
+                HashFunction::SipHash => table_counter::<_, N>(SipHash::new(), capacity),
+            },
+            Engine::BonusUnicode => Box::new(Unicode::<N>::default()),
+            Engine::FastSimple => Box::new(match capacity {
+                Some(capacity) => FastSimple::<N>::with_capacity(capacity),
+                None => FastSimple::default(),
+            }),
+            Engine::Threads => Box::new(match capacity {
+                Some(capacity) => Threads::<N>::with_capacity(capacity),
+                None => Threads::default(),
+            }),
+            Engine::Bumpalo => Box::new(Bumpalo::<N>::with_counts(bump)),
+            Engine::WellFasterThanC => Box::new(match capacity {
+                Some(capacity) => WellFasterThanC::<N>::with_capacity(capacity),
+                None => WellFasterThanC::default(),
+            }),
+        }
+    }
+
+    /// Returns the engine to count each document with for `PerDocument`,
+    /// which creates a counter for every document and drops it once the
+    /// document has been folded into the totals.
+    ///
+    /// That's this engine, except for `Engine::Bumpalo`, whose counters
+    /// allocate in an arena that is only freed once all counting is done, so
+    /// that memory would grow with every document. Its documents are counted
+    /// by `Engine::Threads` instead, which splits and lowercases words the
+    /// same way.
+    pub fn per_document(self) -> Engine {
+        match self {
+            Engine::Bumpalo => Engine::Threads,
+            engine => engine,
+        }
+    }
+
+    /// Picks an engine for an input of the given size (if known) that starts
+    /// with `sample`.
+    ///
+    /// Inputs that are known to be smaller than 64 MiB and that look like
+    /// UTF-8 get `Engine::Bumpalo`, which was the fastest in our benchmarks.
+    /// Everything else gets the runner-up, `Engine::WellFasterThanC`, which
+    /// copes with any bytes at all. Both lowercase with ASCII rules, but
+    /// `Engine::Bumpalo` splits words on all ASCII whitespace rather than just
+    /// spaces and newlines, so the same text would be counted differently on
+    /// either side of the threshold. Wrap the counter for the engine picked in
+    /// `AsciiWhitespace` to count it the same way whichever it is.
+    pub fn auto(size: Option<u64>, sample: &[u8]) -> Engine {
+        let is_utf8 = match std::str::from_utf8(sample) {
+            Ok(_) => true,
+            // the sample may end in the middle of a codepoint
+            Err(err) => err.error_len().is_none(),
+        };
+        match size {
+            Some(size) if is_utf8 && size < AUTO_UTF8_MAX_SIZE => Engine::Bumpalo,
+            _ => Engine::WellFasterThanC,
+        }
+    }
+}
+
+/// Returns a `TableCounter` that hashes words with hashers built by
+/// `hash_builder`, with room for `capacity` distinct words if given.
+fn table_counter<S, N>(hash_builder: S, capacity: Option<usize>) -> Box<dyn WordCounter>
+where
+    S: BuildHasher + Clone + 'static,
+    N: Count,
+{
+    match capacity {
+        Some(capacity) => Box::new(TableCounter::<S, N>::with_capacity_and_counts(
+            capacity,
+            hash_builder,
+        )),
+        None => Box::new(TableCounter::<S, N>::with_counts(hash_builder)),
+    }
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The error returned when parsing an unrecognized engine name.
+#[derive(Clone, Debug)]
+pub struct UnknownEngine(String);
+
+impl fmt::Display for UnknownEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown engine '{}', expected one of: ", self.0)?;
+        for (i, engine) in Engine::ALL.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(engine.name())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnknownEngine {}
+
+impl FromStr for Engine {
+    type Err = UnknownEngine;
+
+    fn from_str(s: &str) -> Result<Engine, UnknownEngine> {
+        Engine::ALL
+            .iter()
+            .copied()
+            .find(|engine| engine.name() == s)
+            .ok_or_else(|| UnknownEngine(s.to_string()))
+    }
+}
+
+/// Splits the words that the counter it wraps counts on every ASCII whitespace
+/// byte, as `Engine::Bumpalo` does, even if the counter itself only splits
+/// them on spaces and newlines. Tabs, carriage returns and form feeds are
+/// replaced by spaces before the counter sees them.
+#[derive(Debug)]
+pub struct AsciiWhitespace<C> {
+    counter: C,
+    /// The current chunk, with its other whitespace replaced.
+    buf: Vec<u8>,
+}
+
+impl<C: WordCounter> AsciiWhitespace<C> {
+    /// Wrap the given counter.
+    pub fn new(counter: C) -> AsciiWhitespace<C> {
+        AsciiWhitespace {
+            counter,
+            buf: vec![],
+        }
+    }
+}
+
+impl<C: WordCounter> Feed for AsciiWhitespace<C> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.buf.clear();
+        self.buf.extend(chunk.iter().map(|&b| match b {
+            b'\t' | b'\r' | b'\x0C' => b' ',
+            b => b,
+        }));
+        self.counter.feed(&self.buf)
+    }
+
+    fn end_input(&mut self) -> io::Result<()> {
+        self.counter.end_input()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.counter.finish()
+    }
+}
+
+impl<C: WordCounter> WordCounter for AsciiWhitespace<C> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        self.counter.for_each_seen(f)
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(&[u8], u64)) {
+        self.counter.for_each(f)
+    }
+
+    fn len(&self) -> usize {
+        self.counter.len()
+    }
+
+    fn is_ordered(&self) -> bool {
+        self.counter.is_ordered()
+    }
+}
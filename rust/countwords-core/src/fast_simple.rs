@@ -0,0 +1,119 @@
+// This version is an approximate port of the optimized Go program. Its buffer
+// handling is slightly simpler: we don't bother with dealing with the last
+// newline character. (This may appear to save work, but it only saves work
+// once per 64KB buffer, so is likely negligible. It's just simpler IMO.)
+//
+// Update, RBS 05/30/2025: Meat of the changes made are about trying to do something similar to
+// the optimized version, which feels like readable, relatively understandable/simple, idiomatic Rust
+// (nothing too galaxy brained).  This has, surprisingly, turned out to be much faster than the optimized
+// version on MacOS/M1 and similar in performance to the optimized version on the x86_64/Linux
+
+use std::io;
+
+// Update, RBS 07/26/2022: Since Rust 1.36, hashbrown is the new hashmap impl of the
+// stdlib, but this crate includes an additional method, insert_unique_unchecked(),
+// which allows us to avoid duplicating hashmap lookups, while avoiding the
+// additional alloc of entry().  Moreover, ahash is the hash function of hashbrown,
+// which is slightly slower than fxhash when used with the stdlib hashmap, but which
+// is slightly faster as used here.
+//
+// Words are stored in a `KeyMap`, which is built on hashbrown's `HashTable`.
+// It keeps short words in the map itself rather than boxing each one, and
+// looks each word up only once.
+use crate::{key::KeyMap, lines::LineBuffer, Count, Feed, WordCounter};
+
+// set hashmap capacity to >= unique words, so we don't allocate again
+pub(crate) const HASHMAP_INITIAL_CAPACITY: usize = 32_768;
+
+/// Counts runs of ASCII letters, lowercased with ASCII rules, in a `KeyMap`.
+/// Every other byte separates words.
+#[derive(Clone, Debug)]
+pub struct FastSimple<N = u64> {
+    /// Each word's count and the order in which it was first seen.
+    counts: KeyMap<(N, usize)>,
+    lines: LineBuffer,
+}
+
+impl<N: Count> Default for FastSimple<N> {
+    fn default() -> FastSimple<N> {
+        FastSimple::with_capacity(HASHMAP_INITIAL_CAPACITY)
+    }
+}
+
+impl FastSimple {
+    /// Create a new empty counter with u64 counts.
+    pub fn new() -> FastSimple {
+        FastSimple::default()
+    }
+}
+
+impl<N: Count> FastSimple<N> {
+    /// Create a new empty counter that can hold at least `capacity` distinct
+    /// words before its map grows.
+    pub fn with_capacity(capacity: usize) -> FastSimple<N> {
+        FastSimple {
+            counts: KeyMap::with_capacity(capacity),
+            lines: LineBuffer::default(),
+        }
+    }
+}
+
+impl<N: Count> Feed for FastSimple<N> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let counts = &mut self.counts;
+        self.lines
+            .feed(chunk, |bytes_buffer| count_words(counts, bytes_buffer))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let counts = &mut self.counts;
+        self.lines
+            .finish(|bytes_buffer| count_words(counts, bytes_buffer))
+    }
+}
+
+impl<N: Count> WordCounter for FastSimple<N> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        for (word, &(count, seen)) in self.counts.iter() {
+            f(word, count.get(), seen);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.counts.len()
+    }
+}
+
+fn count_words<N: Count>(
+    counts: &mut KeyMap<(N, usize)>,
+    bytes_buffer: &mut [u8],
+) -> io::Result<()> {
+    words(bytes_buffer).try_for_each(|word| increment(counts, word))
+}
+
+/// Lowercases the given buffer and returns the words in it, i.e., its runs of
+/// ASCII letters.
+pub(crate) fn words(bytes_buffer: &mut [u8]) -> impl Iterator<Item = &str> {
+    // to_ascii_lowercase on str requires a call to as_bytes(), so we use directly
+    // on bytes here, but there doesn't seem to be a perf advantage
+    bytes_buffer.make_ascii_lowercase();
+
+    bytes_buffer
+        .split(|byte| !byte.is_ascii_alphabetic())
+        .filter(|bytes| !bytes.is_empty())
+        // SAFETY: every byte in 'bytes' is ASCII alphabetic
+        .map(|bytes| unsafe { std::str::from_utf8_unchecked(bytes) })
+}
+
+fn increment<N: Count>(counts: &mut KeyMap<(N, usize)>, word: &str) -> io::Result<()> {
+    // In the exceptionally common case (we see a word we've already seen),
+    // this is one hash lookup without any allocs. A new word is only copied
+    // into its key, and the map only allocates when the word is longer than
+    // a key holds.
+    let seen = counts.len();
+    counts
+        .get_or_insert_with(word.as_bytes(), || (N::default(), seen))
+        .0
+        .increment()?;
+    Ok(())
+}
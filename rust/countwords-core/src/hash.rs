@@ -0,0 +1,147 @@
+// The custom table's big trick is hashing each word in the same pass that
+// finds where it ends, so its hash function has to take one byte at a time.
+// std's `Hasher::write_u8` is exactly that, so any `BuildHasher` will do, and
+// the ones here are just the ones worth choosing between.
+//
+// FNV-1 is what the C program uses, and it's still the default. It's fast, but
+// it's also trivial to find words that collide under it, and every collision
+// lengthens a probe sequence in the table. Someone feeding us text on purpose
+// can make counting quadratic. SipHash with a random key is the cure, at the
+// cost of some speed: without the key, there's no knowing which words collide.
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasherDefault, Hasher},
+    str::FromStr,
+};
+
+use fxhash::FxHasher64;
+
+use crate::UnknownName;
+
+const FNV_OFFSET: u64 = 14695981039346656037;
+const FNV_PRIME: u64 = 1099511628211;
+
+/// Builds `Fnv1Hasher`s.
+pub type Fnv1 = BuildHasherDefault<Fnv1Hasher>;
+/// Builds `Fnv1aHasher`s.
+pub type Fnv1a = BuildHasherDefault<Fnv1aHasher>;
+/// Builds the hashers rustc uses, which mix in each byte with a rotate, an
+/// xor and a multiply.
+pub type Fx = BuildHasherDefault<FxHasher64>;
+/// Builds SipHash-1-3 hashers keyed with a random key. Each new `SipHash`
+/// has its own key.
+pub type SipHash = RandomState;
+
+/// The FNV-1 hash, which multiplies before mixing in each byte.
+#[derive(Clone, Copy, Debug)]
+pub struct Fnv1Hasher(u64);
+
+impl Default for Fnv1Hasher {
+    fn default() -> Fnv1Hasher {
+        Fnv1Hasher(FNV_OFFSET)
+    }
+}
+
+impl Hasher for Fnv1Hasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u8(b);
+        }
+    }
+
+    #[inline]
+    fn write_u8(&mut self, b: u8) {
+        self.0 = self.0.wrapping_mul(FNV_PRIME);
+        self.0 ^= b as u64;
+    }
+}
+
+/// The FNV-1a hash, which mixes in each byte before multiplying. This spreads
+/// the last byte of a word over every bit, where FNV-1 only touches the lowest
+/// eight.
+#[derive(Clone, Copy, Debug)]
+pub struct Fnv1aHasher(u64);
+
+impl Default for Fnv1aHasher {
+    fn default() -> Fnv1aHasher {
+        Fnv1aHasher(FNV_OFFSET)
+    }
+}
+
+impl Hasher for Fnv1aHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u8(b);
+        }
+    }
+
+    #[inline]
+    fn write_u8(&mut self, b: u8) {
+        self.0 ^= b as u64;
+        self.0 = self.0.wrapping_mul(FNV_PRIME);
+    }
+}
+
+/// A hash function to count words with, chosen at runtime.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum HashFunction {
+    /// `Fnv1`, which is what the C program uses.
+    #[default]
+    Fnv1,
+    /// `Fnv1a`.
+    Fnv1a,
+    /// `Fx`, the fastest of them.
+    Fx,
+    /// `SipHash` with a random key, which resists input crafted to collide.
+    SipHash,
+}
+
+impl HashFunction {
+    /// Every hash function, in the order they're listed in help output.
+    pub const ALL: [HashFunction; 4] = [
+        HashFunction::Fnv1,
+        HashFunction::Fnv1a,
+        HashFunction::Fx,
+        HashFunction::SipHash,
+    ];
+
+    /// Returns the name of this hash function, as accepted by
+    /// `HashFunction::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            HashFunction::Fnv1 => "fnv1",
+            HashFunction::Fnv1a => "fnv1a",
+            HashFunction::Fx => "fx",
+            HashFunction::SipHash => "siphash",
+        }
+    }
+}
+
+impl fmt::Display for HashFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashFunction {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<HashFunction, UnknownName> {
+        let names = HashFunction::ALL.map(HashFunction::name);
+        HashFunction::ALL
+            .iter()
+            .copied()
+            .find(|hash| hash.name() == s)
+            .ok_or_else(|| UnknownName::new("hash function", s, &names))
+    }
+}
@@ -0,0 +1,222 @@
+// HyperLogLog (Flajolet, Fusy, Gandouet and Meunier, 2007) estimates how many
+// distinct words there are in a fixed amount of memory, which is the one thing
+// every other strategy here needs memory proportional to the answer for.
+//
+// A uniformly random hash starts with k zero bits with probability 2^-(k+1),
+// so the longest run of leading zeros seen says something about how many
+// distinct hashes there were. One such observation is far too noisy, so the
+// first p bits of each hash pick one of m = 2^p registers instead, each of
+// which keeps the longest run seen among the rest of the bits, and the
+// estimate comes from the harmonic mean of all of them. Its relative standard
+// error is about 1.04/sqrt(m). For small counts, when many registers are
+// still zero, linear counting on the number of empty registers is more
+// accurate and is used instead. Hashes are 64 bits wide, so there's no need
+// for the large range correction of the original paper.
+//
+// Words are split exactly like optimized-customhashmap does it, and the FNV
+// hash it computes in the same pass is scrambled before use, since FNV's high
+// bits (which pick the register) barely depend on a word's last few bytes.
+//
+// Taking the larger of two sketches' registers gives the sketch of both
+// inputs, so sketches built on shards can be merged.
+
+use std::io::{self, Read, Write};
+
+use crate::{
+    sketch::read_u32,
+    table::{mix, FnvWords},
+    Feed,
+};
+
+/// Identifies a serialized sketch.
+const MAGIC: &[u8; 8] = b"cwhll\0\0\0";
+/// The version of the format written by `HyperLogLog::write_to`.
+const VERSION: u32 = 1;
+
+/// Estimates the number of distinct words in a fixed amount of memory. Words
+/// are split and lowercased exactly like `TableCounter`.
+#[derive(Clone, Debug)]
+pub struct HyperLogLog {
+    registers: Registers,
+    words: FnvWords,
+}
+
+/// The registers of a `HyperLogLog`.
+#[derive(Clone, Debug)]
+struct Registers {
+    precision: u8,
+    /// The longest run of leading zeros (plus one) seen among the hashes
+    /// routed to each register.
+    ranks: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// The smallest precision accepted by `HyperLogLog::new`.
+    pub const MIN_PRECISION: u8 = 4;
+    /// The largest precision accepted by `HyperLogLog::new`.
+    pub const MAX_PRECISION: u8 = 18;
+
+    /// Create a new empty sketch with `2^precision` registers of one byte
+    /// each.
+    ///
+    /// # Panics
+    ///
+    /// If `precision` isn't between `HyperLogLog::MIN_PRECISION` and
+    /// `HyperLogLog::MAX_PRECISION`.
+    pub fn new(precision: u8) -> HyperLogLog {
+        assert!(
+            (HyperLogLog::MIN_PRECISION..=HyperLogLog::MAX_PRECISION).contains(&precision),
+            "precision must be in {}..={}",
+            HyperLogLog::MIN_PRECISION,
+            HyperLogLog::MAX_PRECISION,
+        );
+        HyperLogLog {
+            registers: Registers {
+                precision,
+                ranks: vec![0; 1 << precision],
+            },
+            words: FnvWords::new(),
+        }
+    }
+
+    /// Returns the precision, i.e., the base 2 logarithm of the number of
+    /// registers.
+    pub fn precision(&self) -> u8 {
+        self.registers.precision
+    }
+
+    /// Returns the estimated number of distinct words counted.
+    pub fn estimate(&self) -> f64 {
+        self.registers.estimate()
+    }
+
+    /// Returns the relative standard error of `HyperLogLog::estimate`, which
+    /// depends only on the precision. About 68% of estimates are within this
+    /// fraction of the true number, and 95% within twice that.
+    pub fn standard_error(&self) -> f64 {
+        1.04 / (self.registers.ranks.len() as f64).sqrt()
+    }
+
+    /// Adds the words in `other` to this sketch, so that it estimates the
+    /// number of distinct words in both inputs together.
+    ///
+    /// Both sketches must have the same precision.
+    pub fn merge(&mut self, other: &HyperLogLog) -> io::Result<()> {
+        let (registers, other) = (&mut self.registers, &other.registers);
+        if registers.precision != other.precision {
+            let msg = format!(
+                "can't merge a sketch with precision {} into one with precision {}",
+                other.precision, registers.precision
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        for (rank, &other) in registers.ranks.iter_mut().zip(other.ranks.iter()) {
+            *rank = (*rank).max(other);
+        }
+        Ok(())
+    }
+
+    /// Writes this sketch in a versioned binary format that
+    /// `HyperLogLog::read_from` reads back. All integers are little endian.
+    pub fn write_to<W: Write>(&self, wtr: W) -> io::Result<()> {
+        let registers = &self.registers;
+        let mut wtr = io::BufWriter::new(wtr);
+        wtr.write_all(MAGIC)?;
+        wtr.write_all(&VERSION.to_le_bytes())?;
+        wtr.write_all(&u32::from(registers.precision).to_le_bytes())?;
+        wtr.write_all(&registers.ranks)?;
+        wtr.flush()
+    }
+
+    /// Reads a sketch written by `HyperLogLog::write_to`.
+    pub fn read_from<R: Read>(rdr: R) -> io::Result<HyperLogLog> {
+        let mut rdr = io::BufReader::new(rdr);
+        let mut magic = [0; 8];
+        rdr.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_sketch("not a HyperLogLog sketch"));
+        }
+        let version = read_u32(&mut rdr)?;
+        if version != VERSION {
+            let msg = format!("unsupported sketch version {}", version);
+            return Err(invalid_sketch(&msg));
+        }
+        let precision = match u8::try_from(read_u32(&mut rdr)?) {
+            Ok(p) if (HyperLogLog::MIN_PRECISION..=HyperLogLog::MAX_PRECISION).contains(&p) => p,
+            _ => return Err(invalid_sketch("invalid sketch precision")),
+        };
+        let mut hll = HyperLogLog::new(precision);
+        let registers = &mut hll.registers;
+        rdr.read_exact(&mut registers.ranks)?;
+        let max_rank = 64 - precision + 1;
+        if registers.ranks.iter().any(|&rank| rank > max_rank) {
+            return Err(invalid_sketch("invalid register in sketch"));
+        }
+        if rdr.read(&mut [0])? != 0 {
+            return Err(invalid_sketch("trailing data after sketch"));
+        }
+        Ok(hll)
+    }
+}
+
+impl Registers {
+    /// Records the word with the given hash.
+    fn add(&mut self, hash: u64) {
+        let (index, rank) = self.locate(hash);
+        if self.ranks[index] < rank {
+            self.ranks[index] = rank;
+        }
+    }
+
+    /// Returns the register the word with the given hash goes to, and its
+    /// rank there.
+    fn locate(&self, hash: u64) -> (usize, u8) {
+        let p = u32::from(self.precision);
+        let x = mix(hash);
+        let index = (x >> (64 - p)) as usize;
+        // The bits left after the index. The sentinel bit caps the run of
+        // zeros when all of them are zero.
+        let rest = (x << p) | (1 << (p - 1));
+        (index, rest.leading_zeros() as u8 + 1)
+    }
+
+    fn estimate(&self) -> f64 {
+        let m = self.ranks.len() as f64;
+        let alpha = match self.ranks.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self
+            .ranks
+            .iter()
+            .map(|&rank| 2f64.powi(-i32::from(rank)))
+            .sum();
+        let raw = alpha * m * m / sum;
+        let zeros = self.ranks.iter().filter(|&&rank| rank == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+}
+
+impl Feed for HyperLogLog {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let registers = &mut self.registers;
+        self.words.feed(chunk, |_, hash| registers.add(hash));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let registers = &mut self.registers;
+        self.words.finish(|_, hash| registers.add(hash));
+        Ok(())
+    }
+}
+
+fn invalid_sketch(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
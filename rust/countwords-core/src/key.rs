@@ -0,0 +1,233 @@
+// Most distinct words are short, so storing each one in its own heap
+// allocation spends more on the allocator (and its per-allocation overhead)
+// than on the word itself. A `Key` is the size of a `Vec<u8>`, and holds a
+// word of up to 22 bytes in place. A longer word spills to an `Arena`, one
+// growable buffer shared by every key of a map, which the key then refers to
+// by offset. So counting a new vocabulary mostly doesn't allocate at all, and
+// when it does, it's to grow one buffer.
+//
+// Since a spilled key is only an offset, it can't be hashed or compared on its
+// own. `KeyMap` pairs keys with the arena they spilled to in a hashbrown
+// `HashTable`, which takes the hashing and comparing from the caller.
+
+use std::hash::BuildHasher;
+
+use hashbrown::{hash_table, DefaultHashBuilder, HashTable};
+
+/// The longest word stored in a `Key` itself.
+pub const INLINE_LEN: usize = 22;
+
+/// A word stored either in place, if it's at most `INLINE_LEN` bytes long, or
+/// in an `Arena`.
+///
+/// A key doesn't know which arena it spilled to, so it must always be read
+/// with `Arena::get` on the same one. Reading it with another arena returns
+/// the wrong bytes, or panics.
+#[derive(Clone, Copy, Debug)]
+pub struct Key(Repr);
+
+#[derive(Clone, Copy, Debug)]
+enum Repr {
+    Inline { len: u8, bytes: [u8; INLINE_LEN] },
+    Spilled { start: usize, len: usize },
+}
+
+// A key is no bigger than a `Vec<u8>` (or a `String`), and an empty slot for
+// one is free.
+const _: () = assert!(std::mem::size_of::<Key>() == 24);
+const _: () = assert!(std::mem::size_of::<Option<Key>>() == 24);
+
+impl Key {
+    /// Returns a key holding `word` in place, if it's short enough.
+    pub fn inline(word: &[u8]) -> Option<Key> {
+        if word.len() > INLINE_LEN {
+            return None;
+        }
+        let mut bytes = [0; INLINE_LEN];
+        bytes[..word.len()].copy_from_slice(word);
+        Some(Key(Repr::Inline {
+            len: word.len() as u8,
+            bytes,
+        }))
+    }
+
+    /// Returns the length of this key's word.
+    pub fn len(&self) -> usize {
+        match self.0 {
+            Repr::Inline { len, .. } => len as usize,
+            Repr::Spilled { len, .. } => len,
+        }
+    }
+
+    /// Returns true if this key's word is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if this key's word is stored in place rather than in an
+    /// arena.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+}
+
+/// The bytes of the words too long to be stored in their `Key`.
+#[derive(Clone, Debug, Default)]
+pub struct Arena {
+    bytes: Vec<u8>,
+}
+
+impl Arena {
+    /// Create a new empty arena.
+    pub fn new() -> Arena {
+        Arena::default()
+    }
+
+    /// Returns a key for `word`, copying it into this arena if it's too long
+    /// to be stored in place.
+    pub fn key(&mut self, word: &[u8]) -> Key {
+        if let Some(key) = Key::inline(word) {
+            return key;
+        }
+        let start = self.bytes.len();
+        self.bytes.extend_from_slice(word);
+        Key(Repr::Spilled {
+            start,
+            len: word.len(),
+        })
+    }
+
+    /// Returns the word of `key`, which must have come from this arena.
+    ///
+    /// # Panics
+    ///
+    /// If `key` spilled to an arena with more bytes than this one.
+    pub fn get<'a>(&'a self, key: &'a Key) -> &'a [u8] {
+        match key.0 {
+            Repr::Inline { len, ref bytes } => &bytes[..len as usize],
+            Repr::Spilled { start, len } => &self.bytes[start..start + len],
+        }
+    }
+
+    /// Returns the number of bytes spilled to this arena.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns true if no word has been spilled to this arena.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Removes every word from this arena, which makes every key spilled to
+    /// it invalid.
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+}
+
+/// A hash map from words to values, whose words are stored as `Key`s.
+///
+/// Words can't be removed, since the bytes of a long one would stay in the
+/// arena anyway.
+#[derive(Clone, Debug)]
+pub struct KeyMap<V, S = DefaultHashBuilder> {
+    table: HashTable<(Key, V)>,
+    arena: Arena,
+    hash_builder: S,
+}
+
+impl<V, S: BuildHasher + Default> Default for KeyMap<V, S> {
+    fn default() -> KeyMap<V, S> {
+        KeyMap::with_capacity_and_hasher(0, S::default())
+    }
+}
+
+impl<V> KeyMap<V> {
+    /// Create a new empty map.
+    pub fn new() -> KeyMap<V> {
+        KeyMap::with_capacity(0)
+    }
+
+    /// Create a new empty map that can hold at least `capacity` words before
+    /// it grows.
+    pub fn with_capacity(capacity: usize) -> KeyMap<V> {
+        KeyMap::with_capacity_and_hasher(capacity, DefaultHashBuilder::default())
+    }
+}
+
+impl<V, S: BuildHasher> KeyMap<V, S> {
+    /// Create a new empty map that can hold at least `capacity` words before
+    /// it grows, and that hashes words with `hash_builder`.
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> KeyMap<V, S> {
+        KeyMap {
+            table: HashTable::with_capacity(capacity),
+            arena: Arena::new(),
+            hash_builder,
+        }
+    }
+
+    /// Returns the number of words in this map.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Returns true if there are no words in this map.
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Returns the number of bytes spilled to this map's arena, i.e., the
+    /// total length of its words longer than `INLINE_LEN`.
+    pub fn spilled(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Returns the value of `word`, if it's in this map.
+    pub fn get(&self, word: &[u8]) -> Option<&V> {
+        let hash = self.hash_builder.hash_one(word);
+        let arena = &self.arena;
+        self.table
+            .find(hash, |(key, _)| arena.get(key) == word)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns a mutable reference to the value of `word`, if it's in this
+    /// map.
+    pub fn get_mut(&mut self, word: &[u8]) -> Option<&mut V> {
+        let hash = self.hash_builder.hash_one(word);
+        let arena = &self.arena;
+        self.table
+            .find_mut(hash, |(key, _)| arena.get(key) == word)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns a mutable reference to the value of `word`, adding it to this
+    /// map first with the value returned by `default` if it isn't there.
+    ///
+    /// The word is only copied, and only looked up once, when it's new.
+    pub fn get_or_insert_with(&mut self, word: &[u8], default: impl FnOnce() -> V) -> &mut V {
+        let hash = self.hash_builder.hash_one(word);
+        let (arena, hash_builder) = (&self.arena, &self.hash_builder);
+        let entry = self.table.entry(
+            hash,
+            |(key, _)| arena.get(key) == word,
+            |(key, _)| hash_builder.hash_one(arena.get(key)),
+        );
+        match entry {
+            hash_table::Entry::Occupied(e) => &mut e.into_mut().1,
+            hash_table::Entry::Vacant(e) => {
+                let key = self.arena.key(word);
+                &mut e.insert((key, default())).into_mut().1
+            }
+        }
+    }
+
+    /// Returns an iterator over every word in this map and its value, in no
+    /// particular order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&[u8], &V)> + '_ {
+        self.table
+            .iter()
+            .map(|(key, value)| (self.arena.get(key), value))
+    }
+}
@@ -0,0 +1,197 @@
+// The word counting strategies from each of the Rust variants, pulled out of
+// their binaries so that they can be called as a library. Every strategy
+// implements `WordCounter`, which is fed chunks of bytes in whatever sizes the
+// caller likes. Words may straddle two chunks, so each implementation carries
+// its partial word (or line) over to the next call to `feed`, in the same way
+// the original programs carried the tail of their read buffer over to the next
+// read.
+//
+// The binaries in the sibling directories are thin wrappers: they pick an
+// implementation, hand it stdin via `count` and print `sorted`. The countwords
+// binary does the same, except that it picks one at runtime via `Engine` and
+// prints via `Output`, in whichever `Format` was asked for.
+
+use std::io::{self, Read};
+
+mod bump;
+pub mod chunked;
+mod counts;
+mod documents;
+mod engine;
+mod fast_simple;
+pub mod hash;
+mod hyperloglog;
+pub mod key;
+mod lines;
+mod optimized;
+mod output;
+pub mod pipeline;
+mod radix_trie;
+mod simple;
+mod sketch;
+mod sort;
+mod space_saving;
+mod stopwords;
+pub mod table;
+mod threads;
+mod trie;
+mod unicode;
+mod unsafe_arena;
+mod well_faster_than_c;
+
+pub use crate::{
+    bump::Bumpalo,
+    counts::{Count, CountOverflow, CountType},
+    documents::PerDocument,
+    engine::{AsciiWhitespace, Engine, UnknownEngine},
+    fast_simple::FastSimple,
+    hash::HashFunction,
+    hyperloglog::HyperLogLog,
+    key::KeyMap,
+    optimized::Optimized,
+    output::{Format, InvalidUtf8, Output, UnknownName},
+    pipeline::Pipeline,
+    radix_trie::RadixTrieCounter,
+    simple::Simple,
+    sketch::CountMinSketch,
+    sort::{Select, Sort},
+    space_saving::SpaceSaving,
+    stopwords::StopWords,
+    table::{Table, TableCounter},
+    threads::Threads,
+    trie::{TrieCounter, TrieIndex, TrieWords},
+    unicode::Unicode,
+    unsafe_arena::UnsafeArena,
+    well_faster_than_c::WellFasterThanC,
+};
+
+/// The size of the buffer that `count` reads into.
+const BUFFER_SIZE: usize = 64 * (1 << 10);
+
+/// Something that consumes the words in a stream of bytes, fed to it in
+/// chunks.
+///
+/// What constitutes a word (and whether it is lowercased with ASCII or Unicode
+/// rules) is up to each implementation.
+pub trait Feed {
+    /// Consumes the words in the next chunk of input. A word that isn't
+    /// terminated by the end of the chunk is carried over to the next call.
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()>;
+
+    /// Signals the end of one input when more may follow, e.g., between two
+    /// files. A word carried over from it is counted rather than joined with
+    /// the start of the next input.
+    ///
+    /// By default, this feeds a newline, which ends a word in every
+    /// implementation in this crate.
+    fn end_input(&mut self) -> io::Result<()> {
+        self.feed(b"\n")
+    }
+
+    /// Signals the end of all input, consuming any word still carried over.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// A strategy for counting the frequency of every word in a stream of bytes.
+pub trait WordCounter: Feed {
+    /// Calls `f` with every distinct word, its count and the order in which
+    /// it was first seen, in no particular order. The first distinct word in
+    /// the input was seen at 0, the next one at 1 and so on.
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize));
+
+    /// Calls `f` with every distinct word and its count, in no particular
+    /// order.
+    fn for_each(&self, f: &mut dyn FnMut(&[u8], u64)) {
+        self.for_each_seen(&mut |word, count, _| f(word, count))
+    }
+
+    /// Returns the number of distinct words counted so far.
+    fn len(&self) -> usize;
+
+    /// Returns true if no words have been counted.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if `for_each_seen` (and so `for_each`) passes words in
+    /// byte order, so that alphabetical output needs no sorting.
+    ///
+    /// By default, this is false.
+    fn is_ordered(&self) -> bool {
+        false
+    }
+}
+
+impl<C: Feed + ?Sized> Feed for Box<C> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        (**self).feed(chunk)
+    }
+
+    fn end_input(&mut self) -> io::Result<()> {
+        (**self).end_input()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+}
+
+impl<C: WordCounter + ?Sized> WordCounter for Box<C> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        (**self).for_each_seen(f)
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(&[u8], u64)) {
+        (**self).for_each(f)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn is_ordered(&self) -> bool {
+        (**self).is_ordered()
+    }
+}
+
+/// Feeds everything in `rdr` to `counter` and then finishes it.
+pub fn count<R, C>(rdr: R, counter: &mut C) -> io::Result<()>
+where
+    R: Read,
+    C: Feed + ?Sized,
+{
+    feed_reader(rdr, counter)?;
+    counter.finish()
+}
+
+/// Feeds everything in `rdr` to `counter` as one input, ending it with
+/// `Feed::end_input`. The counter is not finished, so that the next input can
+/// be fed to it.
+pub fn feed_reader<R, C>(mut rdr: R, counter: &mut C) -> io::Result<()>
+where
+    R: Read,
+    C: Feed + ?Sized,
+{
+    let mut buf = vec![0; BUFFER_SIZE];
+    loop {
+        let nread = match rdr.read(&mut buf) {
+            Ok(0) => break,
+            Ok(nread) => nread,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        counter.feed(&buf[..nread])?;
+    }
+    counter.end_input()
+}
+
+/// Returns every word and its count, most frequent first. Words with equal
+/// counts come out in byte order, which is `Sort::Count`.
+pub fn sorted<C: WordCounter + ?Sized>(counter: &C) -> Vec<(Vec<u8>, u64)> {
+    sorted_by(counter, Sort::Count)
+}
+
+/// Returns every word and its count in the given order.
+pub fn sorted_by<C: WordCounter + ?Sized>(counter: &C, sort: Sort) -> Vec<(Vec<u8>, u64)> {
+    Select::new(sort).apply(counter)
+}
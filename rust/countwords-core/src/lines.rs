@@ -0,0 +1,63 @@
+// The strategies derived from fast-simple (and bonus, which reads lines) never
+// look for word boundaries at the end of a buffer. Instead, they make sure a
+// buffer always ends on a line boundary by topping it up with
+// `read_until(b'\n')`. `LineBuffer` does the same for chunks that are pushed
+// to us rather than read by us.
+
+use std::io;
+
+/// Accumulates input until it ends on a line boundary.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LineBuffer {
+    buf: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Appends `chunk` and, if it contains a newline, calls `f` with all
+    /// buffered input up to and including its last newline. Whatever follows
+    /// that newline is kept for next time.
+    ///
+    /// `f` may take ownership of the buffer it is given.
+    pub(crate) fn feed<F>(&mut self, chunk: &[u8], f: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Vec<u8>) -> io::Result<()>,
+    {
+        let end = match chunk.iter().rposition(|&b| b == b'\n') {
+            None => {
+                self.buf.extend_from_slice(chunk);
+                return Ok(());
+            }
+            Some(i) => i + 1,
+        };
+        self.buf.extend_from_slice(&chunk[..end]);
+        let result = f(&mut self.buf);
+        self.buf.clear();
+        self.buf.extend_from_slice(&chunk[end..]);
+        result
+    }
+
+    /// Returns true if nothing is left over.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Calls `f` with whatever is left over, if anything. This is the last
+    /// line of input when it doesn't end with a newline.
+    pub(crate) fn finish<F>(&mut self, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Vec<u8>) -> io::Result<()>,
+    {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let result = f(&mut self.buf);
+        self.buf.clear();
+        result
+    }
+}
+
+/// Converts a UTF-8 decoding error into the I/O error that `BufRead::lines`
+/// would have returned for the same input.
+pub(crate) fn invalid_utf8(err: std::str::Utf8Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
@@ -0,0 +1,648 @@
+// This is the one place that turns counts into bytes, in any of a handful of
+// formats, with a single policy for words that aren't valid UTF-8. Each
+// format escapes words as it needs to. The policy is applied before anything
+// is written, so output is never cut short by a bad word.
+
+use std::{
+    borrow::Cow,
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+/// How counts are written.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Format {
+    /// `word count` lines, as printed by the original programs. There is no
+    /// header, except when there's more than one count per word.
+    #[default]
+    Plain,
+    /// Tab separated values with a header. Tabs, newlines, carriage returns
+    /// and backslashes in words are escaped as `\t`, `\n`, `\r` and `\\`.
+    Tsv,
+    /// Comma separated values with a header. Words are quoted as per RFC 4180
+    /// when they need to be.
+    Csv,
+    /// A JSON array of objects, one per word, keyed by column name.
+    Json,
+    /// One JSON object per line, one per word, keyed by column name.
+    Ndjson,
+    /// A single JSON object mapping each word to its count, or to an object of
+    /// its counts keyed by column name when there's more than one.
+    JsonObject,
+}
+
+impl Format {
+    /// Every format, in the order they're listed in help output.
+    pub const ALL: [Format; 6] = [
+        Format::Plain,
+        Format::Tsv,
+        Format::Csv,
+        Format::Json,
+        Format::Ndjson,
+        Format::JsonObject,
+    ];
+
+    /// Returns the name of this format, as accepted by `Format::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Plain => "plain",
+            Format::Tsv => "tsv",
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::JsonObject => "json-object",
+        }
+    }
+
+    fn is_json(self) -> bool {
+        matches!(self, Format::Json | Format::Ndjson | Format::JsonObject)
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Format, UnknownName> {
+        let names = Format::ALL.map(Format::name);
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| UnknownName::new("format", s, &names))
+    }
+}
+
+/// What to do with words that aren't valid UTF-8.
+///
+/// Only some engines can produce them in the first place: the ones that don't
+/// require their input to be UTF-8 and split words on bytes.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum InvalidUtf8 {
+    /// Write the word's bytes as they are. JSON can't represent them, so JSON
+    /// formats treat this as `InvalidUtf8::Lossy`.
+    #[default]
+    Raw,
+    /// Replace each invalid sequence with U+FFFD. Two words may then look the
+    /// same, which in `Format::JsonObject` means a duplicate key.
+    Lossy,
+    /// Leave the word out.
+    Skip,
+    /// Fail before writing anything.
+    Error,
+}
+
+impl InvalidUtf8 {
+    /// Every policy, in the order they're listed in help output.
+    pub const ALL: [InvalidUtf8; 4] = [
+        InvalidUtf8::Raw,
+        InvalidUtf8::Lossy,
+        InvalidUtf8::Skip,
+        InvalidUtf8::Error,
+    ];
+
+    /// Returns the name of this policy, as accepted by
+    /// `InvalidUtf8::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            InvalidUtf8::Raw => "raw",
+            InvalidUtf8::Lossy => "lossy",
+            InvalidUtf8::Skip => "skip",
+            InvalidUtf8::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for InvalidUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for InvalidUtf8 {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<InvalidUtf8, UnknownName> {
+        let names = InvalidUtf8::ALL.map(InvalidUtf8::name);
+        InvalidUtf8::ALL
+            .iter()
+            .copied()
+            .find(|policy| policy.name() == s)
+            .ok_or_else(|| UnknownName::new("invalid UTF-8 policy", s, &names))
+    }
+}
+
+/// The error returned when parsing an unrecognized name for one of the
+/// options in this crate that are chosen by name, like `Format`.
+#[derive(Clone, Debug)]
+pub struct UnknownName {
+    what: &'static str,
+    name: String,
+    expected: Vec<&'static str>,
+}
+
+impl UnknownName {
+    pub(crate) fn new(what: &'static str, name: &str, expected: &[&'static str]) -> UnknownName {
+        UnknownName {
+            what,
+            name: name.to_string(),
+            expected: expected.to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for UnknownName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown {} '{}', expected one of: {}",
+            self.what,
+            self.name,
+            self.expected.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownName {}
+
+/// Writes counts in a `Format`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Output {
+    format: Format,
+    invalid_utf8: InvalidUtf8,
+}
+
+impl Output {
+    /// Create a writer for the given format, which writes words that aren't
+    /// valid UTF-8 as they are (or lossily, in JSON).
+    pub fn new(format: Format) -> Output {
+        Output {
+            format,
+            invalid_utf8: InvalidUtf8::Raw,
+        }
+    }
+
+    /// Set what to do with words that aren't valid UTF-8.
+    pub fn invalid_utf8(mut self, policy: InvalidUtf8) -> Output {
+        self.invalid_utf8 = policy;
+        self
+    }
+
+    /// Writes each word and its count, in the order given, under the column
+    /// names "word" and "count".
+    pub fn write_counts<W: Write>(&self, wtr: W, counts: &[(Vec<u8>, u64)]) -> io::Result<()> {
+        let rows = counts
+            .iter()
+            .map(|(word, count)| (&word[..], std::slice::from_ref(count)));
+        self.write_table(wtr, &["word", "count"], rows)
+    }
+
+    /// Like `Output::write_counts`, but for the words and counts that
+    /// `for_each` calls its argument with, in that order, so that they don't
+    /// have to be collected first. E.g., `|f| select.for_each(&counter, f)`.
+    ///
+    /// If `InvalidUtf8::Error` is set, `for_each` is called twice: once to
+    /// check every word before anything is written.
+    pub fn write_each<W, F>(&self, wtr: W, for_each: F) -> io::Result<()>
+    where
+        W: Write,
+        F: Fn(&mut dyn FnMut(&[u8], u64)),
+    {
+        if self.invalid_utf8 == InvalidUtf8::Error {
+            let mut result = Ok(());
+            for_each(&mut |word, _| {
+                if result.is_ok() {
+                    result = check_utf8(word);
+                }
+            });
+            result?;
+        }
+        let columns = ["word", "count"];
+        let mut table = Table::start(wtr, self.format, &columns, 0)?;
+        let mut result = Ok(());
+        for_each(&mut |word, count| {
+            if result.is_ok() {
+                if let Some(word) = self.word(word) {
+                    result = table.row(&[&word], std::slice::from_ref(&count));
+                }
+            }
+        });
+        result?;
+        table.finish()
+    }
+
+    /// Writes each word and its counts, in the order given. `columns` names
+    /// the word followed by each count, so every row must have exactly
+    /// `columns.len() - 1` counts.
+    ///
+    /// Nothing is written if `InvalidUtf8::Error` is set and a word isn't
+    /// valid UTF-8.
+    pub fn write_table<'a, W, I>(&self, wtr: W, columns: &[&str], rows: I) -> io::Result<()>
+    where
+        W: Write,
+        I: Iterator<Item = (&'a [u8], &'a [u64])> + Clone,
+    {
+        self.write_rows(wtr, columns, 0, rows)
+    }
+
+    /// Writes each word with its total count followed by its count in each
+    /// of the given files, in the order given.
+    ///
+    /// The columns are "word", "total" and then the name of each file. In
+    /// JSON formats, the counts in each file are instead an array of objects
+    /// under "files", each with the "file" and its "count", since two files
+    /// may well have the same name.
+    pub fn write_per_file<'a, W, I>(&self, wtr: W, files: &[&str], rows: I) -> io::Result<()>
+    where
+        W: Write,
+        I: Iterator<Item = (&'a [u8], &'a [u64])> + Clone,
+    {
+        let mut columns = vec!["word", "total"];
+        columns.extend(files);
+        self.write_rows(wtr, &columns, files.len(), rows)
+    }
+
+    /// Writes a table whose last `files` columns each name a file.
+    fn write_rows<'a, W, I>(
+        &self,
+        wtr: W,
+        columns: &[&str],
+        files: usize,
+        rows: I,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        I: Iterator<Item = (&'a [u8], &'a [u64])> + Clone,
+    {
+        if self.invalid_utf8 == InvalidUtf8::Error {
+            for (word, _) in rows.clone() {
+                check_utf8(word)?;
+            }
+        }
+        let mut table = Table::start(wtr, self.format, columns, files)?;
+        for (word, counts) in rows {
+            debug_assert_eq!(counts.len() + 1, columns.len());
+            if let Some(word) = self.word(word) {
+                table.row(&[&word], counts)?;
+            }
+        }
+        table.finish()
+    }
+
+    /// Writes each word with the surface forms that were counted as it and
+    /// the count of each form, under the column names "stem", "form" and
+    /// "count". Words are written in the order given, each followed by its
+    /// forms in the order given. See `Unicode::forms`.
+    ///
+    /// In `Format::JsonObject`, each word maps to an object of its forms'
+    /// counts. A row is left out if either its word or its form is to be
+    /// left out under the invalid UTF-8 policy.
+    pub fn write_forms<'a, W, I>(&self, wtr: W, forms: I) -> io::Result<()>
+    where
+        W: Write,
+        I: Iterator<Item = (&'a [u8], &'a [(Vec<u8>, u64)])> + Clone,
+    {
+        if self.invalid_utf8 == InvalidUtf8::Error {
+            for (word, forms) in forms.clone() {
+                check_utf8(word)?;
+                for (form, _) in forms {
+                    check_utf8(form)?;
+                }
+            }
+        }
+        let columns = ["stem", "form", "count"];
+        let mut table = Table::start(wtr, self.format, &columns, 0)?;
+        for (word, forms) in forms {
+            let word = match self.word(word) {
+                None => continue,
+                Some(word) => word,
+            };
+            for (form, count) in forms {
+                if let Some(form) = self.word(form) {
+                    table.row(&[&word, &form], std::slice::from_ref(count))?;
+                }
+            }
+        }
+        table.finish()
+    }
+
+    /// Applies the invalid UTF-8 policy to a word, returning None if it should
+    /// be left out.
+    fn word<'w>(&self, word: &'w [u8]) -> Option<Cow<'w, [u8]>> {
+        if std::str::from_utf8(word).is_ok() {
+            return Some(Cow::Borrowed(word));
+        }
+        match self.invalid_utf8 {
+            InvalidUtf8::Raw if !self.format.is_json() => Some(Cow::Borrowed(word)),
+            InvalidUtf8::Skip => None,
+            // Error has already been checked for, so the rest are lossy.
+            _ => match String::from_utf8_lossy(word) {
+                Cow::Borrowed(word) => Some(Cow::Borrowed(word.as_bytes())),
+                Cow::Owned(word) => Some(Cow::Owned(word.into_bytes())),
+            },
+        }
+    }
+}
+
+/// Returns an error if a word isn't valid UTF-8.
+fn check_utf8(word: &[u8]) -> io::Result<()> {
+    if let Err(err) = std::str::from_utf8(word) {
+        let msg = format!(
+            "word '{}' is not valid UTF-8: {}",
+            String::from_utf8_lossy(word),
+            err
+        );
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    Ok(())
+}
+
+/// A table being written in a `Format`, one row at a time.
+///
+/// Each row starts with one or more words, followed by counts. In
+/// `Format::JsonObject`, a row with two words is nested under the first, so
+/// consecutive rows with the same first word are grouped together.
+struct Table<'c, W: Write> {
+    wtr: io::BufWriter<W>,
+    format: Format,
+    columns: &'c [&'c str],
+    /// The number of columns at the end that each name a file.
+    files: usize,
+    /// True until the first row is written.
+    empty: bool,
+    /// In `Format::JsonObject`, the first word of the last row, if rows are
+    /// nested under it.
+    group: Option<Vec<u8>>,
+}
+
+impl<'c, W: Write> Table<'c, W> {
+    /// Writes whatever comes before the rows in the given format.
+    fn start(
+        wtr: W,
+        format: Format,
+        columns: &'c [&'c str],
+        files: usize,
+    ) -> io::Result<Table<'c, W>> {
+        let mut wtr = io::BufWriter::new(wtr);
+        match format {
+            Format::Plain if columns.len() > 2 => writeln!(wtr, "{}", columns.join(" "))?,
+            Format::Plain | Format::Ndjson => {}
+            Format::Tsv => write_header(&mut wtr, b'\t', write_tsv_field, columns)?,
+            Format::Csv => write_header(&mut wtr, b',', write_csv_field, columns)?,
+            Format::Json => wtr.write_all(b"[")?,
+            Format::JsonObject => wtr.write_all(b"{")?,
+        }
+        Ok(Table {
+            wtr,
+            format,
+            columns,
+            files,
+            empty: true,
+            group: None,
+        })
+    }
+
+    /// Writes one row of words, which have already had the invalid UTF-8
+    /// policy applied, and counts.
+    fn row(&mut self, words: &[&[u8]], counts: &[u64]) -> io::Result<()> {
+        debug_assert_eq!(words.len() + counts.len(), self.columns.len());
+        let wtr = &mut self.wtr;
+        match self.format {
+            Format::Plain => {
+                for (i, word) in words.iter().enumerate() {
+                    if i > 0 {
+                        wtr.write_all(b" ")?;
+                    }
+                    wtr.write_all(word)?;
+                }
+                for count in counts {
+                    write!(wtr, " {}", count)?;
+                }
+                wtr.write_all(b"\n")?;
+            }
+            Format::Tsv => write_row(wtr, b'\t', write_tsv_field, words, counts)?,
+            Format::Csv => write_row(wtr, b',', write_csv_field, words, counts)?,
+            Format::Json => {
+                wtr.write_all(if self.empty { b"\n  " } else { b",\n  " })?;
+                write_json_row(wtr, self.columns, self.files, words, counts)?;
+            }
+            Format::Ndjson => {
+                write_json_row(wtr, self.columns, self.files, words, counts)?;
+                wtr.write_all(b"\n")?;
+            }
+            Format::JsonObject => {
+                let key = match *words {
+                    [word] => {
+                        wtr.write_all(if self.empty { b"\n  " } else { b",\n  " })?;
+                        word
+                    }
+                    [group, word] if self.group.as_deref() == Some(group) => {
+                        wtr.write_all(b",\n    ")?;
+                        word
+                    }
+                    [group, word] => {
+                        wtr.write_all(match self.group {
+                            None => b"\n  ",
+                            Some(_) => b"\n  },\n  ",
+                        })?;
+                        write_json_string(wtr, group)?;
+                        wtr.write_all(b": {\n    ")?;
+                        self.group = Some(group.to_vec());
+                        word
+                    }
+                    _ => unreachable!("JSON objects only nest one level"),
+                };
+                write_json_string(wtr, key)?;
+                wtr.write_all(b": ")?;
+                if let ([count], 0) = (counts, self.files) {
+                    write!(wtr, "{}", count)?;
+                } else {
+                    wtr.write_all(b"{")?;
+                    write_json_counts(wtr, &self.columns[words.len()..], self.files, counts)?;
+                    wtr.write_all(b"}")?;
+                }
+            }
+        }
+        self.empty = false;
+        Ok(())
+    }
+
+    /// Writes whatever comes after the rows, and flushes.
+    fn finish(mut self) -> io::Result<()> {
+        match self.format {
+            Format::Plain | Format::Tsv | Format::Csv | Format::Ndjson => {}
+            Format::Json => self
+                .wtr
+                .write_all(if self.empty { b"]\n" } else { b"\n]\n" })?,
+            Format::JsonObject => {
+                if self.group.is_some() {
+                    self.wtr.write_all(b"\n  }")?;
+                }
+                self.wtr
+                    .write_all(if self.empty { b"}\n" } else { b"\n}\n" })?
+            }
+        }
+        self.wtr.flush()
+    }
+}
+
+fn write_header<W: Write>(
+    wtr: &mut W,
+    delimiter: u8,
+    write_field: fn(&mut W, &[u8]) -> io::Result<()>,
+    columns: &[&str],
+) -> io::Result<()> {
+    for (i, column) in columns.iter().enumerate() {
+        if i > 0 {
+            wtr.write_all(&[delimiter])?;
+        }
+        write_field(wtr, column.as_bytes())?;
+    }
+    wtr.write_all(b"\n")
+}
+
+fn write_row<W: Write>(
+    wtr: &mut W,
+    delimiter: u8,
+    write_field: fn(&mut W, &[u8]) -> io::Result<()>,
+    words: &[&[u8]],
+    counts: &[u64],
+) -> io::Result<()> {
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            wtr.write_all(&[delimiter])?;
+        }
+        write_field(wtr, word)?;
+    }
+    for count in counts {
+        wtr.write_all(&[delimiter])?;
+        write!(wtr, "{}", count)?;
+    }
+    wtr.write_all(b"\n")
+}
+
+fn write_tsv_field<W: Write>(wtr: &mut W, field: &[u8]) -> io::Result<()> {
+    let mut last = 0;
+    for (i, &b) in field.iter().enumerate() {
+        let escaped: &[u8] = match b {
+            b'\t' => b"\\t",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\\' => b"\\\\",
+            _ => continue,
+        };
+        wtr.write_all(&field[last..i])?;
+        wtr.write_all(escaped)?;
+        last = i + 1;
+    }
+    wtr.write_all(&field[last..])
+}
+
+fn write_csv_field<W: Write>(wtr: &mut W, field: &[u8]) -> io::Result<()> {
+    let needs_quotes = field
+        .iter()
+        .any(|&b| matches!(b, b',' | b'"' | b'\n' | b'\r'));
+    if !needs_quotes {
+        return wtr.write_all(field);
+    }
+    wtr.write_all(b"\"")?;
+    for (i, part) in field.split(|&b| b == b'"').enumerate() {
+        if i > 0 {
+            wtr.write_all(b"\"\"")?;
+        }
+        wtr.write_all(part)?;
+    }
+    wtr.write_all(b"\"")
+}
+
+/// Writes one row of words and counts as a JSON object.
+fn write_json_row<W: Write>(
+    wtr: &mut W,
+    columns: &[&str],
+    files: usize,
+    words: &[&[u8]],
+    counts: &[u64],
+) -> io::Result<()> {
+    wtr.write_all(b"{")?;
+    for (i, (column, word)) in columns.iter().zip(words).enumerate() {
+        if i > 0 {
+            wtr.write_all(b", ")?;
+        }
+        write_json_string(wtr, column.as_bytes())?;
+        wtr.write_all(b": ")?;
+        write_json_string(wtr, word)?;
+    }
+    if !counts.is_empty() {
+        wtr.write_all(b", ")?;
+        write_json_counts(wtr, &columns[words.len()..], files, counts)?;
+    }
+    wtr.write_all(b"}")
+}
+
+/// Writes counts as the members of a JSON object keyed by column name. The
+/// last `files` columns name files rather than counts, and since names may
+/// repeat, their counts go in an array of objects under "files" instead.
+fn write_json_counts<W: Write>(
+    wtr: &mut W,
+    columns: &[&str],
+    files: usize,
+    counts: &[u64],
+) -> io::Result<()> {
+    let keyed = columns.len() - files;
+    for (i, (column, count)) in columns[..keyed].iter().zip(counts).enumerate() {
+        if i > 0 {
+            wtr.write_all(b", ")?;
+        }
+        write_json_string(wtr, column.as_bytes())?;
+        write!(wtr, ": {}", count)?;
+    }
+    if files == 0 {
+        return Ok(());
+    }
+    if keyed > 0 {
+        wtr.write_all(b", ")?;
+    }
+    wtr.write_all(b"\"files\": [")?;
+    for (i, (file, count)) in columns[keyed..].iter().zip(&counts[keyed..]).enumerate() {
+        if i > 0 {
+            wtr.write_all(b", ")?;
+        }
+        wtr.write_all(b"{\"file\": ")?;
+        write_json_string(wtr, file.as_bytes())?;
+        write!(wtr, ", \"count\": {}}}", count)?;
+    }
+    wtr.write_all(b"]")
+}
+
+/// Writes a quoted JSON string. `s` must be valid UTF-8.
+fn write_json_string<W: Write>(wtr: &mut W, s: &[u8]) -> io::Result<()> {
+    wtr.write_all(b"\"")?;
+    let mut last = 0;
+    for (i, &b) in s.iter().enumerate() {
+        let escaped: &[u8] = match b {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0x00..=0x1F => {
+                wtr.write_all(&s[last..i])?;
+                write!(wtr, "\\u{:04x}", b)?;
+                last = i + 1;
+                continue;
+            }
+            _ => continue,
+        };
+        wtr.write_all(&s[last..i])?;
+        wtr.write_all(escaped)?;
+        last = i + 1;
+    }
+    wtr.write_all(&s[last..])?;
+    wtr.write_all(b"\"")
+}
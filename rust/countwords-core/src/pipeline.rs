@@ -0,0 +1,379 @@
+// Every variant decides what a word is in its own inner loop: bonus segments
+// Unicode words and lowercases them with Unicode rules, while the others split
+// on ASCII whitespace or runs of ASCII letters and lowercase with ASCII rules.
+// That's what makes them fast, but it also means that counting words some
+// other way means writing another counter.
+//
+// A `Pipeline` pulls those decisions out into three stages, each a trait: a
+// `Tokenizer` finds the words in some text, each `Normalizer` in turn rewrites
+// a word, and every `Filter` must keep a word for it to be counted. `Unicode`
+// counts whatever its pipeline yields, so the built-in stages can be mixed and
+// matched, and library users can plug in their own.
+//
+// A pipeline may also end with a stemmer, which is just another normalizer,
+// except that the word it was given is passed along too. That's the surface
+// form, so that the words that were counted as each stem can be reported.
+//
+// Each stage is called through a trait object, which costs a little per word.
+// That's small next to Unicode word segmentation, which is what `Unicode` does
+// by default, but the byte oriented engines keep their hard-coded loops.
+
+use std::{fmt, io, str::FromStr, sync::Arc};
+
+use bstr::ByteSlice;
+
+use crate::UnknownName;
+
+pub use self::stem::{Language, Stemmer};
+
+mod stem;
+
+/// Finds the words in some text.
+pub trait Tokenizer: fmt::Debug + Send + Sync {
+    /// Calls `f` with each word in `text`, in order, and stops at the first
+    /// error it returns.
+    ///
+    /// `text` is always one or more whole lines, so a word never has to be
+    /// carried over from one call to the next.
+    fn tokenize(&self, text: &[u8], f: &mut dyn FnMut(&[u8]) -> io::Result<()>) -> io::Result<()>;
+}
+
+/// Rewrites a word before it's counted, e.g., by lowercasing it.
+pub trait Normalizer: fmt::Debug + Send + Sync {
+    /// Appends the normalized form of `word` to `out`, which is empty.
+    fn normalize(&self, word: &[u8], out: &mut Vec<u8>);
+}
+
+/// Decides whether a normalized word is counted.
+pub trait Filter: fmt::Debug + Send + Sync {
+    /// Returns true if `word` should be counted.
+    fn keep(&self, word: &[u8]) -> bool;
+}
+
+/// Splits text on ASCII whitespace, like the fun-with-threads and
+/// fun-with-bumpalo variants. (well-faster-than-c splits on spaces and
+/// newlines only, unlike either tokenizer.)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Whitespace;
+
+impl Tokenizer for Whitespace {
+    fn tokenize(&self, text: &[u8], f: &mut dyn FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+        for word in text.split(u8::is_ascii_whitespace) {
+            if !word.is_empty() {
+                f(word)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds runs of ASCII letters, so that anything else separates words, like
+/// the fast-simple variant.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsciiAlphabetic;
+
+impl Tokenizer for AsciiAlphabetic {
+    fn tokenize(&self, text: &[u8], f: &mut dyn FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+        for word in text.split(|b| !b.is_ascii_alphabetic()) {
+            if !word.is_empty() {
+                f(word)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds words with the Unicode word segmentation algorithm, like the bonus
+/// variant. Punctuation and whitespace between words are skipped, and invalid
+/// UTF-8 is tolerated.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UnicodeWords;
+
+impl Tokenizer for UnicodeWords {
+    fn tokenize(&self, text: &[u8], f: &mut dyn FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+        for word in text.words() {
+            f(word.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Lowercases ASCII letters and leaves every other byte alone.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsciiLowercase;
+
+impl Normalizer for AsciiLowercase {
+    fn normalize(&self, word: &[u8], out: &mut Vec<u8>) {
+        out.extend(word.iter().map(u8::to_ascii_lowercase));
+    }
+}
+
+/// Folds case by lowercasing with Unicode rules, like the bonus variant.
+/// Invalid UTF-8 is left as it is.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UnicodeLowercase;
+
+impl Normalizer for UnicodeLowercase {
+    fn normalize(&self, word: &[u8], out: &mut Vec<u8>) {
+        // an API not available in std, which lets every word reuse the same
+        // buffer instead of allocating a new string
+        word.to_lowercase_into(out);
+    }
+}
+
+/// Only keeps words that are at least this many bytes long.
+#[derive(Clone, Copy, Debug)]
+pub struct MinLen(pub usize);
+
+impl Filter for MinLen {
+    fn keep(&self, word: &[u8]) -> bool {
+        word.len() >= self.0
+    }
+}
+
+/// Only keeps words that are at most this many bytes long.
+#[derive(Clone, Copy, Debug)]
+pub struct MaxLen(pub usize);
+
+impl Filter for MaxLen {
+    fn keep(&self, word: &[u8]) -> bool {
+        word.len() <= self.0
+    }
+}
+
+/// A built-in tokenizer, chosen at runtime.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Tokenization {
+    /// `UnicodeWords`.
+    #[default]
+    UnicodeWords,
+    /// `Whitespace`.
+    Whitespace,
+    /// `AsciiAlphabetic`.
+    AsciiAlphabetic,
+}
+
+impl Tokenization {
+    /// Every tokenizer, in the order they're listed in help output.
+    pub const ALL: [Tokenization; 3] = [
+        Tokenization::UnicodeWords,
+        Tokenization::Whitespace,
+        Tokenization::AsciiAlphabetic,
+    ];
+
+    /// Returns the name of this tokenizer, as accepted by
+    /// `Tokenization::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Tokenization::UnicodeWords => "unicode-words",
+            Tokenization::Whitespace => "whitespace",
+            Tokenization::AsciiAlphabetic => "ascii-alphabetic",
+        }
+    }
+}
+
+impl Tokenizer for Tokenization {
+    fn tokenize(&self, text: &[u8], f: &mut dyn FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+        match self {
+            Tokenization::UnicodeWords => UnicodeWords.tokenize(text, f),
+            Tokenization::Whitespace => Whitespace.tokenize(text, f),
+            Tokenization::AsciiAlphabetic => AsciiAlphabetic.tokenize(text, f),
+        }
+    }
+}
+
+impl fmt::Display for Tokenization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Tokenization {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Tokenization, UnknownName> {
+        let names = Tokenization::ALL.map(Tokenization::name);
+        Tokenization::ALL
+            .iter()
+            .copied()
+            .find(|tokenizer| tokenizer.name() == s)
+            .ok_or_else(|| UnknownName::new("tokenizer", s, &names))
+    }
+}
+
+/// A built-in normalizer, chosen at runtime.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Normalization {
+    /// `UnicodeLowercase`.
+    #[default]
+    UnicodeLowercase,
+    /// `AsciiLowercase`.
+    AsciiLowercase,
+    /// Leaves words as they are.
+    Unchanged,
+}
+
+impl Normalization {
+    /// Every normalizer, in the order they're listed in help output.
+    pub const ALL: [Normalization; 3] = [
+        Normalization::UnicodeLowercase,
+        Normalization::AsciiLowercase,
+        Normalization::Unchanged,
+    ];
+
+    /// Returns the name of this normalizer, as accepted by
+    /// `Normalization::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Normalization::UnicodeLowercase => "unicode-lowercase",
+            Normalization::AsciiLowercase => "ascii-lowercase",
+            Normalization::Unchanged => "none",
+        }
+    }
+}
+
+impl Normalizer for Normalization {
+    fn normalize(&self, word: &[u8], out: &mut Vec<u8>) {
+        match self {
+            Normalization::UnicodeLowercase => UnicodeLowercase.normalize(word, out),
+            Normalization::AsciiLowercase => AsciiLowercase.normalize(word, out),
+            Normalization::Unchanged => out.extend_from_slice(word),
+        }
+    }
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Normalization {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Normalization, UnknownName> {
+        let names = Normalization::ALL.map(Normalization::name);
+        Normalization::ALL
+            .iter()
+            .copied()
+            .find(|normalizer| normalizer.name() == s)
+            .ok_or_else(|| UnknownName::new("normalizer", s, &names))
+    }
+}
+
+/// A tokenizer, the normalizers applied to each word it finds, in order, the
+/// filters that decide which of the normalized words are counted, and
+/// optionally the stemmer that turns each of those into what's counted.
+///
+/// Cloning a pipeline is cheap, since its stages are shared.
+#[derive(Clone, Debug)]
+pub struct Pipeline {
+    tokenizer: Arc<dyn Tokenizer>,
+    normalizers: Vec<Arc<dyn Normalizer>>,
+    filters: Vec<Arc<dyn Filter>>,
+    stemmer: Option<Arc<dyn Normalizer>>,
+    /// Where the last normalizer's output is.
+    buf: Vec<u8>,
+    /// Where the next normalizer writes, when there's more than one.
+    tmp: Vec<u8>,
+    /// Where the stemmer writes.
+    stem: Vec<u8>,
+}
+
+impl Default for Pipeline {
+    /// The bonus variant's pipeline: Unicode words, lowercased with Unicode
+    /// rules.
+    fn default() -> Pipeline {
+        Pipeline::new(UnicodeWords).normalize(UnicodeLowercase)
+    }
+}
+
+impl Pipeline {
+    /// Create a pipeline that counts the words `tokenizer` finds as they
+    /// are.
+    pub fn new<T: Tokenizer + 'static>(tokenizer: T) -> Pipeline {
+        Pipeline {
+            tokenizer: Arc::new(tokenizer),
+            normalizers: vec![],
+            filters: vec![],
+            stemmer: None,
+            buf: vec![],
+            tmp: vec![],
+            stem: vec![],
+        }
+    }
+
+    /// Add a normalizer, which is applied after the ones added before it.
+    pub fn normalize<N: Normalizer + 'static>(mut self, normalizer: N) -> Pipeline {
+        self.normalizers.push(Arc::new(normalizer));
+        self
+    }
+
+    /// Add a filter. A word is only counted if every filter keeps it.
+    pub fn filter<F: Filter + 'static>(mut self, filter: F) -> Pipeline {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    /// Set the stemmer, which is applied to the words that every filter
+    /// keeps, e.g., `Stemmer::new(Language::English)`. Filters see words
+    /// before they're stemmed, so that, e.g., a list of words to leave out
+    /// doesn't have to be stemmed too.
+    pub fn stem<S: Normalizer + 'static>(mut self, stemmer: S) -> Pipeline {
+        self.stemmer = Some(Arc::new(stemmer));
+        self
+    }
+
+    /// Returns true if this pipeline has a stemmer.
+    pub fn is_stemmed(&self) -> bool {
+        self.stemmer.is_some()
+    }
+
+    /// Calls `f` with each word in `text` that makes it through the
+    /// pipeline, and stops at the first error it returns.
+    ///
+    /// `f` is given the word to count, followed by its surface form: the word
+    /// before it was stemmed. Without a stemmer, the two are the same.
+    ///
+    /// `text` should be one or more whole lines.
+    pub fn for_each_word<F>(&mut self, text: &[u8], mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&[u8], &[u8]) -> io::Result<()>,
+    {
+        let Pipeline {
+            tokenizer,
+            normalizers,
+            filters,
+            stemmer,
+            buf,
+            tmp,
+            stem,
+        } = self;
+        tokenizer.tokenize(text, &mut |token| {
+            let word = match normalizers.split_first() {
+                None => token,
+                Some((first, rest)) => {
+                    buf.clear();
+                    first.normalize(token, buf);
+                    for normalizer in rest {
+                        tmp.clear();
+                        normalizer.normalize(buf, tmp);
+                        std::mem::swap(buf, tmp);
+                    }
+                    &buf[..]
+                }
+            };
+            if !filters.iter().all(|filter| filter.keep(word)) {
+                return Ok(());
+            }
+            match stemmer {
+                None => f(word, word),
+                Some(stemmer) => {
+                    stem.clear();
+                    stemmer.normalize(word, stem);
+                    f(stem, word)
+                }
+            }
+        })
+    }
+}
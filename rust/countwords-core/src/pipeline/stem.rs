@@ -0,0 +1,114 @@
+// Stemming maps the inflections of a word to one stem, so that "bless",
+// "blessed" and "blessing" are counted as one term. The Snowball stemmers do
+// this with a set of suffix stripping rules for each language, which is crude
+// next to a dictionary, but needs no dictionary and is what search engines
+// have long used. The stems aren't always words themselves: "blessed" becomes
+// "bless", but "happy" becomes "happi". Nor do they know archaic forms: the
+// KJV's "blesseth" is left as it is, since stripping every "eth" would make a
+// mess of "teeth" and "Seth".
+//
+// The stemmers expect lowercase words, so a stemmer goes after lowercasing.
+
+use std::{fmt, str::FromStr};
+
+use rust_stemmers::Algorithm;
+
+use super::Normalizer;
+use crate::UnknownName;
+
+/// A language with a built-in stemmer.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Language {
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+impl Language {
+    /// Every language, in the order they're listed in help output.
+    pub const ALL: [Language; 4] = [
+        Language::English,
+        Language::German,
+        Language::French,
+        Language::Spanish,
+    ];
+
+    /// Returns the ISO 639-1 code of this language, as accepted by
+    /// `Language::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Spanish => "es",
+        }
+    }
+
+    fn algorithm(self) -> Algorithm {
+        match self {
+            Language::English => Algorithm::English,
+            Language::German => Algorithm::German,
+            Language::French => Algorithm::French,
+            Language::Spanish => Algorithm::Spanish,
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Language {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Language, UnknownName> {
+        let names = Language::ALL.map(Language::name);
+        Language::ALL
+            .iter()
+            .copied()
+            .find(|language| language.name() == s)
+            .ok_or_else(|| UnknownName::new("language", s, &names))
+    }
+}
+
+/// Stems words with the Snowball stemmer for a language. Words that aren't
+/// valid UTF-8 are left as they are.
+pub struct Stemmer {
+    language: Language,
+    stemmer: rust_stemmers::Stemmer,
+}
+
+impl Stemmer {
+    /// Create a stemmer for the given language.
+    pub fn new(language: Language) -> Stemmer {
+        Stemmer {
+            language,
+            stemmer: rust_stemmers::Stemmer::create(language.algorithm()),
+        }
+    }
+
+    /// Returns the language this stemmer is for.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+}
+
+impl fmt::Debug for Stemmer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stemmer")
+            .field("language", &self.language)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Normalizer for Stemmer {
+    fn normalize(&self, word: &[u8], out: &mut Vec<u8>) {
+        match std::str::from_utf8(word) {
+            Ok(word) => out.extend_from_slice(self.stemmer.stem(word).as_bytes()),
+            Err(_) => out.extend_from_slice(word),
+        }
+    }
+}
@@ -0,0 +1,326 @@
+// The trie in optimized-trie gives every node a slot for each of 128 children,
+// which makes stepping to a child a single load, but costs half a kilobyte per
+// node. With one node per byte of every distinct word (less shared prefixes),
+// a large vocabulary takes gigabytes, nearly all of it empty slots.
+//
+// This one is a radix trie instead: a node that would have had only one child
+// is merged with it, so each node has a label of any number of bytes, and
+// there's only a node where words branch or end. Children are kept in a list
+// of siblings rather than a table, and each node's label is a slice of one
+// buffer shared by the whole trie, so a node is 28 bytes however long its
+// label. The root is the exception, with a table of all 256 children, since
+// every word starts there and it has the most of them.
+//
+// Words are still counted as they're scanned, without finding their ends
+// first: the position in the trie is a node and how much of its label has
+// matched so far. A byte that doesn't match splits the node in two. A byte
+// that doesn't match any child starts a new leaf, and since the leaf's label
+// is always at the end of the shared buffer, the rest of the word is appended
+// to it in place.
+
+use std::{convert::TryFrom, io};
+
+use crate::{Count, Feed, WordCounter};
+
+/// The root's ID. No node has it as a child or sibling, so it doubles as
+/// "none" in those.
+const ROOT: u32 = 0;
+
+/// Counts words exactly like `TrieCounter`, but in a radix trie, which takes
+/// a small fraction of the memory.
+///
+/// It's slower than `TrieCounter` when there are few distinct words, but
+/// faster when there are many, since then the dense trie no longer fits in
+/// any cache. See `benches/trie.rs`.
+///
+/// Counts are u32 by default, which keeps a node at 28 bytes. With u64
+/// counts, it's 32. Labels are found by u32 offsets, so feeding fails once
+/// they would take more than 4 GiB.
+#[derive(Clone, Debug)]
+pub struct RadixTrieCounter<N = u32> {
+    trie: RadixTrie<N>,
+    /// The node reached by the incomplete word at the end of the last chunk.
+    node: u32,
+    /// How many bytes of 'node's label the incomplete word has matched.
+    matched: u32,
+    /// Whether 'node' is a new leaf whose label is the rest of the incomplete
+    /// word, and so can be extended in place.
+    growing: bool,
+    /// The number of distinct words, i.e., nodes with a non-zero count.
+    len: usize,
+}
+
+impl<N: Count> Default for RadixTrieCounter<N> {
+    fn default() -> RadixTrieCounter<N> {
+        RadixTrieCounter {
+            trie: RadixTrie::new(),
+            node: ROOT,
+            matched: 0,
+            growing: false,
+            len: 0,
+        }
+    }
+}
+
+impl RadixTrieCounter {
+    /// Create a new empty counter with u32 counts.
+    pub fn new() -> RadixTrieCounter {
+        RadixTrieCounter::default()
+    }
+}
+
+impl<N: Count> RadixTrieCounter<N> {
+    /// Returns the number of bytes of memory allocated by this counter, not
+    /// counting itself.
+    pub fn memory_usage(&self) -> usize {
+        let trie = &self.trie;
+        trie.nodes.capacity() * std::mem::size_of::<Node<N>>()
+            + trie.labels.capacity()
+            + std::mem::size_of_val(&*trie.root)
+    }
+
+    /// Advances the incomplete word by one byte. Fails if a new byte of label
+    /// is needed and the labels are full.
+    fn step(&mut self, b: u8) -> io::Result<()> {
+        let trie = &mut self.trie;
+        if self.growing {
+            trie.push_label(b)?;
+            trie.nodes[self.node as usize].len += 1;
+            self.matched += 1;
+            return Ok(());
+        }
+        let node = trie.nodes[self.node as usize];
+        if self.matched < node.len {
+            if trie.labels[(node.start + self.matched) as usize] == b {
+                self.matched += 1;
+                return Ok(());
+            }
+            trie.split(self.node, self.matched);
+        }
+        match trie.child(self.node, b) {
+            Ok(child) => self.node = child,
+            Err(last) => {
+                self.node = trie.add_leaf(self.node, last, b)?;
+                self.growing = true;
+            }
+        }
+        self.matched = 1;
+        Ok(())
+    }
+
+    /// Counts the incomplete word, which must not be empty.
+    fn increment(&mut self) -> io::Result<()> {
+        let trie = &mut self.trie;
+        if self.matched < trie.nodes[self.node as usize].len {
+            trie.split(self.node, self.matched);
+        }
+        let node = &mut trie.nodes[self.node as usize];
+        self.node = ROOT;
+        self.matched = 0;
+        self.growing = false;
+        node.count.increment()?;
+        if node.count.get() == 1 {
+            // There are fewer words than nodes, and node IDs fit in a u32.
+            node.seen = self.len as u32;
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<N: Count> Feed for RadixTrieCounter<N> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        for &b in chunk {
+            if b == b' ' || b == b'\n' || b == b'\r' {
+                if self.node != ROOT {
+                    self.increment()?;
+                }
+            } else {
+                self.step(b.to_ascii_lowercase())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if self.node != ROOT {
+            self.increment()?;
+        }
+        Ok(())
+    }
+}
+
+impl<N: Count> WordCounter for RadixTrieCounter<N> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        self.trie
+            .for_each(&mut |word, count, seen| f(word, count.get(), seen as usize));
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RadixTrie<N> {
+    /// Every node, indexed by ID. The root is first.
+    nodes: Vec<Node<N>>,
+    /// The labels of every node, back to back.
+    labels: Vec<u8>,
+    /// The children of the root, by the first byte of their label, or ROOT
+    /// for none.
+    root: Box<[u32; 256]>,
+}
+
+// A node is 28 bytes with u32 counts, since the label's first byte is
+// padded, and 32 with u64 counts.
+const _: () = assert!(std::mem::size_of::<Node<u32>>() == 28);
+const _: () = assert!(std::mem::size_of::<Node<u64>>() == 32);
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Node<N> {
+    /// Where this node's label starts in 'labels'.
+    start: u32,
+    /// The length of this node's label, which is only 0 for the root.
+    len: u32,
+    /// The first byte of this node's label, so that finding a child doesn't
+    /// need to look at 'labels'.
+    first: u8,
+    /// The first child of this node, or ROOT for none.
+    first_child: u32,
+    /// The next child of this node's parent, or ROOT for none.
+    next_sibling: u32,
+    /// The number of times the word ending at this node was counted.
+    count: N,
+    /// The order in which the word ending at this node was first seen. This
+    /// is meaningless for nodes with a zero count.
+    seen: u32,
+}
+
+impl<N: Count> RadixTrie<N> {
+    fn new() -> RadixTrie<N> {
+        RadixTrie {
+            nodes: vec![Node::default()],
+            labels: vec![],
+            root: Box::new([ROOT; 256]),
+        }
+    }
+
+    /// Returns the child of the given node whose label starts with `b`. If
+    /// there isn't one, then the last child is returned as an error instead,
+    /// or ROOT if there are no children or the node is the root.
+    fn child(&self, id: u32, b: u8) -> Result<u32, u32> {
+        if id == ROOT {
+            return match self.root[b as usize] {
+                ROOT => Err(ROOT),
+                child => Ok(child),
+            };
+        }
+        let mut last = ROOT;
+        let mut child = self.nodes[id as usize].first_child;
+        while child != ROOT {
+            let node = &self.nodes[child as usize];
+            if node.first == b {
+                return Ok(child);
+            }
+            last = child;
+            child = node.next_sibling;
+        }
+        Err(last)
+    }
+
+    /// Adds a leaf with a label of `b` to the given node, after its child
+    /// `last` (as returned by `RadixTrie::child`), and returns its ID. Fails
+    /// if the labels are full.
+    fn add_leaf(&mut self, parent: u32, last: u32, b: u8) -> io::Result<u32> {
+        let start = self.push_label(b)?;
+        let id = self.alloc(Node {
+            start,
+            len: 1,
+            first: b,
+            ..Node::default()
+        });
+        if parent == ROOT {
+            self.root[b as usize] = id;
+        } else if last == ROOT {
+            self.nodes[parent as usize].first_child = id;
+        } else {
+            self.nodes[last as usize].next_sibling = id;
+        }
+        Ok(id)
+    }
+
+    /// Appends `b` to the labels and returns where it is. Fails if the end of
+    /// a label ending with it wouldn't fit in a u32, which leaves the trie as
+    /// it was.
+    fn push_label(&mut self, b: u8) -> io::Result<u32> {
+        let at = u32::try_from(self.labels.len())
+            .ok()
+            .filter(|&at| at < u32::MAX)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::OutOfMemory,
+                    "radix trie labels can't hold more than 4 GiB",
+                )
+            })?;
+        self.labels.push(b);
+        Ok(at)
+    }
+
+    /// Splits the label of the given node after its first `at` bytes, which
+    /// must be fewer than all of them. The node keeps its ID and its place
+    /// among its siblings, and the rest of its label, its children and its
+    /// count move to a new child.
+    fn split(&mut self, id: u32, at: u32) {
+        let node = self.nodes[id as usize];
+        debug_assert!(0 < at && at < node.len);
+        let start = node.start + at;
+        let tail = self.alloc(Node {
+            start,
+            len: node.len - at,
+            first: self.labels[start as usize],
+            next_sibling: ROOT,
+            ..node
+        });
+        self.nodes[id as usize] = Node {
+            len: at,
+            first_child: tail,
+            count: N::default(),
+            seen: 0,
+            ..node
+        };
+    }
+
+    fn alloc(&mut self, node: Node<N>) -> u32 {
+        let id = u32::try_from(self.nodes.len()).expect("too many trie nodes");
+        self.nodes.push(node);
+        id
+    }
+
+    /// Calls `f` with every word in this trie, its count and the order in
+    /// which it was first seen, in no particular order.
+    fn for_each(&self, f: &mut dyn FnMut(&[u8], N, u32)) {
+        let mut word = vec![];
+        // Each node still to visit, and the length of its parent's word.
+        let mut stack: Vec<(u32, usize)> = self
+            .root
+            .iter()
+            .filter(|&&child| child != ROOT)
+            .map(|&child| (child, 0))
+            .collect();
+        while let Some((id, depth)) = stack.pop() {
+            let node = &self.nodes[id as usize];
+            word.truncate(depth);
+            let start = node.start as usize;
+            word.extend_from_slice(&self.labels[start..start + node.len as usize]);
+            if node.count.get() > 0 {
+                f(&word, node.count, node.seen);
+            }
+            let mut child = node.first_child;
+            while child != ROOT {
+                stack.push((child, word.len()));
+                child = self.nodes[child as usize].next_sibling;
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+// This version is roughly how I would write this program if someone gave
+// me the problem as an interview challenge and said, "don't worry about
+// performance." One of the main performance problems in this program is
+// the `word.to_lowercase()` function, which results in an allocation. (It
+// also does Unicode case folding, which is unnecessary for this particular
+// challenge. But the simple Go variant does the same, and this is what I
+// would reach for naturally, so I left it in.) However, one needs to allocate
+// anyway to use the `entry` API, even though the common case is that the
+// word already exists in the hash map. The code changes required to use
+// `make_ascii_uppercase` and check if the word is already in the hash map (at
+// the expense of doing another lookup if it isn't) are quite small and lead to
+// a massive performance improvement. Nevertheless, this code is simpler.
+//
+// Other costs, although smaller, are UTF-8 validation and the fact that we're
+// also allocating a new String for each line. (And the fact that we're even
+// worrying about lines at all. The other Rust programs do not.)
+
+use std::{collections::HashMap, io};
+
+use crate::{
+    lines::{invalid_utf8, LineBuffer},
+    Count, Feed, WordCounter,
+};
+
+/// Counts whitespace separated words, lowercased with Unicode rules, in a
+/// std `HashMap`. The input must be valid UTF-8.
+#[derive(Clone, Debug, Default)]
+pub struct Simple<N = u64> {
+    /// Each word's count and the order in which it was first seen.
+    counts: HashMap<String, (N, usize)>,
+    lines: LineBuffer,
+}
+
+impl Simple {
+    /// Create a new empty counter with u64 counts.
+    pub fn new() -> Simple {
+        Simple::default()
+    }
+}
+
+impl<N: Count> Feed for Simple<N> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let counts = &mut self.counts;
+        self.lines.feed(chunk, |lines| count_lines(counts, lines))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let counts = &mut self.counts;
+        self.lines.finish(|lines| count_lines(counts, lines))
+    }
+}
+
+impl<N: Count> WordCounter for Simple<N> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        for (word, &(count, seen)) in self.counts.iter() {
+            f(word.as_bytes(), count.get(), seen);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.counts.len()
+    }
+}
+
+fn count_lines<N: Count>(counts: &mut HashMap<String, (N, usize)>, lines: &[u8]) -> io::Result<()> {
+    let lines = std::str::from_utf8(lines).map_err(invalid_utf8)?;
+    for word in lines.split_whitespace() {
+        let canon = word.to_lowercase();
+        let seen = counts.len();
+        counts
+            .entry(canon)
+            .or_insert((N::default(), seen))
+            .0
+            .increment()?;
+    }
+    Ok(())
+}
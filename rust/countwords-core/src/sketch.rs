@@ -0,0 +1,292 @@
+// A Count-Min Sketch (Cormode and Muthukrishnan, 2005) answers "how often did
+// this word occur?" in a fixed amount of memory, no matter how many distinct
+// words there are. It's a grid of counters, `depth` rows of `width` each, and
+// every word has one counter in each row. Counting a word increments all of
+// its counters, and its estimate is the smallest of them. Other words share
+// those counters, so the estimate may be too high, but it's never too low.
+// With a width of e/ε and a depth of ln(1/δ), the estimate is within εN of
+// the true count with probability 1 - δ, where N is the number of words.
+//
+// With conservative update, counting a word only raises those of its counters
+// that are below its new estimate. That can't make any estimate too low, but
+// does make them a lot less often too high.
+//
+// Words are split exactly like optimized-customhashmap does it, and the FNV
+// hash it computes in the same pass is the only hashing done per word. Each
+// row's counter comes from double hashing: row i uses h1 + i*h2, where h1 is
+// the FNV hash and h2 is derived from it by a cheap mixing function.
+//
+// Sketches of the same dimensions can be merged by adding their counters,
+// which gives the sketch of both inputs. That's what makes it possible to
+// build sketches of shards separately and query them together.
+
+use std::io::{self, Read, Write};
+
+use crate::{
+    table::{fnv, mix, FnvWords},
+    CountOverflow, Feed,
+};
+
+/// Identifies a serialized sketch.
+const MAGIC: &[u8; 8] = b"cwcmsk\0\0";
+/// The version of the format written by `CountMinSketch::write_to`.
+const VERSION: u32 = 1;
+/// Set in the flags of a sketch built with conservative update.
+const FLAG_CONSERVATIVE: u32 = 1 << 0;
+
+/// Estimates the count of any word in a fixed amount of memory. Words are
+/// split and lowercased exactly like `TableCounter`.
+///
+/// Estimates are never too low.
+#[derive(Clone, Debug)]
+pub struct CountMinSketch {
+    grid: Grid,
+    words: FnvWords,
+}
+
+/// The counters of a `CountMinSketch`.
+#[derive(Clone, Debug)]
+struct Grid {
+    width: usize,
+    depth: usize,
+    conservative: bool,
+    /// The total number of words counted.
+    total: u64,
+    /// `depth` rows of `width` counters, one row after the other.
+    counters: Vec<u64>,
+}
+
+impl CountMinSketch {
+    /// Create a new empty sketch with `depth` rows of `width` counters.
+    ///
+    /// # Panics
+    ///
+    /// If either dimension is zero.
+    pub fn new(width: usize, depth: usize) -> CountMinSketch {
+        assert!(width > 0 && depth > 0, "a sketch can't be empty");
+        CountMinSketch {
+            grid: Grid {
+                width,
+                depth,
+                conservative: false,
+                total: 0,
+                counters: vec![0; width.checked_mul(depth).unwrap()],
+            },
+            words: FnvWords::new(),
+        }
+    }
+
+    /// Create a new empty sketch whose estimates are within `epsilon` times
+    /// the number of words counted of the true count, with probability at
+    /// least `1 - delta`.
+    ///
+    /// # Panics
+    ///
+    /// If `epsilon` or `delta` isn't strictly between 0 and 1.
+    pub fn with_error(epsilon: f64, delta: f64) -> CountMinSketch {
+        assert!(0.0 < epsilon && epsilon < 1.0, "epsilon must be in (0, 1)");
+        assert!(0.0 < delta && delta < 1.0, "delta must be in (0, 1)");
+        let width = (std::f64::consts::E / epsilon).ceil() as usize;
+        let depth = (1.0 / delta).ln().ceil().max(1.0) as usize;
+        CountMinSketch::new(width, depth)
+    }
+
+    /// Set whether counting a word only raises the counters that need it.
+    /// This should be set before anything is counted.
+    pub fn conservative(mut self, yes: bool) -> CountMinSketch {
+        self.grid.conservative = yes;
+        self
+    }
+
+    /// Returns the number of counters in each row.
+    pub fn width(&self) -> usize {
+        self.grid.width
+    }
+
+    /// Returns the number of rows.
+    pub fn depth(&self) -> usize {
+        self.grid.depth
+    }
+
+    /// Returns the total number of words counted.
+    pub fn total(&self) -> u64 {
+        self.grid.total
+    }
+
+    /// Returns the estimated count of `word`, which is lowercased with ASCII
+    /// rules first, just as counted words are.
+    pub fn estimate(&self, word: &[u8]) -> u64 {
+        self.grid.estimate(fnv(&word.to_ascii_lowercase()))
+    }
+
+    /// Adds the counts in `other` to this sketch, so that it estimates the
+    /// counts of both inputs together.
+    ///
+    /// Both sketches must have the same dimensions. Merging sketches built
+    /// with conservative update gives estimates that are still never too low,
+    /// but the result isn't the sketch conservative update would have built
+    /// from both inputs.
+    ///
+    /// If any counter would overflow, then an error is returned and this
+    /// sketch is left alone.
+    pub fn merge(&mut self, other: &CountMinSketch) -> io::Result<()> {
+        let (grid, other) = (&mut self.grid, &other.grid);
+        if (grid.width, grid.depth) != (other.width, other.depth) {
+            let msg = format!(
+                "can't merge a {}x{} sketch into a {}x{} sketch",
+                other.width, other.depth, grid.width, grid.depth
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        let overflow = || io::Error::from(CountOverflow::new(u64::MAX));
+        let total = grid.total.checked_add(other.total).ok_or_else(overflow)?;
+        let counters = grid
+            .counters
+            .iter()
+            .zip(other.counters.iter())
+            .map(|(&count, &other)| count.checked_add(other))
+            .collect::<Option<Vec<u64>>>()
+            .ok_or_else(overflow)?;
+        grid.counters = counters;
+        grid.total = total;
+        grid.conservative = grid.conservative && other.conservative;
+        Ok(())
+    }
+
+    /// Writes this sketch in a versioned binary format that
+    /// `CountMinSketch::read_from` reads back. All integers are little
+    /// endian.
+    pub fn write_to<W: Write>(&self, wtr: W) -> io::Result<()> {
+        let grid = &self.grid;
+        let mut wtr = io::BufWriter::new(wtr);
+        let flags = if grid.conservative {
+            FLAG_CONSERVATIVE
+        } else {
+            0
+        };
+        wtr.write_all(MAGIC)?;
+        wtr.write_all(&VERSION.to_le_bytes())?;
+        wtr.write_all(&flags.to_le_bytes())?;
+        wtr.write_all(&(grid.width as u64).to_le_bytes())?;
+        wtr.write_all(&(grid.depth as u64).to_le_bytes())?;
+        wtr.write_all(&grid.total.to_le_bytes())?;
+        for count in grid.counters.iter() {
+            wtr.write_all(&count.to_le_bytes())?;
+        }
+        wtr.flush()
+    }
+
+    /// Reads a sketch written by `CountMinSketch::write_to`.
+    pub fn read_from<R: Read>(rdr: R) -> io::Result<CountMinSketch> {
+        let mut rdr = io::BufReader::new(rdr);
+        let mut magic = [0; 8];
+        rdr.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_sketch("not a Count-Min Sketch"));
+        }
+        let version = read_u32(&mut rdr)?;
+        if version != VERSION {
+            let msg = format!("unsupported sketch version {}", version);
+            return Err(invalid_sketch(&msg));
+        }
+        let flags = read_u32(&mut rdr)?;
+        let width = usize::try_from(read_u64(&mut rdr)?).ok();
+        let depth = usize::try_from(read_u64(&mut rdr)?).ok();
+        let total = read_u64(&mut rdr)?;
+        let (width, depth) = match (width, depth) {
+            (Some(width), Some(depth))
+                if width > 0 && depth > 0 && width.checked_mul(depth).is_some() =>
+            {
+                (width, depth)
+            }
+            _ => return Err(invalid_sketch("invalid sketch dimensions")),
+        };
+
+        let mut counters = Vec::new();
+        let mut buf = [0; 8];
+        for _ in 0..width * depth {
+            rdr.read_exact(&mut buf)?;
+            counters.push(u64::from_le_bytes(buf));
+        }
+        if rdr.read(&mut buf)? != 0 {
+            return Err(invalid_sketch("trailing data after sketch"));
+        }
+        Ok(CountMinSketch {
+            grid: Grid {
+                width,
+                depth,
+                conservative: flags & FLAG_CONSERVATIVE != 0,
+                total,
+                counters,
+            },
+            words: FnvWords::new(),
+        })
+    }
+}
+
+impl Grid {
+    /// Counts one occurrence of the word with the given hash.
+    fn add(&mut self, hash: u64) {
+        self.total += 1;
+        if !self.conservative {
+            for i in self.indices(hash) {
+                self.counters[i] += 1;
+            }
+            return;
+        }
+        let estimate = self.estimate(hash) + 1;
+        for i in self.indices(hash) {
+            if self.counters[i] < estimate {
+                self.counters[i] = estimate;
+            }
+        }
+    }
+
+    fn estimate(&self, hash: u64) -> u64 {
+        // This unwrap is correct because there's always at least one row.
+        self.indices(hash).map(|i| self.counters[i]).min().unwrap()
+    }
+
+    /// Returns the index of the counter for the word with the given hash in
+    /// each row.
+    fn indices(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let (width, depth) = (self.width, self.depth);
+        // An odd h2 is coprime with any power of two width, so it never maps
+        // two rows to the same column there.
+        let (h1, h2) = (hash, mix(hash) | 1);
+        (0..depth).map(move |row| {
+            let column = h1.wrapping_add((row as u64).wrapping_mul(h2)) % width as u64;
+            row * width + column as usize
+        })
+    }
+}
+
+impl Feed for CountMinSketch {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let grid = &mut self.grid;
+        self.words.feed(chunk, |_, hash| grid.add(hash));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let grid = &mut self.grid;
+        self.words.finish(|_, hash| grid.add(hash));
+        Ok(())
+    }
+}
+
+pub(crate) fn read_u32<R: Read>(rdr: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    rdr.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub(crate) fn read_u64<R: Read>(rdr: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    rdr.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn invalid_sketch(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
@@ -0,0 +1,360 @@
+// The original programs only sorted by count, and left words with equal
+// counts in whatever order their hash map happened to produce, which is why
+// test.sh pipes everything through normalize.py. Every order here is total,
+// so the same counts always come out the same way, whichever engine counted
+// them and however many times it's run.
+//
+// Most of the time only the first few words are wanted, though, and sorting
+// the whole vocabulary to print a hundred of them is a waste. `Select` keeps
+// the best N words seen so far in a heap instead, and only copies a word out
+// of the counter when it makes the cut. So beyond the one pass over every
+// word, the cost scales with N rather than with the size of the vocabulary.
+//
+// Some counters (the trie) already pass their words in byte order. Then
+// alphabetical order needs no sorting at all, and `Select::for_each` passes
+// words along as they come, without even copying them. Ordering those by
+// count is a counting sort instead of a comparison sort: words with equal
+// counts are already in the right order, so they only need bucketing by
+// count, and only the distinct counts, of which there are few, get sorted.
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    fmt,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{StopWords, UnknownName, WordCounter};
+
+/// An order for words and their counts.
+///
+/// Ties are always broken, so that sorting is deterministic.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Sort {
+    /// Most frequent first, with equal counts in byte order. This is the
+    /// order normalize.py produces.
+    #[default]
+    Count,
+    /// Least frequent first, with equal counts in byte order.
+    CountAsc,
+    /// Byte order, which for UTF-8 is the same as codepoint order.
+    Alpha,
+    /// Longest first (in bytes), with equal lengths in byte order.
+    Length,
+    /// The order in which each word first appeared in the input.
+    FirstSeen,
+}
+
+impl Sort {
+    /// Every order, in the order they're listed in help output.
+    pub const ALL: [Sort; 5] = [
+        Sort::Count,
+        Sort::CountAsc,
+        Sort::Alpha,
+        Sort::Length,
+        Sort::FirstSeen,
+    ];
+
+    /// Returns the name of this order, as accepted by `Sort::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Sort::Count => "count",
+            Sort::CountAsc => "count-asc",
+            Sort::Alpha => "alpha",
+            Sort::Length => "length",
+            Sort::FirstSeen => "first-seen",
+        }
+    }
+
+    /// Compares two distinct words, each given with its count and the order
+    /// in which it was first seen (as reported by
+    /// `WordCounter::for_each_seen`).
+    pub fn compare(self, a: (&[u8], u64, usize), b: (&[u8], u64, usize)) -> Ordering {
+        let (aword, acount, aseen) = a;
+        let (bword, bcount, bseen) = b;
+        match self {
+            Sort::Count => bcount.cmp(&acount).then_with(|| aword.cmp(bword)),
+            Sort::CountAsc => acount.cmp(&bcount).then_with(|| aword.cmp(bword)),
+            Sort::Alpha => aword.cmp(bword),
+            Sort::Length => bword.len().cmp(&aword.len()).then_with(|| aword.cmp(bword)),
+            Sort::FirstSeen => aseen.cmp(&bseen),
+        }
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Sort {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Sort, UnknownName> {
+        let names = Sort::ALL.map(Sort::name);
+        Sort::ALL
+            .iter()
+            .copied()
+            .find(|sort| sort.name() == s)
+            .ok_or_else(|| UnknownName::new("sort", s, &names))
+    }
+}
+
+/// Which words to take from a counter, and in what order.
+///
+/// By default, every word is taken, most frequent first.
+///
+/// Cloning a selection is cheap, since its stop words are shared.
+#[derive(Clone, Debug, Default)]
+pub struct Select {
+    sort: Sort,
+    top: Option<usize>,
+    min_count: u64,
+    max_count: u64,
+    min_len: usize,
+    max_len: usize,
+    stop: Option<Arc<StopWords>>,
+}
+
+impl Select {
+    /// Create a selection of every word, in the given order.
+    pub fn new(sort: Sort) -> Select {
+        Select {
+            sort,
+            top: None,
+            min_count: 0,
+            max_count: u64::MAX,
+            min_len: 0,
+            max_len: usize::MAX,
+            stop: None,
+        }
+    }
+
+    /// Only take the first `n` words in this selection's order.
+    pub fn top(mut self, n: usize) -> Select {
+        self.top = Some(n);
+        self
+    }
+
+    /// Only take words counted at least this many times.
+    pub fn min_count(mut self, count: u64) -> Select {
+        self.min_count = count;
+        self
+    }
+
+    /// Only take words counted at most this many times.
+    pub fn max_count(mut self, count: u64) -> Select {
+        self.max_count = count;
+        self
+    }
+
+    /// Only take words that are at least this many bytes long.
+    pub fn min_len(mut self, len: usize) -> Select {
+        self.min_len = len;
+        self
+    }
+
+    /// Only take words that are at most this many bytes long.
+    pub fn max_len(mut self, len: usize) -> Select {
+        self.max_len = len;
+        self
+    }
+
+    /// Leave out the given stop words. See `StopWords` for why they're left
+    /// out here rather than while counting.
+    pub fn stopwords(mut self, stop: StopWords) -> Select {
+        self.stop = Some(Arc::new(stop));
+        self
+    }
+
+    /// Returns true if a word with the given count passes this selection's
+    /// thresholds and isn't a stop word. This doesn't account for
+    /// `Select::top`.
+    pub fn keeps(&self, word: &[u8], count: u64) -> bool {
+        self.min_count <= count
+            && count <= self.max_count
+            && self.min_len <= word.len()
+            && word.len() <= self.max_len
+            && !self.stop.as_ref().is_some_and(|stop| stop.contains(word))
+    }
+
+    /// Returns the selected words and their counts, in order.
+    pub fn apply<C: WordCounter + ?Sized>(&self, counter: &C) -> Vec<(Vec<u8>, u64)> {
+        if self.is_streamed(counter) {
+            let mut ordered = vec![];
+            self.for_each(counter, &mut |word, count| {
+                ordered.push((word.to_vec(), count))
+            });
+            return ordered;
+        }
+        let by_count = matches!(self.sort, Sort::Count | Sort::CountAsc);
+        if by_count && self.top.is_none() && counter.is_ordered() {
+            return self.bucketed(counter);
+        }
+        self.apply_each(Some(counter.len()), |f| counter.for_each_seen(f))
+    }
+
+    /// Returns true if `Select::for_each` passes words straight from
+    /// `counter`, without collecting them first. That's the case when the
+    /// order is `Sort::Alpha` and `WordCounter::is_ordered` is true.
+    pub fn is_streamed<C: WordCounter + ?Sized>(&self, counter: &C) -> bool {
+        self.sort == Sort::Alpha && counter.is_ordered()
+    }
+
+    /// Calls `f` with the selected words and their counts, in order.
+    ///
+    /// Unless `Select::is_streamed` is true, this collects them with
+    /// `Select::apply` first.
+    pub fn for_each<C: WordCounter + ?Sized>(&self, counter: &C, f: &mut dyn FnMut(&[u8], u64)) {
+        if !self.is_streamed(counter) {
+            for (word, count) in self.apply(counter) {
+                f(&word, count);
+            }
+            return;
+        }
+        let mut left = self.top.unwrap_or(usize::MAX);
+        counter.for_each(&mut |word, count| {
+            if left > 0 && self.keeps(word, count) {
+                left -= 1;
+                f(word, count);
+            }
+        });
+    }
+
+    /// Like `Select::apply`, but for the words that `for_each_seen` calls its
+    /// argument with, as `WordCounter::for_each_seen` would. `len` is how many
+    /// distinct words there are, if that's known.
+    pub(crate) fn apply_each(
+        &self,
+        len: Option<usize>,
+        for_each_seen: impl Fn(&mut dyn FnMut(&[u8], u64, usize)),
+    ) -> Vec<(Vec<u8>, u64)> {
+        let ordered = match self.top {
+            Some(n) if len.is_none_or(|len| n < len) => self.top_n(for_each_seen, n),
+            _ => self.all(len.unwrap_or(0), for_each_seen),
+        };
+        ordered
+            .into_iter()
+            .map(|ranked| (ranked.word, ranked.count))
+            .collect()
+    }
+
+    fn all(
+        &self,
+        len: usize,
+        for_each_seen: impl Fn(&mut dyn FnMut(&[u8], u64, usize)),
+    ) -> Vec<Ranked> {
+        let mut ordered = Vec::with_capacity(len);
+        for_each_seen(&mut |word, count, seen| {
+            if self.keeps(word, count) {
+                ordered.push(self.ranked(word, count, seen));
+            }
+        });
+        ordered.sort_unstable();
+        ordered
+    }
+
+    /// Orders the words of a counter whose words are in byte order by count,
+    /// with a counting sort. See the comments at the top.
+    fn bucketed<C: WordCounter + ?Sized>(&self, counter: &C) -> Vec<(Vec<u8>, u64)> {
+        let mut words = Vec::with_capacity(counter.len());
+        // The number of words with each count, and then where the next word
+        // with each count goes.
+        let mut buckets: HashMap<u64, usize> = HashMap::new();
+        counter.for_each(&mut |word, count| {
+            if self.keeps(word, count) {
+                words.push((word.to_vec(), count));
+                *buckets.entry(count).or_insert(0) += 1;
+            }
+        });
+        let mut counts: Vec<u64> = buckets.keys().copied().collect();
+        counts.sort_unstable_by(|&a, &b| self.sort.compare((&[], a, 0), (&[], b, 0)));
+        let mut start = 0;
+        for count in counts {
+            // This unwrap is correct because every count has a bucket.
+            let len = buckets.get_mut(&count).unwrap();
+            start += std::mem::replace(len, start);
+        }
+
+        let mut ordered = vec![(vec![], 0); words.len()];
+        for (word, count) in words {
+            // This unwrap is correct because every count has a bucket.
+            let next = buckets.get_mut(&count).unwrap();
+            ordered[*next] = (word, count);
+            *next += 1;
+        }
+        ordered
+    }
+
+    fn top_n(
+        &self,
+        for_each_seen: impl Fn(&mut dyn FnMut(&[u8], u64, usize)),
+        n: usize,
+    ) -> Vec<Ranked> {
+        if n == 0 {
+            return vec![];
+        }
+        // A max-heap, so the worst of the best N words so far is on top.
+        let mut best = BinaryHeap::with_capacity(n);
+        for_each_seen(&mut |word, count, seen| {
+            if !self.keeps(word, count) {
+                return;
+            }
+            if best.len() < n {
+                best.push(self.ranked(word, count, seen));
+                return;
+            }
+            // This unwrap is correct because the heap has n > 0 words.
+            let worst = best.peek().unwrap();
+            let key = (&worst.word[..], worst.count, worst.seen);
+            if self.sort.compare((word, count, seen), key) == Ordering::Less {
+                best.pop();
+                best.push(self.ranked(word, count, seen));
+            }
+        });
+        best.into_sorted_vec()
+    }
+
+    fn ranked(&self, word: &[u8], count: u64, seen: usize) -> Ranked {
+        Ranked {
+            sort: self.sort,
+            word: word.to_vec(),
+            count,
+            seen,
+        }
+    }
+}
+
+/// A word that is ordered by `Sort`.
+#[derive(Debug)]
+struct Ranked {
+    sort: Sort,
+    word: Vec<u8>,
+    count: u64,
+    seen: usize,
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Ranked) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Ranked) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Ranked) -> Ordering {
+        self.sort.compare(
+            (&self.word, self.count, self.seen),
+            (&other.word, other.count, other.seen),
+        )
+    }
+}
@@ -0,0 +1,288 @@
+// Every other strategy keeps a count for every distinct word, so its memory
+// grows with the vocabulary. That's fine for a book, but not for a few
+// terabytes of logs, where there's no end of distinct words. This one uses
+// the Space-Saving algorithm (Metwally, Agrawal and El Abbadi, 2005) instead,
+// which only ever keeps K counters.
+//
+// While there's a free counter, a new word gets it, exactly like an ordinary
+// hash map. Once they're all taken, a new word takes over the counter with the
+// smallest count, inheriting that count (plus one for itself) and recording
+// the inherited part as its error. A word's estimate therefore never
+// undercounts it, and overcounts it by at most its error. The smallest count
+// is never more than N/K after N words, so any word occurring more often than
+// that is guaranteed to be in the summary.
+//
+// The counters are kept in a min-heap by count, with a hash table of their
+// positions in the heap. The table only holds the positions, and is searched
+// by comparing against the words in the heap, so each word is stored once.
+// Seeing a word again only ever raises its count,
+// so it only ever moves down the heap, and a word getting a free counter has
+// the smallest possible count, so it only ever moves up. The words themselves
+// are split exactly like fast-simple does it.
+//
+// Unlike every other strategy, this one leaves stop words out as it counts
+// rather than when its counts are read, since a stop word would otherwise
+// hold on to one of the K counters that the words being looked for need.
+
+use std::{hash::BuildHasher, io};
+
+use hashbrown::{DefaultHashBuilder, HashTable};
+
+use crate::{
+    fast_simple::words, lines::LineBuffer, Count, CountOverflow, Feed, StopWords, WordCounter,
+};
+
+/// Estimates the counts of the most frequent words, using a fixed number of
+/// counters. Words are split and lowercased exactly like `FastSimple`.
+///
+/// As a `WordCounter`, this reports estimated counts, which never undercount.
+/// A word is reported as first seen when it last took over a counter.
+#[derive(Clone, Debug)]
+pub struct SpaceSaving {
+    summary: Summary,
+    lines: LineBuffer,
+}
+
+/// The K counters.
+#[derive(Clone, Debug)]
+struct Summary {
+    /// A min-heap of counters, ordered by count.
+    heap: Vec<Counter>,
+    /// The position of every counter in 'heap', found by the hash of its
+    /// word.
+    positions: HashTable<usize>,
+    hash_builder: DefaultHashBuilder,
+    capacity: usize,
+    /// The total number of words counted.
+    total: u64,
+    /// The number of times a word has taken a counter.
+    taken: usize,
+    /// The words that aren't counted at all.
+    stop: StopWords,
+}
+
+/// A word's estimated count.
+#[derive(Clone, Debug)]
+struct Counter {
+    word: Box<str>,
+    /// The hash of 'word', kept so that moving the counter never hashes it
+    /// again.
+    hash: u64,
+    count: u64,
+    /// The largest amount by which 'count' may overcount 'word'.
+    error: u64,
+    seen: usize,
+}
+
+impl SpaceSaving {
+    /// Create a new empty counter with room for `k` words.
+    ///
+    /// # Panics
+    ///
+    /// If `k` is zero.
+    pub fn new(k: usize) -> SpaceSaving {
+        assert!(k > 0, "Space-Saving needs at least one counter");
+        SpaceSaving {
+            summary: Summary {
+                heap: Vec::with_capacity(k),
+                positions: HashTable::with_capacity(k),
+                hash_builder: DefaultHashBuilder::default(),
+                capacity: k,
+                total: 0,
+                taken: 0,
+                stop: StopWords::new(),
+            },
+            lines: LineBuffer::default(),
+        }
+    }
+
+    /// Leave the given stop words out as they're counted, so that none of
+    /// them takes a counter. They're left out of `SpaceSaving::total` too.
+    /// This should be set before anything is counted.
+    pub fn stopwords(mut self, stop: StopWords) -> SpaceSaving {
+        self.summary.stop = stop;
+        self
+    }
+
+    /// Returns the total number of words counted, including those no longer
+    /// in the summary.
+    pub fn total(&self) -> u64 {
+        self.summary.total
+    }
+
+    /// Returns the estimated count of `word` and the largest amount by which
+    /// it may overcount it, i.e., its true count is somewhere in
+    /// `count - error..=count`. If `word` isn't in the summary, then it
+    /// occurred at most `SpaceSaving::max_unreported` times.
+    pub fn estimate(&self, word: &[u8]) -> Option<(u64, u64)> {
+        let word = std::str::from_utf8(word).ok()?;
+        let summary = &self.summary;
+        let counter = &summary.heap[summary.position(word, summary.hash(word))?];
+        Some((counter.count, counter.error))
+    }
+
+    /// Returns the largest number of times a word that isn't in the summary
+    /// may have occurred. Every word that occurred more often than this is
+    /// reported. It is never more than `total / k`.
+    pub fn max_unreported(&self) -> u64 {
+        let summary = &self.summary;
+        if summary.heap.len() < summary.capacity {
+            return 0;
+        }
+        summary.heap[0].count
+    }
+
+    /// Calls `f` with every word in the summary, its estimated count and the
+    /// largest amount by which that may overcount it, in no particular order.
+    pub fn for_each_estimate(&self, mut f: impl FnMut(&[u8], u64, u64)) {
+        for counter in self.summary.heap.iter() {
+            f(counter.word.as_bytes(), counter.count, counter.error);
+        }
+    }
+}
+
+impl Summary {
+    fn hash(&self, word: &str) -> u64 {
+        self.hash_builder.hash_one(word)
+    }
+
+    /// Returns the position in the heap of the counter for `word`, whose hash
+    /// is `hash`, if it has one.
+    fn position(&self, word: &str, hash: u64) -> Option<usize> {
+        let heap = &self.heap;
+        self.positions
+            .find(hash, |&i| &*heap[i].word == word)
+            .copied()
+    }
+
+    /// Adds the counter at position `i` of the heap to the positions.
+    fn insert_position(&mut self, i: usize) {
+        let heap = &self.heap;
+        self.positions
+            .insert_unique(heap[i].hash, i, |&j| heap[j].hash);
+    }
+
+    fn increment(&mut self, word: &str) -> Result<(), CountOverflow> {
+        // The counts add up to the total, so none of them can overflow unless
+        // it does, but they're checked anyway.
+        self.total.increment()?;
+        let hash = self.hash(word);
+        if let Some(i) = self.position(word, hash) {
+            self.heap[i].count.increment()?;
+            self.sift_down(i);
+            return Ok(());
+        }
+        let counter = Counter {
+            word: Box::from(word),
+            hash,
+            count: 1,
+            error: 0,
+            seen: self.taken,
+        };
+        self.taken += 1;
+        if self.heap.len() < self.capacity {
+            let i = self.heap.len();
+            self.heap.push(counter);
+            self.insert_position(i);
+            self.sift_up(i);
+            return Ok(());
+        }
+        let mut count = self.heap[0].count;
+        count.increment()?;
+        let min = std::mem::replace(&mut self.heap[0], counter);
+        // This unwrap is correct because every counter in the heap has a
+        // position.
+        self.positions
+            .find_entry(min.hash, |&j| j == 0)
+            .unwrap()
+            .remove();
+        let new = &mut self.heap[0];
+        new.count = count;
+        new.error = min.count;
+        self.insert_position(0);
+        self.sift_down(0);
+        Ok(())
+    }
+
+    /// Moves the counter at position `i` up the heap until it's no smaller
+    /// than its parent.
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.heap[parent].count <= self.heap[i].count {
+                return;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    /// Moves the counter at position `i` down the heap until it's no bigger
+    /// than its children.
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let (left, right) = (2 * i + 1, 2 * i + 2);
+            let mut smallest = i;
+            if left < self.heap.len() && self.heap[left].count < self.heap[smallest].count {
+                smallest = left;
+            }
+            if right < self.heap.len() && self.heap[right].count < self.heap[smallest].count {
+                smallest = right;
+            }
+            if smallest == i {
+                return;
+            }
+            self.swap(i, smallest);
+            i = smallest;
+        }
+    }
+
+    /// Swaps two counters in the heap, keeping their positions up to date.
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        // Both positions are looked up at once, since the two words may have
+        // the same hash, and then only their old positions tell them apart.
+        let hashes = [self.heap[i].hash, self.heap[j].hash];
+        let old = [j, i];
+        let [a, b] = self.positions.get_many_mut(hashes, |k, &p| p == old[k]);
+        // These unwraps are correct because every counter in the heap has a
+        // position.
+        *a.unwrap() = i;
+        *b.unwrap() = j;
+    }
+
+    fn count_words(&mut self, bytes_buffer: &mut [u8]) -> io::Result<()> {
+        for word in words(bytes_buffer) {
+            if !self.stop.contains(word.as_bytes()) {
+                self.increment(word)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Feed for SpaceSaving {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let summary = &mut self.summary;
+        self.lines
+            .feed(chunk, |bytes_buffer| summary.count_words(bytes_buffer))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let summary = &mut self.summary;
+        self.lines
+            .finish(|bytes_buffer| summary.count_words(bytes_buffer))
+    }
+}
+
+impl WordCounter for SpaceSaving {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        for counter in self.summary.heap.iter() {
+            f(counter.word.as_bytes(), counter.count, counter.seen);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.summary.heap.len()
+    }
+}
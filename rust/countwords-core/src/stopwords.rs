@@ -0,0 +1,95 @@
+// Stop words are the words that top every count and say nothing about the
+// text: "the", "and", "of". The obvious way to leave them out is to look up
+// every word in the stop list as it's counted, but that puts a lookup in the
+// innermost loop of every engine, which is exactly the loop each of them has
+// been tuned to keep short. Instead, stop words are counted like any other
+// word, and `Select` leaves them out when the counts are read. That's one
+// lookup per distinct word rather than per word, so counting is as fast as
+// ever, and since each engine has already normalized its words, the list
+// matches them however the engine lowercased them. It costs a counter for
+// each of the few hundred stop words, which is nothing next to the rest.
+//
+// The exceptions are a `Pipeline` with a stemmer: a stop list holds words
+// rather than stems, so there it's a `Filter`, which sees words before
+// they're stemmed; and `SpaceSaving`, which only has a few counters and
+// can't spare any for stop words, so it leaves them out as it counts.
+
+use std::io::{self, BufRead};
+
+use bstr::ByteSlice;
+use fxhash::FxHashSet as HashSet;
+
+use crate::pipeline::{Filter, Language};
+
+/// A set of words to leave out of counts.
+#[derive(Clone, Debug, Default)]
+pub struct StopWords {
+    words: HashSet<Box<[u8]>>,
+}
+
+impl StopWords {
+    /// Create an empty set.
+    pub fn new() -> StopWords {
+        StopWords::default()
+    }
+
+    /// Create a set of the built-in stop words for the given language.
+    pub fn builtin(language: Language) -> StopWords {
+        let mut stop = StopWords::new();
+        stop.add_builtin(language);
+        stop
+    }
+
+    /// Adds the built-in stop words for the given language.
+    pub fn add_builtin(&mut self, language: Language) {
+        let list = match language {
+            Language::English => include_str!("stopwords/en.txt"),
+            Language::German => include_str!("stopwords/de.txt"),
+            Language::French => include_str!("stopwords/fr.txt"),
+            Language::Spanish => include_str!("stopwords/es.txt"),
+        };
+        // This unwrap is correct because reading from a slice never fails.
+        self.add_list(list.as_bytes()).unwrap();
+    }
+
+    /// Adds the stop words in a list with one word per line. Blank lines and
+    /// lines starting with `#` are skipped, and each word is lowercased with
+    /// Unicode rules.
+    pub fn add_list<R: BufRead>(&mut self, mut rdr: R) -> io::Result<()> {
+        let mut line = vec![];
+        while rdr.read_until(b'\n', &mut line)? > 0 {
+            let word = line.trim();
+            if !word.is_empty() && !word.starts_with(b"#") {
+                self.add(&word.to_lowercase());
+            }
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// Adds a stop word, exactly as given.
+    pub fn add(&mut self, word: &[u8]) {
+        self.words.insert(word.into());
+    }
+
+    /// Returns true if the given word is a stop word.
+    pub fn contains(&self, word: &[u8]) -> bool {
+        self.words.contains(word)
+    }
+
+    /// Returns the number of stop words.
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Returns true if there are no stop words.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+impl Filter for StopWords {
+    fn keep(&self, word: &[u8]) -> bool {
+        !self.contains(word)
+    }
+}
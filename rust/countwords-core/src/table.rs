@@ -0,0 +1,675 @@
+// This version is an approximate port of the optimized C program, which rolls
+// its own custom hash table. The main benefit here, I think, is that the
+// hash can be computed as the bytes are visited in the buffer. When compared
+// to using std's hashmap, this saves a pass over the bytes of each word. It
+// otherwise uses approximately the same buffering technique as the optimized
+// Rust version.
+//
+// On my system, the performance of this program is just about on par with the
+// optimized C variant when the C program is compiled with clang. That same C
+// program compiled with gcc is measurably faster by a bit. I didn't dig into
+// the codegen to figure out why.
+//
+// Unlike the C program's table, this one isn't a fixed size, which would never
+// stop probing once every entry was taken. It doubles whenever another word
+// would take it over its maximum load factor. Each entry keeps the hash
+// computed while scanning, so moving the words into the bigger table doesn't
+// hash any of them again, and comparing hashes first skips most byte
+// comparisons while probing.
+//
+// The table itself is public as `Table`, a map from byte strings to counts.
+// Its entry API takes the hash of the word from the caller, which is how
+// `TableCounter` keeps hashing in the same pass as scanning. Removing a word
+// shifts the words after it in its probe sequence back, rather than leaving a
+// tombstone, so lookups never get slower from removals.
+//
+// Words are stored as `Key`s, so only the ones too long to fit in a slot's key
+// are copied out of the slot, into one arena for the whole table. Their bytes
+// stay there until the table is cleared, even if the word is removed.
+//
+// Probing is linear, like the C program's, unless the robin-hood feature is
+// enabled. See the `linear` and `robin_hood` modules.
+
+use std::{
+    hash::{BuildHasher, Hasher},
+    io,
+};
+
+use crate::{
+    hash::Fnv1,
+    key::{Arena, Key},
+    Count, CountOverflow, Feed, WordCounter,
+};
+
+#[cfg(not(feature = "robin-hood"))]
+mod linear;
+#[cfg(feature = "robin-hood")]
+mod robin_hood;
+
+/// The number of words a new table holds before it first grows. At the
+/// default load factor, this is the size of the C program's fixed table.
+const INITIAL_CAPACITY: usize = 32 * (1 << 10);
+/// The default maximum load factor.
+const MAX_LOAD: f64 = 0.5;
+
+/// Counts words exactly like `Optimized`, but in a custom open addressing
+/// hash table whose hash is computed in the same pass that finds word
+/// boundaries.
+///
+/// Words are hashed with FNV-1 by default, like the C program does. Any
+/// `BuildHasher` can be used instead, and is fed one byte at a time with
+/// `Hasher::write_u8`. See the `hash` module for ones worth using.
+///
+/// The table doubles in size whenever counting another word would take it
+/// over its maximum load factor, which is 0.5 by default.
+///
+/// Counts are u64 by default, and may be any `Count` instead.
+#[derive(Clone, Debug)]
+pub struct TableCounter<S = Fnv1, N = u64> {
+    table: Table<S, N>,
+    words: HashedWords<S>,
+}
+
+impl<N: Count> Default for TableCounter<Fnv1, N> {
+    fn default() -> TableCounter<Fnv1, N> {
+        TableCounter::with_capacity_and_counts(INITIAL_CAPACITY, Fnv1::default())
+    }
+}
+
+impl TableCounter {
+    /// Create a new empty counter.
+    pub fn new() -> TableCounter {
+        TableCounter::with_capacity(INITIAL_CAPACITY)
+    }
+
+    /// Create a new empty counter that can hold at least `capacity` distinct
+    /// words before its table grows.
+    pub fn with_capacity(capacity: usize) -> TableCounter {
+        TableCounter::with_capacity_and_hasher(capacity, Fnv1::default())
+    }
+}
+
+impl<S: BuildHasher + Clone> TableCounter<S> {
+    /// Create a new empty counter that hashes words with hashers built by
+    /// `hash_builder`.
+    pub fn with_hasher(hash_builder: S) -> TableCounter<S> {
+        TableCounter::with_counts(hash_builder)
+    }
+
+    /// Create a new empty counter that can hold at least `capacity` distinct
+    /// words before its table grows, and that hashes words with hashers built
+    /// by `hash_builder`.
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> TableCounter<S> {
+        TableCounter::with_capacity_and_counts(capacity, hash_builder)
+    }
+}
+
+impl<S: BuildHasher + Clone, N: Count> TableCounter<S, N> {
+    /// Create a new empty counter that hashes words with hashers built by
+    /// `hash_builder`, and keeps its counts as `N`.
+    pub fn with_counts(hash_builder: S) -> TableCounter<S, N> {
+        TableCounter::with_capacity_and_counts(INITIAL_CAPACITY, hash_builder)
+    }
+
+    /// Create a new empty counter that can hold at least `capacity` distinct
+    /// words before its table grows, that hashes words with hashers built by
+    /// `hash_builder`, and keeps its counts as `N`.
+    pub fn with_capacity_and_counts(capacity: usize, hash_builder: S) -> TableCounter<S, N> {
+        TableCounter {
+            words: HashedWords::with_hasher(hash_builder.clone()),
+            table: Table::with_capacity_and_hasher(capacity, hash_builder),
+        }
+    }
+}
+
+impl<S: BuildHasher, N: Count> TableCounter<S, N> {
+    /// Set the largest fraction of the table that may be occupied before it
+    /// grows. Higher wastes less memory, but makes for longer probe
+    /// sequences. The table keeps at least the capacity it had.
+    ///
+    /// # Panics
+    ///
+    /// If `load_factor` isn't strictly between 0 and 1.
+    pub fn load_factor(mut self, load_factor: f64) -> TableCounter<S, N> {
+        self.table = self.table.load_factor(load_factor);
+        self
+    }
+
+    /// Returns the number of distinct words this counter can hold before its
+    /// table grows.
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    /// Returns the table of words counted so far.
+    pub fn table(&self) -> &Table<S, N> {
+        &self.table
+    }
+
+    /// Returns the table of words counted. Any word carried over from the
+    /// last chunk fed is dropped, so this should only be called after
+    /// `Feed::finish`.
+    pub fn into_table(self) -> Table<S, N> {
+        self.table
+    }
+}
+
+impl<S: BuildHasher, N: Count> Feed for TableCounter<S, N> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let table = &mut self.table;
+        let mut result = Ok(());
+        self.words.feed(chunk, |word, hash| {
+            if result.is_ok() {
+                result = table.entry(word, hash).or_insert(N::default()).increment();
+            }
+        });
+        Ok(result?)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let table = &mut self.table;
+        let mut result = Ok(());
+        self.words.finish(|word, hash| {
+            result = table.entry(word, hash).or_insert(N::default()).increment()
+        });
+        Ok(result?)
+    }
+}
+
+impl<S: BuildHasher, N: Count> WordCounter for TableCounter<S, N> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        let raw = &self.table.raw;
+        for slot in raw.slots.iter() {
+            if let Some(ref word) = slot.word {
+                f(raw.arena.get(word), slot.count.get(), slot.seen);
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.table.len()
+    }
+}
+
+/// Splits words exactly like `TableCounter` does with the default hasher.
+pub(crate) type FnvWords = HashedWords<Fnv1>;
+
+/// Splits words exactly like `Optimized`, hashing each word in the same pass
+/// that finds where it ends.
+#[derive(Clone, Debug)]
+pub(crate) struct HashedWords<S> {
+    /// The current chunk, preceded by the incomplete word (if any) carried
+    /// over from the previous chunk.
+    buf: Vec<u8>,
+    hash_builder: S,
+}
+
+impl<S: BuildHasher + Default> HashedWords<S> {
+    pub(crate) fn new() -> HashedWords<S> {
+        HashedWords::with_hasher(S::default())
+    }
+}
+
+impl<S: BuildHasher> HashedWords<S> {
+    pub(crate) fn with_hasher(hash_builder: S) -> HashedWords<S> {
+        HashedWords {
+            buf: vec![],
+            hash_builder,
+        }
+    }
+
+    /// Calls `f` with every word that ends in `chunk`, lowercased, and its
+    /// hash.
+    pub(crate) fn feed(&mut self, chunk: &[u8], mut f: impl FnMut(&[u8], u64)) {
+        let offset = self.buf.len();
+        self.buf.extend_from_slice(chunk);
+        let buf = &mut self.buf[..];
+
+        // The word carried over is hashed again rather than keeping its
+        // hasher around. It's at most one short word per chunk.
+        let mut hasher = self.hash_builder.build_hasher();
+        for &b in buf[..offset].iter() {
+            hasher.write_u8(b);
+        }
+        let mut start = if offset > 0 { Some(0) } else { None };
+        for i in offset..buf.len() {
+            let b = buf[i];
+            if b == b' ' || b == b'\n' {
+                if let Some(start) = start.take() {
+                    f(&buf[start..i], hasher.finish());
+                    hasher = self.hash_builder.build_hasher();
+                }
+            } else {
+                // 0x20 (6th bit) is the only different bit between lowercase and uppercase
+                buf[i] |= (buf[i].is_ascii_uppercase() as u8) << 5;
+                if start.is_none() {
+                    start = Some(i);
+                }
+                hasher.write_u8(buf[i]);
+            }
+        }
+        match start {
+            Some(start) => {
+                self.buf.drain(..start);
+            }
+            None => self.buf.clear(),
+        }
+    }
+
+    /// Calls `f` with the word carried over from the last chunk, if any.
+    pub(crate) fn finish(&mut self, f: impl FnOnce(&[u8], u64)) {
+        if !self.buf.is_empty() {
+            f(&self.buf, hash_bytes(&self.hash_builder, &self.buf));
+            self.buf.clear();
+        }
+    }
+}
+
+/// Returns the hash `HashedWords` computes for `word`, which must already be
+/// lowercased.
+pub(crate) fn hash_bytes<S: BuildHasher>(hash_builder: &S, word: &[u8]) -> u64 {
+    let mut hasher = hash_builder.build_hasher();
+    for &b in word {
+        hasher.write_u8(b);
+    }
+    hasher.finish()
+}
+
+/// Returns the hash `FnvWords` computes for `word`, which must already be
+/// lowercased.
+pub(crate) fn fnv(word: &[u8]) -> u64 {
+    hash_bytes(&Fnv1::default(), word)
+}
+
+/// A map from byte strings to counts, in an open addressing hash table with
+/// linear probing.
+///
+/// Counts are u64 by default, and may be any `Count` instead, though only
+/// u64 counts can be added to with `Table::add`.
+///
+/// Words are hashed with FNV-1 by default. Any `BuildHasher` can be used
+/// instead, and is fed a word one byte at a time with `Hasher::write_u8`.
+/// `Table::entry` takes a hash computed that way by the caller, so that it
+/// can be computed while scanning for the word.
+///
+/// The table doubles in size whenever adding another word would take it over
+/// its maximum load factor, which is 0.5 by default. It never shrinks.
+#[derive(Clone, Debug)]
+pub struct Table<S = Fnv1, N = u64> {
+    raw: RawTable<N>,
+    hash_builder: S,
+}
+
+/// The part of a `Table` that doesn't depend on its hash function.
+#[derive(Clone, Debug)]
+struct RawTable<N> {
+    /// A power of two number of slots, so that a hash is reduced to an index
+    /// with a mask.
+    slots: Vec<Slot<N>>,
+    /// The number of occupied slots.
+    len: usize,
+    /// The number of words ever added, which is when the next new word is
+    /// seen.
+    added: usize,
+    /// The largest fraction of slots that may be occupied.
+    max_load: f64,
+    /// Where the words too long for a key are stored.
+    arena: Arena,
+    /// The number of slots that may be occupied before the table doubles in
+    /// size.
+    capacity: usize,
+    /// A short tag from the hash of each slot's word and how far the word is
+    /// from its home slot, or 0 for an empty slot. Probing only looks at a
+    /// slot when its tag matches.
+    #[cfg(feature = "robin-hood")]
+    control: Vec<u16>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot<N> {
+    word: Option<Key>,
+    /// The hash of 'word', kept so that growing the table never hashes
+    /// anything again.
+    hash: u64,
+    count: N,
+    /// The order in which this slot's word was first seen.
+    seen: usize,
+}
+
+impl<N: Count> Slot<N> {
+    fn empty() -> Slot<N> {
+        Slot {
+            word: None,
+            hash: 0,
+            count: N::default(),
+            seen: 0,
+        }
+    }
+}
+
+impl Default for Table {
+    fn default() -> Table {
+        Table::new()
+    }
+}
+
+impl Table {
+    /// Create a new empty table.
+    pub fn new() -> Table {
+        Table::with_capacity(INITIAL_CAPACITY)
+    }
+
+    /// Create a new empty table that can hold at least `capacity` words
+    /// before it grows.
+    pub fn with_capacity(capacity: usize) -> Table {
+        Table::with_capacity_and_hasher(capacity, Fnv1::default())
+    }
+}
+
+impl<S: BuildHasher, N: Count> Table<S, N> {
+    /// Create a new empty table that hashes words with hashers built by
+    /// `hash_builder`.
+    pub fn with_hasher(hash_builder: S) -> Table<S, N> {
+        Table::with_capacity_and_hasher(INITIAL_CAPACITY, hash_builder)
+    }
+
+    /// Create a new empty table that can hold at least `capacity` words before
+    /// it grows, and that hashes words with hashers built by `hash_builder`.
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Table<S, N> {
+        let mut raw = RawTable::new(MAX_LOAD);
+        raw.reserve(capacity);
+        Table { raw, hash_builder }
+    }
+
+    /// Set the largest fraction of the table that may be occupied before it
+    /// grows. Higher wastes less memory, but makes for longer probe
+    /// sequences. The table keeps at least the capacity it had.
+    ///
+    /// # Panics
+    ///
+    /// If `load_factor` isn't strictly between 0 and 1.
+    pub fn load_factor(mut self, load_factor: f64) -> Table<S, N> {
+        assert!(
+            0.0 < load_factor && load_factor < 1.0,
+            "load factor must be in (0, 1)"
+        );
+        let raw = &mut self.raw;
+        let capacity = raw.capacity;
+        raw.max_load = load_factor;
+        raw.resize(raw.slots.len());
+        raw.reserve(capacity);
+        self
+    }
+
+    /// Returns the number of words in this table.
+    pub fn len(&self) -> usize {
+        self.raw.len
+    }
+
+    /// Returns true if there are no words in this table.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of words this table can hold before it grows.
+    pub fn capacity(&self) -> usize {
+        self.raw.capacity
+    }
+
+    /// Returns the hash builder of this table.
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// Returns the hash of `word`, as `Table::entry` expects it.
+    pub fn hash(&self, word: &[u8]) -> u64 {
+        hash_bytes(&self.hash_builder, word)
+    }
+
+    /// Returns the count of `word`, if it's in this table.
+    pub fn get(&self, word: &[u8]) -> Option<u64> {
+        let i = self.raw.find(word, self.hash(word)).ok()?;
+        Some(self.raw.slots[i].count.get())
+    }
+
+    /// Removes `word` from this table, returning its count if it was there.
+    pub fn remove(&mut self, word: &[u8]) -> Option<u64> {
+        match self.entry(word, self.hash(word)) {
+            Entry::Occupied(e) => Some(e.remove()),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    /// Removes every word from this table, keeping its capacity.
+    pub fn clear(&mut self) {
+        self.raw.clear();
+    }
+
+    /// Returns an iterator over every word in this table and its count, in no
+    /// particular order.
+    pub fn iter(&self) -> Iter<'_, N> {
+        Iter {
+            slots: self.raw.slots.iter(),
+            arena: &self.raw.arena,
+            len: self.raw.len,
+        }
+    }
+
+    /// Returns the entry for `word`, whose hash must be `hash`, for looking
+    /// its count up or changing it in place.
+    ///
+    /// The hash must be the one `Table::hash` returns for `word`, e.g., one
+    /// computed by feeding each of its bytes to `Hasher::write_u8` on a hasher
+    /// from this table's hash builder. With any other hash, the word may not
+    /// be found, or end up in the table twice.
+    pub fn entry<'a, 'w>(&'a mut self, word: &'w [u8], hash: u64) -> Entry<'a, 'w, N> {
+        let raw = &mut self.raw;
+        match raw.find(word, hash) {
+            Ok(index) => Entry::Occupied(OccupiedEntry { raw, index }),
+            Err(index) => Entry::Vacant(VacantEntry {
+                raw,
+                word,
+                hash,
+                index,
+            }),
+        }
+    }
+}
+
+impl<S: BuildHasher> Table<S> {
+    /// Adds `n` to the count of `word`, adding it to this table first with a
+    /// count of zero if it isn't there. Returns its new count.
+    ///
+    /// If the new count would overflow, then an error is returned and the
+    /// count is left alone, though the word is still added.
+    pub fn add(&mut self, word: &[u8], n: u64) -> Result<u64, CountOverflow> {
+        let hash = self.hash(word);
+        let count = self.entry(word, hash).or_insert(0);
+        *count = count
+            .checked_add(n)
+            .ok_or_else(|| CountOverflow::new(u64::MAX))?;
+        Ok(*count)
+    }
+}
+
+impl<'a, S: BuildHasher, N: Count> IntoIterator for &'a Table<S, N> {
+    type Item = (&'a [u8], u64);
+    type IntoIter = Iter<'a, N>;
+
+    fn into_iter(self) -> Iter<'a, N> {
+        self.iter()
+    }
+}
+
+impl<N: Count> RawTable<N> {
+    /// Grows the table, if needed, so that it can hold `capacity` words
+    /// without growing again.
+    fn reserve(&mut self, capacity: usize) {
+        if capacity <= self.capacity && !self.slots.is_empty() {
+            return;
+        }
+        let needed = (capacity as f64 / self.max_load).ceil() as usize;
+        // There's always at least one empty slot, which is what stops probing
+        // for a missing word from going around forever.
+        let len = needed
+            .max(capacity + 1)
+            .checked_next_power_of_two()
+            .expect("capacity overflow");
+        self.resize(len);
+    }
+
+    /// Sets the capacity for the current number of slots.
+    fn update_capacity(&mut self) {
+        let len = self.slots.len();
+        let max = (len as f64 * self.max_load) as usize;
+        self.capacity = max.clamp(self.len, len - 1);
+    }
+
+    /// Returns a slot for a word that's being seen for the first time.
+    fn new_slot(&mut self, word: &[u8], hash: u64, count: N) -> Slot<N> {
+        self.added += 1;
+        Slot {
+            word: Some(self.arena.key(word)),
+            hash,
+            count,
+            seen: self.added - 1,
+        }
+    }
+}
+
+/// An iterator over the words in a `Table` and their counts.
+#[derive(Clone, Debug)]
+pub struct Iter<'a, N = u64> {
+    slots: std::slice::Iter<'a, Slot<N>>,
+    arena: &'a Arena,
+    /// The number of words not yet yielded.
+    len: usize,
+}
+
+impl<'a, N: Count> Iterator for Iter<'a, N> {
+    type Item = (&'a [u8], u64);
+
+    fn next(&mut self) -> Option<(&'a [u8], u64)> {
+        for slot in self.slots.by_ref() {
+            if let Some(ref word) = slot.word {
+                self.len -= 1;
+                return Some((self.arena.get(word), slot.count.get()));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<N: Count> ExactSizeIterator for Iter<'_, N> {}
+
+/// A word's place in a `Table`, which it may or may not occupy.
+#[derive(Debug)]
+pub enum Entry<'a, 'w, N = u64> {
+    /// The word is in the table.
+    Occupied(OccupiedEntry<'a, N>),
+    /// The word isn't in the table.
+    Vacant(VacantEntry<'a, 'w, N>),
+}
+
+impl<'a, 'w, N: Count> Entry<'a, 'w, N> {
+    /// Returns the word of this entry.
+    pub fn key(&self) -> &[u8] {
+        match *self {
+            Entry::Occupied(ref e) => e.key(),
+            Entry::Vacant(ref e) => e.key(),
+        }
+    }
+
+    /// Returns the word's count, adding the word with a count of `count`
+    /// first if it isn't in the table.
+    pub fn or_insert(self, count: N) -> &'a mut N {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(count),
+        }
+    }
+
+    /// Calls `f` with the word's count if it's in the table.
+    pub fn and_modify(mut self, f: impl FnOnce(&mut N)) -> Entry<'a, 'w, N> {
+        if let Entry::Occupied(ref mut e) = self {
+            f(e.get_mut());
+        }
+        self
+    }
+}
+
+/// The entry of a word in a `Table`.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, N = u64> {
+    raw: &'a mut RawTable<N>,
+    index: usize,
+}
+
+impl<'a, N: Count> OccupiedEntry<'a, N> {
+    /// Returns the word of this entry.
+    pub fn key(&self) -> &[u8] {
+        // This unwrap is correct because this entry's slot is occupied.
+        let word = self.raw.slots[self.index].word.as_ref().unwrap();
+        self.raw.arena.get(word)
+    }
+
+    /// Returns the word's count.
+    pub fn get(&self) -> u64 {
+        self.raw.slots[self.index].count.get()
+    }
+
+    /// Returns a mutable reference to the word's count.
+    pub fn get_mut(&mut self) -> &mut N {
+        &mut self.raw.slots[self.index].count
+    }
+
+    /// Returns a mutable reference to the word's count that lives as long as
+    /// the borrow of the table.
+    pub fn into_mut(self) -> &'a mut N {
+        &mut self.raw.slots[self.index].count
+    }
+
+    /// Removes the word from the table, returning its count.
+    pub fn remove(self) -> u64 {
+        self.raw.remove(self.index).get()
+    }
+}
+
+/// The place in a `Table` where a word that isn't in it would go.
+#[derive(Debug)]
+pub struct VacantEntry<'a, 'w, N = u64> {
+    raw: &'a mut RawTable<N>,
+    word: &'w [u8],
+    hash: u64,
+    /// Where `RawTable::find` said the word belongs.
+    index: usize,
+}
+
+impl<'a, N: Count> VacantEntry<'a, '_, N> {
+    /// Returns the word of this entry.
+    pub fn key(&self) -> &[u8] {
+        self.word
+    }
+
+    /// Adds the word to the table with the given count, returning a mutable
+    /// reference to it.
+    pub fn insert(self, count: N) -> &'a mut N {
+        let index = self.raw.insert(self.index, self.word, self.hash, count);
+        &mut self.raw.slots[index].count
+    }
+}
+
+/// Scrambles the bits of a hash, so that the result is independent enough of
+/// it to serve as a second hash. This is the finalizer from MurmurHash3.
+pub(crate) fn mix(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^= h >> 33;
+    h
+}
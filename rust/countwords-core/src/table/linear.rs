@@ -0,0 +1,98 @@
+// Linear probing, as in the C program: a word goes in the first empty slot at
+// or after the one its hash picks, and looking it up walks from there until
+// it's found or an empty slot turns up.
+
+use super::{Arena, Count, RawTable, Slot};
+
+impl<N: Count> RawTable<N> {
+    pub(super) fn new(max_load: f64) -> RawTable<N> {
+        RawTable {
+            slots: vec![],
+            len: 0,
+            added: 0,
+            max_load,
+            arena: Arena::new(),
+            capacity: 0,
+        }
+    }
+
+    /// Returns the index of the slot holding `word`, or otherwise the index
+    /// of the empty slot where it belongs, to pass to `RawTable::insert`.
+    pub(super) fn find(&self, word: &[u8], hash: u64) -> Result<usize, usize> {
+        let mask = self.slots.len() - 1;
+        let mut index = hash as usize & mask;
+        loop {
+            let slot = &self.slots[index];
+            match slot.word {
+                None => return Err(index),
+                Some(ref key) if slot.hash == hash && self.arena.get(key) == word => {
+                    return Ok(index)
+                }
+                Some(_) => index = (index + 1) & mask,
+            }
+        }
+    }
+
+    /// Puts a word that isn't in the table in the empty slot at `index`,
+    /// where `RawTable::find` said it belongs, and returns its index, which
+    /// changes if the table grows.
+    pub(super) fn insert(&mut self, mut index: usize, word: &[u8], hash: u64, count: N) -> usize {
+        if self.len == self.capacity {
+            self.reserve(self.len + 1);
+            // This unwrap_err is correct because the word isn't in the table.
+            index = self.find(word, hash).unwrap_err();
+        }
+        self.slots[index] = self.new_slot(word, hash, count);
+        self.len += 1;
+        index
+    }
+
+    /// Empties the slot at `index`, returning its count. Every word after it
+    /// in the same run of occupied slots that would have landed in it (or
+    /// before it) moves back, so that no probe sequence is broken.
+    pub(super) fn remove(&mut self, mut index: usize) -> N {
+        let count = self.slots[index].count;
+        self.slots[index] = Slot::empty();
+        self.len -= 1;
+        let mask = self.slots.len() - 1;
+        let mut next = index;
+        loop {
+            next = (next + 1) & mask;
+            if self.slots[next].word.is_none() {
+                return count;
+            }
+            let home = self.slots[next].hash as usize & mask;
+            // How far each slot is from 'next', going back around the table.
+            let from_home = next.wrapping_sub(home) & mask;
+            let from_hole = next.wrapping_sub(index) & mask;
+            if from_home >= from_hole {
+                self.slots.swap(index, next);
+                index = next;
+            }
+        }
+    }
+
+    /// Empties every slot.
+    pub(super) fn clear(&mut self) {
+        self.slots.fill(Slot::empty());
+        self.arena.clear();
+        self.len = 0;
+        self.added = 0;
+    }
+
+    /// Moves every word to where its stored hash puts it in a table of `len`
+    /// slots, which must be a power of two bigger than the number of words.
+    pub(super) fn resize(&mut self, len: usize) {
+        let old = std::mem::replace(&mut self.slots, vec![Slot::empty(); len]);
+        let mask = len - 1;
+        for slot in old.into_iter().filter(|s| s.word.is_some()) {
+            // every word is distinct, so there's no need to compare any
+            let mut index = slot.hash as usize & mask;
+            while self.slots[index].word.is_some() {
+                index = (index + 1) & mask;
+            }
+            self.slots[index] = slot;
+        }
+        self.update_capacity();
+    }
+}
@@ -0,0 +1,176 @@
+// Robin Hood hashing (Celis, 1986). Probing is still linear, but a new word
+// takes the slot of any word it meets that's closer to its own home slot than
+// the new word is to its own, and that word carries on probing instead. So no
+// word ends up much further from home than any other, which keeps the longest
+// probe sequences short as the table fills up, where plain linear probing
+// lets a few of them grow very long. It also means that looking for a word
+// can stop as soon as it meets one that's closer to home than the word would
+// be at that point, since the word would have taken that slot.
+//
+// Each slot gets two bytes of control in an array of their own: a tag taken
+// from its word's hash, and how far the word is from home. Probing walks the
+// control bytes, and only looks at the slot itself (and so compares words)
+// when the tag matches, which with seven bits of hash is one time in 128 for a
+// word that isn't the one being looked for. The control bytes of a whole probe
+// sequence are usually in one cache line.
+//
+// Removing a word shifts the words after it back by one slot, until one that
+// is already home, which leaves the table as if the word was never inserted.
+
+use super::{Arena, Count, RawTable, Slot};
+
+/// The control of an empty slot.
+const EMPTY: u16 = 0;
+/// The largest distance from home kept in a slot's control. Further ones are
+/// computed from the hash in the slot when needed.
+const MAX_DISTANCE: usize = 0xFF;
+
+/// Returns the tag for a word with the given hash. The top bit is always set,
+/// so that a control is never `EMPTY`. The rest come from the top of the
+/// hash, since the bottom picks the home slot and so is mostly the same for
+/// words that meet.
+fn tag(hash: u64) -> u16 {
+    ((hash >> 57) as u16 | 0x80) << 8
+}
+
+/// Returns the control for a word with the given tag that's `distance` slots
+/// from home.
+fn control(tag: u16, distance: usize) -> u16 {
+    tag | distance.min(MAX_DISTANCE) as u16
+}
+
+impl<N: Count> RawTable<N> {
+    pub(super) fn new(max_load: f64) -> RawTable<N> {
+        RawTable {
+            slots: vec![],
+            len: 0,
+            added: 0,
+            max_load,
+            arena: Arena::new(),
+            capacity: 0,
+            control: vec![],
+        }
+    }
+
+    /// Returns the index of the slot holding `word`, or otherwise where the
+    /// search for it stopped, which `RawTable::insert` doesn't need.
+    pub(super) fn find(&self, word: &[u8], hash: u64) -> Result<usize, usize> {
+        let mask = self.slots.len() - 1;
+        let tag = tag(hash);
+        let mut index = hash as usize & mask;
+        let mut distance = 0;
+        loop {
+            let control = self.control[index];
+            if control == EMPTY {
+                return Err(index);
+            }
+            let theirs = (control & 0xFF) as usize;
+            if theirs < distance.min(MAX_DISTANCE) {
+                return Err(index);
+            }
+            if control & 0xFF00 == tag {
+                let slot = &self.slots[index];
+                if slot.hash == hash && slot.word.is_some_and(|key| self.arena.get(&key) == word) {
+                    return Ok(index);
+                }
+            }
+            index = (index + 1) & mask;
+            distance += 1;
+        }
+    }
+
+    /// Puts a word that isn't in the table where it belongs, and returns its
+    /// index.
+    pub(super) fn insert(&mut self, _: usize, word: &[u8], hash: u64, count: N) -> usize {
+        if self.len == self.capacity {
+            self.reserve(self.len + 1);
+        }
+        let slot = self.new_slot(word, hash, count);
+        self.len += 1;
+        self.place(slot)
+    }
+
+    /// Puts a slot for a word that isn't in the table where it belongs,
+    /// moving along any words that are closer to home, and returns its index.
+    fn place(&mut self, mut slot: Slot<N>) -> usize {
+        let mask = self.slots.len() - 1;
+        let mut index = slot.hash as usize & mask;
+        let mut tag = tag(slot.hash);
+        // How far 'slot' is from home.
+        let mut distance = 0;
+        let mut placed = None;
+        loop {
+            if self.control[index] == EMPTY {
+                self.slots[index] = slot;
+                self.control[index] = control(tag, distance);
+                return placed.unwrap_or(index);
+            }
+            let theirs = self.distance(index);
+            if theirs < distance {
+                std::mem::swap(&mut self.slots[index], &mut slot);
+                let old = std::mem::replace(&mut self.control[index], control(tag, distance));
+                tag = old & 0xFF00;
+                placed.get_or_insert(index);
+                distance = theirs;
+            }
+            index = (index + 1) & mask;
+            distance += 1;
+        }
+    }
+
+    /// Returns how far the word in the occupied slot at `index` is from its
+    /// home slot.
+    fn distance(&self, index: usize) -> usize {
+        let distance = (self.control[index] & 0xFF) as usize;
+        if distance < MAX_DISTANCE {
+            return distance;
+        }
+        let mask = self.slots.len() - 1;
+        let home = self.slots[index].hash as usize & mask;
+        index.wrapping_sub(home) & mask
+    }
+
+    /// Empties the slot at `index`, returning its count, and moves every word
+    /// after it that isn't home back by one slot.
+    pub(super) fn remove(&mut self, mut index: usize) -> N {
+        let count = self.slots[index].count;
+        self.slots[index] = Slot::empty();
+        self.control[index] = EMPTY;
+        self.len -= 1;
+        let mask = self.slots.len() - 1;
+        loop {
+            let next = (index + 1) & mask;
+            if self.control[next] == EMPTY {
+                return count;
+            }
+            let distance = self.distance(next);
+            if distance == 0 {
+                return count;
+            }
+            self.slots.swap(index, next);
+            self.control[index] = control(self.control[next] & 0xFF00, distance - 1);
+            self.control[next] = EMPTY;
+            index = next;
+        }
+    }
+
+    /// Empties every slot.
+    pub(super) fn clear(&mut self) {
+        self.slots.fill(Slot::empty());
+        self.control.fill(EMPTY);
+        self.arena.clear();
+        self.len = 0;
+        self.added = 0;
+    }
+
+    /// Moves every word to where its stored hash puts it in a table of `len`
+    /// slots, which must be a power of two bigger than the number of words.
+    pub(super) fn resize(&mut self, len: usize) {
+        let old = std::mem::replace(&mut self.slots, vec![Slot::empty(); len]);
+        self.control = vec![EMPTY; len];
+        for slot in old.into_iter().filter(|s| s.word.is_some()) {
+            self.place(slot);
+        }
+        self.update_capacity();
+    }
+}
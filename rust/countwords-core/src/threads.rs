@@ -0,0 +1,226 @@
+// This version is fast-simple, attempting to use threading: one thread splits
+// buffers into words and another counts them, while the caller keeps reading
+// (and lowercasing) the next buffer.
+//
+// The word splitting thread sends the words of each buffer together, as
+// `Key`s, so that a short word costs neither an allocation nor a trip through
+// the channel of its own.
+//
+// Each input is counted by threads of its own, which are joined at its end.
+// So an error from either thread is reported for the input that caused it,
+// and the inputs that follow are still counted.
+//
+// Update, RBS 07/26/2022: Meat of the changes made are about trying to do something similar to
+// the optimized version without doing anything unsafe/unchecked, which feels like readable, relatively
+// understandable/simple, idiomatic Rust (nothing too galaxy brained).
+
+use std::{io, thread::JoinHandle};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::{
+    fast_simple::HASHMAP_INITIAL_CAPACITY,
+    key::{Arena, Key, KeyMap},
+    lines::{invalid_utf8, LineBuffer},
+    Count, Feed, WordCounter,
+};
+
+/// Each word's count and the order in which it was first seen. There's only
+/// one counting thread, so this is the order of the input.
+type Counts<N> = KeyMap<(N, usize)>;
+
+/// The words of one buffer, in order, and the arena their long ones spilled
+/// to.
+type Words = (Vec<Key>, Arena);
+
+/// Counts words separated by ASCII whitespace, lowercased with ASCII rules,
+/// in a `KeyMap` that is filled by a pipeline of threads. The
+/// input must be valid UTF-8.
+///
+/// Each input gets threads of its own, which are joined when it ends, so
+/// counts are only available once `end_input` or `finish` has been called.
+#[derive(Debug)]
+pub struct Threads<N = u64> {
+    lines: LineBuffer,
+    /// The threads counting the current input, if any of it has been fed.
+    workers: Option<Workers<N>>,
+    /// The counts of every input whose threads have been joined. The
+    /// counting thread of the current input has them in the meantime.
+    counts: Counts<N>,
+}
+
+/// The pair of threads that counts one input.
+#[derive(Debug)]
+struct Workers<N> {
+    /// Sends lowercased buffers to the word splitting thread.
+    tx: Sender<Vec<u8>>,
+    words: JoinHandle<io::Result<()>>,
+    /// Hands back the counts it was given, along with the error that stopped
+    /// it early, if any.
+    counter: JoinHandle<(Counts<N>, io::Result<()>)>,
+}
+
+impl<N: Count> Default for Threads<N> {
+    fn default() -> Threads<N> {
+        Threads::with_capacity(HASHMAP_INITIAL_CAPACITY)
+    }
+}
+
+impl Threads {
+    /// Create a new empty counter with u64 counts.
+    pub fn new() -> Threads {
+        Threads::default()
+    }
+}
+
+impl<N: Count> Threads<N> {
+    /// Create a new empty counter that can hold at least `capacity` distinct
+    /// words before its map grows.
+    pub fn with_capacity(capacity: usize) -> Threads<N> {
+        Threads {
+            lines: LineBuffer::default(),
+            workers: None,
+            counts: KeyMap::with_capacity(capacity),
+        }
+    }
+
+    /// Sends whatever is left of the current input and waits for its threads
+    /// to count it.
+    fn end(&mut self) -> io::Result<()> {
+        if self.workers.is_none() && self.lines.is_empty() {
+            return Ok(());
+        }
+        let counts = &mut self.counts;
+        let workers = self
+            .workers
+            .get_or_insert_with(|| Workers::spawn(std::mem::take(counts)));
+        // If this fails, then joining will tell us why.
+        let _ = self
+            .lines
+            .finish(|bytes_buffer| send(&workers.tx, bytes_buffer));
+        self.join()
+    }
+
+    /// Hangs up on the word splitting thread of the current input and waits
+    /// for both of its threads to finish. The error of whichever one failed,
+    /// if any, is returned.
+    fn join(&mut self) -> io::Result<()> {
+        let Some(workers) = self.workers.take() else {
+            return Ok(());
+        };
+        drop(workers.tx);
+        let result = workers
+            .words
+            .join()
+            .expect("word splitting thread panicked");
+        let (counts, counted) = workers.counter.join().expect("counting thread panicked");
+        self.counts = counts;
+        result.and(counted)
+    }
+}
+
+impl<N: Count> Workers<N> {
+    /// Spawns a word splitting thread and a thread that adds the words it
+    /// splits to `counts`.
+    fn spawn(mut counts: Counts<N>) -> Workers<N> {
+        let (tx1, rx1) = unbounded();
+        let (tx2, rx2) = unbounded();
+
+        let words = std::thread::spawn(move || ready_words(rx1, tx2));
+        let counter = std::thread::spawn(move || {
+            let result = count_words(&mut counts, rx2);
+            (counts, result)
+        });
+        Workers {
+            tx: tx1,
+            words,
+            counter,
+        }
+    }
+}
+
+impl<N: Count> Feed for Threads<N> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let counts = &mut self.counts;
+        let workers = self
+            .workers
+            .get_or_insert_with(|| Workers::spawn(std::mem::take(counts)));
+        let sent = self
+            .lines
+            .feed(chunk, |bytes_buffer| send(&workers.tx, bytes_buffer));
+        if sent.is_err() {
+            // The word splitting thread only hangs up early when it or the
+            // counting thread fails. Either way, the rest of this input gets
+            // new threads, if it's fed any more.
+            self.join()?;
+        }
+        sent
+    }
+
+    fn end_input(&mut self) -> io::Result<()> {
+        // Joining here means that an error is returned for the input that
+        // caused it, rather than whichever one is fed next.
+        self.end()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.end()
+    }
+}
+
+impl<N: Count> WordCounter for Threads<N> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        for (word, &(count, seen)) in self.counts.iter() {
+            f(word, count.get(), seen);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.counts.len()
+    }
+}
+
+fn send(tx: &Sender<Vec<u8>>, bytes_buffer: &mut Vec<u8>) -> io::Result<()> {
+    // make_ascii_lowercase on str requires a call to as_bytes(), so use here on
+    // directly bytes, but there doesn't seem to be perf advantage
+    bytes_buffer.make_ascii_lowercase();
+    tx.send(std::mem::take(bytes_buffer))
+        .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "word splitting thread hung up"))
+}
+
+fn ready_words(rx: Receiver<Vec<u8>>, tx: Sender<Words>) -> io::Result<()> {
+    while let Ok(bytes_buffer) = rx.recv() {
+        let mut arena = Arena::new();
+        let keys = std::str::from_utf8(&bytes_buffer)
+            .map_err(invalid_utf8)?
+            .split_ascii_whitespace()
+            .map(|word| arena.key(word.as_bytes()))
+            .collect();
+        // the counting thread only hangs up early when it fails, which
+        // joining will report
+        if tx.send((keys, arena)).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn count_words<N: Count>(counts: &mut Counts<N>, rx: Receiver<Words>) -> io::Result<()> {
+    while let Ok((keys, arena)) = rx.recv() {
+        for key in keys.iter() {
+            increment(counts, arena.get(key))?;
+        }
+    }
+    Ok(())
+}
+
+fn increment<N: Count>(counts: &mut Counts<N>, word: &[u8]) -> io::Result<()> {
+    // One hash lookup, and a new word is only copied into its key (and into
+    // the map's arena, if it's long).
+    let seen = counts.len();
+    counts
+        .get_or_insert_with(word, || (N::default(), seen))
+        .0
+        .increment()?;
+    Ok(())
+}
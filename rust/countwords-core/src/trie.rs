@@ -0,0 +1,549 @@
+// This version was an experiment to write a faster version of the word
+// counting program by using a trie. Ultimately, while it's pretty fast, it's
+// not as fast the optimized Go, Rust or C versions using a standard hashmap.
+// My hypothesis was that the trie avoids the need to hash each word, and thus,
+// let's us inspect each byte only once. But this comes at the expense of an
+// additional memory operation for each byte (to traverse the trie). I made
+// that as cheap as I could, but alas, profiling seems to suggest it is still
+// too expensive.
+//
+// Of course, the trie in this program is quite reckless in terms of memory
+// usage. But, it only scales with the number of unique words, and memory usage
+// doesn't appear to be a metric in this particular exercise.
+//
+// Also, since the trie is a finite state machine itself, it somewhat
+// simplifies the buffer handling in the code below. There's no need to keep
+// track of incomplete words/lines. You just advance the state machine. Alas,
+// this doesn't really help with performance, since the tracking is only done
+// once per buffer read.
+//
+// Nodes only have room for the 128 ASCII bytes, which keeps them small. The
+// rest of the byte range goes through an escape: a byte of 128 or more is a
+// step to a child reserved for high bytes, and then another for its low seven
+// bits. Bytes 0 and 1 are escaped the same way through a second reserved
+// child, since their own children are the ones reserved for escapes. So
+// UTF-8 (or any other bytes) is counted correctly, at the cost of an extra
+// node for each non-ASCII byte, while ASCII words take one step per byte.
+//
+// Node IDs are plain node numbers rather than premultiplied by the size of a
+// node. Premultiplying would save a shift per byte, but a u32 would run out at
+// 2^32/128 nodes, about 33 million, while plain numbers let the node table run
+// out of memory long before they run out of bits.
+//
+// Walking the trie visits words in byte order, which is what alphabetical
+// output wants anyway, so `TrieWords` hands them out in that order straight
+// from the walk, and `Select` skips sorting them. See `WordCounter::is_ordered`.
+
+use std::{convert::TryFrom, io};
+
+use crate::{Count, Feed, Select, WordCounter};
+
+pub use self::index::TrieIndex;
+
+mod index;
+
+/// Counts words separated by ASCII spaces, newlines and carriage returns,
+/// lowercased with ASCII rules, in a trie.
+///
+/// Any bytes other than those are counted, but words with bytes outside of
+/// ASCII take more memory.
+///
+/// Counts are u32 by default, since there's one for every node.
+#[derive(Clone, Debug)]
+pub struct TrieCounter<N = u32> {
+    counts: Trie<N>,
+    /// The node reached by the incomplete word at the end of the last chunk.
+    node_id: TrieNodeID,
+    /// The number of distinct words, i.e., nodes with a non-zero count.
+    len: usize,
+}
+
+impl<N: Count> Default for TrieCounter<N> {
+    fn default() -> TrieCounter<N> {
+        let counts = Trie::new();
+        let node_id = counts.root();
+        TrieCounter {
+            counts,
+            node_id,
+            len: 0,
+        }
+    }
+}
+
+impl TrieCounter {
+    /// Create a new empty counter with u32 counts.
+    pub fn new() -> TrieCounter {
+        TrieCounter::default()
+    }
+}
+
+impl<N: Count> TrieCounter<N> {
+    /// Calls `f` with every word that starts with `prefix`, including
+    /// `prefix` itself if it was counted, along with its count and the order
+    /// in which it was first seen, in byte order.
+    ///
+    /// The prefix is lowercased with ASCII rules first, like counted words
+    /// are.
+    pub fn for_each_completion(&self, prefix: &[u8], f: &mut dyn FnMut(&[u8], u64, usize)) {
+        let prefix = prefix.to_ascii_lowercase();
+        if let Some(id) = self.counts.find(&prefix) {
+            self.counts
+                .for_each_from(id, &prefix, &mut |word, count, seen| {
+                    f(word, count, seen as usize)
+                });
+        }
+    }
+
+    /// Returns the words that start with `prefix` and their counts, as
+    /// selected by `select`. E.g., `Select::new(Sort::Count).top(10)` picks
+    /// the ten most frequent, as suggestions for completing the prefix.
+    ///
+    /// The prefix is lowercased with ASCII rules first, like counted words
+    /// are.
+    pub fn completions(&self, prefix: &[u8], select: &Select) -> Vec<(Vec<u8>, u64)> {
+        select.apply_each(None, |f| self.for_each_completion(prefix, f))
+    }
+
+    /// Returns the total count of every word that starts with `prefix`, and
+    /// how many distinct words that is. The total stops at `u64::MAX`.
+    ///
+    /// The prefix is lowercased with ASCII rules first, like counted words
+    /// are.
+    pub fn prefix_count(&self, prefix: &[u8]) -> (u64, usize) {
+        let (mut total, mut len) = (0u64, 0);
+        self.for_each_completion(prefix, &mut |_, count, _| {
+            total = total.saturating_add(count);
+            len += 1;
+        });
+        (total, len)
+    }
+
+    /// Returns every word counted so far and its count, in byte order,
+    /// without copying any of them.
+    pub fn words(&self) -> TrieWords<'_, N> {
+        TrieWords {
+            trie: &self.counts,
+            walk: Walk::new(self.counts.root(), &[]),
+        }
+    }
+
+    /// Returns the number of bytes of memory allocated by this counter, not
+    /// counting itself.
+    pub fn memory_usage(&self) -> usize {
+        let trie = &self.counts;
+        trie.nodes.capacity() * std::mem::size_of::<Option<TrieNodeID>>()
+            + trie.counts.capacity() * std::mem::size_of::<N>()
+            + trie.seen.capacity() * std::mem::size_of::<u32>()
+    }
+
+    fn increment(&mut self) -> io::Result<()> {
+        let node_id = std::mem::replace(&mut self.node_id, self.counts.root());
+        if self.counts.increment(node_id)? == 1 {
+            self.counts.set_seen(node_id, self.len);
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<N: Count> Feed for TrieCounter<N> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        for &b in chunk {
+            if b == b' ' || b == b'\n' || b == b'\r' {
+                if !self.counts.is_root(self.node_id) {
+                    self.increment()?;
+                }
+            } else {
+                self.node_id = self.counts.add_child(self.node_id, b.to_ascii_lowercase());
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.counts.is_root(self.node_id) {
+            self.increment()?;
+        }
+        Ok(())
+    }
+}
+
+impl<N: Count> WordCounter for TrieCounter<N> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        let root = self.counts.root();
+        self.counts
+            .for_each_from(root, &[], &mut |word, count, seen| {
+                f(word, count, seen as usize)
+            });
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_ordered(&self) -> bool {
+        true
+    }
+}
+
+/// The words counted by a `TrieCounter` and their counts, in byte order. See
+/// `TrieCounter::words`.
+///
+/// This isn't an `Iterator`, since each word it returns is borrowed from a
+/// buffer that's reused for the next one. That way, no word is ever copied.
+#[derive(Clone, Debug)]
+pub struct TrieWords<'t, N> {
+    trie: &'t Trie<N>,
+    walk: Walk,
+}
+
+impl<'t, N: Count> TrieWords<'t, N> {
+    /// Returns the next word and its count, or None once every word has been
+    /// returned.
+    pub fn next_word(&mut self) -> Option<(&[u8], u64)> {
+        let (word, count, _) = self.walk.next(self.trie)?;
+        Some((word, count))
+    }
+}
+
+/// Returns the child that a byte is escaped through and the index of its
+/// child from there, or None if the byte isn't escaped.
+fn escape(b: u8) -> Option<(usize, usize)> {
+    if b as usize >= NODE_SIZE {
+        Some((HIGH_ESCAPE as usize, b as usize - NODE_SIZE))
+    } else if b <= LOW_ESCAPE {
+        Some((LOW_ESCAPE as usize, b as usize))
+    } else {
+        None
+    }
+}
+
+/// Since the challenge only requires dealing with ASCII, we can keep out
+/// NODE_SIZE to the ASCII range of bytes. This halves the size of the trie
+/// node table.
+///
+/// Technically, there are only 47 unique bytes in the input, so this could in
+/// theory be halved again. But you'd need some way to (very quickly) map the
+/// bytes into the smaller range, since the maximum byte value is 122. At this
+/// point, I think it probably starts violating the spirit of the challenge
+/// anyway. Coupling the program too tightly to a specific input is bad juju.
+///
+/// (At the very least, this should be a power-of-2. Otherwise, shifts get
+/// turned into more costly DIV instructions.)
+///
+/// Bytes outside of this range are escaped. See `Trie::add_child`.
+const NODE_SIZE: usize = 128;
+
+/// The child that a byte of 128 or more goes through, before the child for
+/// the rest of the byte.
+const HIGH_ESCAPE: u8 = 0;
+/// The child that bytes 0 and 1 go through, before the child for the byte
+/// itself. These bytes can't have children of their own, since those are
+/// the escapes.
+const LOW_ESCAPE: u8 = 1;
+
+/// The number of a node in a trie. Given a node ID 'N' and a byte 'b', its
+/// child is at 'trie.nodes[(N * NODE_SIZE) + b]'. Since NODE_SIZE is a power
+/// of 2, the multiplication is a shift. (See the comments at the top for why
+/// these aren't premultiplied.)
+///
+/// We use a u32 here instead of usize to cut the size of the trie node table
+/// in half.
+///
+/// Also, note that Option<NonZeroU32> is the same size in memory as a u32!
+///
+/// We could use a NonZeroU16 here to halve the table size again, but then
+/// the trie would be limited to 65535 nodes. In experiments, it looks like a
+/// wash anyway.
+type TrieNodeID = std::num::NonZeroU32;
+
+/// A trie that stores its nodes contiguously in memory.
+#[derive(Clone, Debug)]
+struct Trie<N> {
+    /// A row-major contiguous allocation of trie nodes.
+    ///
+    /// The performance of this program seems highly sensitive to how big this
+    /// table is in memory. The smaller it is, the higher our cache hit rate
+    /// and the faster our program. See comments above for how NODE_SIZE and
+    /// the representation of TrieNodeID impact this.
+    ///
+    /// It's not quite clear how to shrink the table more. It's possible that
+    /// a strategy based on the frequency distribution of bytes that we see
+    /// (e.g., b'a'-b'z' are overwhelmingly the most common). That is, we might
+    /// have a separate table for common bytes and another larger one to handle
+    /// the rest. If most accesses are in the smaller table, then we likely
+    /// will increase our cache hit rate. The problem with this strategy is
+    /// tying everything together without introducing more overhead. Alas, I
+    /// ran out of time.
+    nodes: Vec<Option<TrieNodeID>>,
+    /// A count exists for each node in the trie. For a node ID 'N', one can
+    /// get its count via 'counts[N]'.
+    counts: Vec<N>,
+    /// Parallel to 'counts', the order in which the word ending at each node
+    /// was first seen. This is meaningless for nodes with a zero count.
+    seen: Vec<u32>,
+}
+
+/// The nodes of a trie, as finding and walking its words needs them. Both
+/// `Trie` and a `TrieIndex` loaded from one have them, so the code that does
+/// that is shared.
+trait Nodes {
+    /// Returns the child node ID of the given current node at the given
+    /// index, which must be less than NODE_SIZE. If there is no child for the
+    /// given args, then None is returned.
+    fn child(&self, current_id: TrieNodeID, index: usize) -> Option<TrieNodeID>;
+
+    /// Returns the count of the word ending at the given node, and the order
+    /// in which it was first seen.
+    fn word(&self, id: TrieNodeID) -> (u64, u32);
+
+    /// Return the ID of the root node of this trie.
+    fn root(&self) -> TrieNodeID {
+        TrieNodeID::new(1).unwrap()
+    }
+
+    /// Returns the node that the given bytes lead to from the root, if any
+    /// word in this trie starts with them.
+    fn find(&self, bytes: &[u8]) -> Option<TrieNodeID> {
+        let mut id = self.root();
+        for &b in bytes {
+            id = match escape(b) {
+                None => self.child(id, b as usize)?,
+                Some((escape, rest)) => self.child(self.child(id, escape)?, rest)?,
+            };
+        }
+        Some(id)
+    }
+
+    /// Calls `f` with every word that starts with `prefix`, whose node is
+    /// `id`, along with its count and the order in which it was first seen,
+    /// in byte order.
+    fn for_each_from(&self, id: TrieNodeID, prefix: &[u8], f: &mut dyn FnMut(&[u8], u64, u32)) {
+        let mut walk = Walk::new(id, prefix);
+        while let Some((word, count, seen)) = walk.next(self) {
+            f(word, count, seen);
+        }
+    }
+}
+
+/// A walk over the words under one node of a trie, in byte order. It doesn't
+/// hold onto the trie, so that it can walk a `Trie` or a `TrieIndex`, and be
+/// wrapped by `TrieWords`.
+///
+/// A node's word comes before the words under it, and its children are
+/// visited in the order of their bytes. Since the escapes are the first two
+/// children, that's not the order of their indices: bytes 0 and 1 come first,
+/// through LOW_ESCAPE, then the ASCII children, then the bytes of 128 or more,
+/// through HIGH_ESCAPE.
+#[derive(Clone, Debug)]
+struct Walk {
+    /// The node the walk starts at, until its word has been looked at.
+    start: Option<TrieNodeID>,
+    /// The nodes whose children are being visited, innermost last.
+    stack: Vec<Visit>,
+    /// The word ending at the node last visited.
+    word: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Visit {
+    id: TrieNodeID,
+    /// The position, in byte order, of the next child to try.
+    next: usize,
+    /// For the child of an escape, what to add to the index of each of its
+    /// children to get their byte. None for every other node.
+    escaped: Option<u8>,
+    /// The length of the word before this node's byte was added to it.
+    len: usize,
+}
+
+impl Walk {
+    fn new(id: TrieNodeID, prefix: &[u8]) -> Walk {
+        Walk {
+            start: Some(id),
+            stack: vec![],
+            word: prefix.to_vec(),
+        }
+    }
+
+    /// Returns the next word with a non-zero count, along with its count and
+    /// the order in which it was first seen.
+    fn next<T: Nodes + ?Sized>(&mut self, trie: &T) -> Option<(&[u8], u64, u32)> {
+        if let Some(id) = self.start.take() {
+            self.stack.push(Visit {
+                id,
+                next: 0,
+                escaped: None,
+                len: self.word.len(),
+            });
+            let (count, seen) = trie.word(id);
+            if count > 0 {
+                return Some((&self.word, count, seen));
+            }
+        }
+        loop {
+            let visit = self.stack.last_mut()?;
+            let child = (visit.next..NODE_SIZE).find_map(|pos| {
+                let index = child_index(pos, visit.escaped.is_some());
+                trie.child(visit.id, index).map(|id| (pos, index, id))
+            });
+            let Some((pos, index, id)) = child else {
+                let done = self.stack.pop().unwrap();
+                self.word.truncate(done.len);
+                continue;
+            };
+            visit.next = pos + 1;
+            let (escaped, len) = (visit.escaped, self.word.len());
+            let escape = match escaped {
+                None if index == HIGH_ESCAPE as usize => Some(NODE_SIZE as u8),
+                None if index == LOW_ESCAPE as usize => Some(0),
+                _ => None,
+            };
+            if escape.is_some() {
+                // Reaching the child of an escape doesn't add to the word.
+                self.stack.push(Visit {
+                    id,
+                    next: 0,
+                    escaped: escape,
+                    len,
+                });
+                continue;
+            }
+            self.word.push(index as u8 + escaped.unwrap_or(0));
+            self.stack.push(Visit {
+                id,
+                next: 0,
+                escaped: None,
+                len,
+            });
+            let (count, seen) = trie.word(id);
+            if count > 0 {
+                return Some((&self.word, count, seen));
+            }
+        }
+    }
+}
+
+/// Returns the index of the child at the given position in byte order, for
+/// the child of an escape if `escaped`, and for any other node if not.
+fn child_index(pos: usize, escaped: bool) -> usize {
+    if escaped {
+        // The children of an escape are already in byte order.
+        return pos;
+    }
+    match pos {
+        0 => LOW_ESCAPE as usize,
+        _ if pos < NODE_SIZE - 1 => pos + 1,
+        _ => HIGH_ESCAPE as usize,
+    }
+}
+
+impl<N: Count> Nodes for Trie<N> {
+    fn child(&self, current_id: TrieNodeID, index: usize) -> Option<TrieNodeID> {
+        debug_assert!(index < NODE_SIZE);
+        self.nodes[offset(current_id) + index]
+    }
+
+    fn word(&self, id: TrieNodeID) -> (u64, u32) {
+        let id = id.get() as usize;
+        (self.counts[id].get(), self.seen[id])
+    }
+}
+
+impl<N: Count> Trie<N> {
+    /// Create a new empty trie.
+    fn new() -> Trie<N> {
+        let mut trie = Trie {
+            nodes: vec![],
+            counts: vec![],
+            seen: vec![],
+        };
+        // add dummy node with id==0. This is never used. We explicitly add it
+        // to avoid needing to subtract 1 on every node lookup. The root starts
+        // at id==1.
+        trie.nodes.extend(std::iter::repeat_n(None, NODE_SIZE));
+        trie.counts.push(N::default());
+        trie.seen.push(0);
+        trie.alloc_node(); // root node
+        trie
+    }
+
+    /// Adds a transition from current_id for the given byte to a child node
+    /// if one doesn't exist. Either way, the corresponding child node ID is
+    /// returned.
+    ///
+    /// A byte that isn't ASCII, or that is one of the escapes, takes two
+    /// transitions: one to its escape's child, and one from there for the
+    /// rest of the byte. Only the node at the end of the second one may be
+    /// counted, which happens naturally since words are fed whole bytes.
+    fn add_child(&mut self, current_id: TrieNodeID, b: u8) -> TrieNodeID {
+        if b > LOW_ESCAPE && (b as usize) < NODE_SIZE {
+            return self.add_index(current_id, b as usize);
+        }
+        self.add_escaped(current_id, b)
+    }
+
+    #[cold]
+    fn add_escaped(&mut self, current_id: TrieNodeID, b: u8) -> TrieNodeID {
+        let (escape, rest) = escape(b).unwrap();
+        let escaped_id = self.add_index(current_id, escape);
+        self.add_index(escaped_id, rest)
+    }
+
+    /// Like `add_child`, but for the child at the given index into the node,
+    /// which must be less than NODE_SIZE.
+    fn add_index(&mut self, current_id: TrieNodeID, index: usize) -> TrieNodeID {
+        match self.child(current_id, index) {
+            Some(child) => child,
+            None => self.alloc_child(current_id, index),
+        }
+    }
+
+    /// Allocate a new child for the given args and return its ID.
+    fn alloc_child(&mut self, current_id: TrieNodeID, index: usize) -> TrieNodeID {
+        debug_assert!(index < NODE_SIZE);
+        let child = self.alloc_node();
+        self.nodes[offset(current_id) + index] = Some(child);
+        child
+    }
+
+    /// Allocates a new node in this trie (with all children empty) and returns
+    /// its ID.
+    fn alloc_node(&mut self) -> TrieNodeID {
+        // 2^32 nodes would take 2 TiB for their children alone, so this only
+        // fails if the allocation below would have.
+        let id = u32::try_from(self.counts.len()).expect("too many trie nodes");
+        // This unwrap is correct since new() allocates space for a dummy node,
+        // so self.counts.len() is always at least 1 and thus id is always
+        // greater than 0.
+        let id = TrieNodeID::new(id).unwrap();
+        self.nodes.extend(std::iter::repeat_n(None, NODE_SIZE));
+        self.counts.push(N::default());
+        self.seen.push(0);
+        id
+    }
+
+    /// Increments the count of the given node and returns its new count.
+    fn increment(&mut self, id: TrieNodeID) -> io::Result<u64> {
+        let count = &mut self.counts[id.get() as usize];
+        count.increment()?;
+        Ok(count.get())
+    }
+
+    /// Records the order in which the word ending at the given node was first
+    /// seen.
+    fn set_seen(&mut self, id: TrieNodeID, seen: usize) {
+        // There are fewer words than nodes, and node IDs fit in a u32.
+        self.seen[id.get() as usize] = seen as u32;
+    }
+
+    fn is_root(&self, id: TrieNodeID) -> bool {
+        id.get() == 1
+    }
+}
+
+/// Returns the index of the first child of the given node in a trie's node
+/// table.
+fn offset(id: TrieNodeID) -> usize {
+    id.get() as usize * NODE_SIZE
+}
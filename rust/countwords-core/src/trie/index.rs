@@ -0,0 +1,264 @@
+// A `TrieIndex` is a counted trie written to a file, so that it can be queried
+// later without counting the corpus again. The file is the trie's own node
+// table, counts and first seen order, back to back, so loading it is just
+// mapping it into memory: lookups read the bytes they need straight from the
+// map, and only the pages a query touches are ever read from disk.
+//
+// The format, with every integer little endian:
+//
+//   magic      8 bytes, "cwtrie\0\0"
+//   version    u32
+//   checksum   u32, the CRC-32 of everything after the header
+//   nodes      u64, the number of nodes, including the unused node 0
+//   words      u64, the number of distinct words
+//   children   nodes * NODE_SIZE u32s, each a node ID or 0 for none
+//   counts     nodes u64s
+//   seen       nodes u32s
+//
+// Every child has a bigger ID than its parent, since nodes are only ever
+// allocated as the child of an existing one. Lookups rely on that instead of
+// trusting the file: a child that doesn't have a bigger ID, or that's past
+// the last node, is treated as missing, so no file can make a walk loop or
+// read out of bounds.
+//
+// Opening an index only checks its header and size, which is what makes it
+// safe to read. Checking the checksum means reading the whole file, which
+// would cost every query a pass over what may be gigabytes, so that's left
+// to `TrieIndex::verify`, for when a file may have been damaged.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use memmap2::Mmap;
+
+use super::{Nodes, TrieCounter, TrieNodeID, NODE_SIZE};
+use crate::{Count, Select};
+
+/// The magic bytes at the start of every index.
+const MAGIC: &[u8; 8] = b"cwtrie\0\0";
+/// The version of the format written by `TrieCounter::write_index`.
+const VERSION: u32 = 1;
+/// The size of the header, which keeps everything after it aligned.
+const HEADER_LEN: usize = 32;
+
+/// The words counted by a `TrieCounter`, loaded from a file written by
+/// `TrieCounter::write_index`, for looking up counts and completions.
+///
+/// The bytes of the file are used where they are, so opening an index only
+/// reads its header. A damaged index gives wrong answers rather than
+/// failing, unless it's checked with `TrieIndex::verify`.
+#[derive(Debug)]
+pub struct TrieIndex<B = Mmap> {
+    bytes: B,
+    /// The CRC-32 of everything after the header, from the header.
+    checksum: u32,
+    /// The number of nodes, including the unused node 0.
+    nodes: usize,
+    /// The number of distinct words.
+    words: usize,
+}
+
+impl TrieIndex<Mmap> {
+    /// Memory maps the index at the given path and checks its header.
+    ///
+    /// The file must not be changed while the index is open.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<TrieIndex<Mmap>> {
+        let file = File::open(path)?;
+        // SAFETY: an index is only ever read through this map, and we ask
+        // that the file isn't changed while it is. Even then, any bytes at
+        // all are handled safely, just not with sensible results.
+        let map = unsafe { Mmap::map(&file)? };
+        TrieIndex::new(map)
+    }
+}
+
+impl<B: AsRef<[u8]>> TrieIndex<B> {
+    /// Checks that the given bytes look like an index written by
+    /// `TrieCounter::write_index`, i.e., that its header is valid and that
+    /// it's the size the header says, and returns it.
+    pub fn new(bytes: B) -> io::Result<TrieIndex<B>> {
+        let data = bytes.as_ref();
+        if data.len() < HEADER_LEN || &data[..8] != MAGIC {
+            return Err(invalid_index("not a trie index"));
+        }
+        let version = read_u32(data, 8);
+        if version != VERSION {
+            let msg = format!("unsupported index version {}", version);
+            return Err(invalid_index(&msg));
+        }
+        let checksum = read_u32(data, 12);
+        let nodes = usize::try_from(read_u64(data, 16)).ok();
+        let words = usize::try_from(read_u64(data, 24)).ok();
+        let len = nodes.and_then(|nodes| nodes.checked_mul(NODE_SIZE * 4 + 8 + 4));
+        let (nodes, words) = match (nodes, words, len) {
+            (Some(nodes), Some(words), Some(len))
+                if nodes >= 2 && Some(data.len()) == HEADER_LEN.checked_add(len) =>
+            {
+                (nodes, words)
+            }
+            _ => return Err(invalid_index("invalid index size")),
+        };
+        Ok(TrieIndex {
+            bytes,
+            checksum,
+            nodes,
+            words,
+        })
+    }
+
+    /// Checks this index against its checksum, which reads all of it.
+    pub fn verify(&self) -> io::Result<()> {
+        if crc32fast::hash(&self.bytes.as_ref()[HEADER_LEN..]) != self.checksum {
+            return Err(invalid_index("index checksum mismatch"));
+        }
+        Ok(())
+    }
+
+    /// Returns how many times the given word was counted, which is zero if it
+    /// wasn't.
+    ///
+    /// The word is lowercased with ASCII rules first, like counted words are.
+    pub fn count(&self, word: &[u8]) -> u64 {
+        self.find(&word.to_ascii_lowercase())
+            .map_or(0, |id| self.word(id).0)
+    }
+
+    /// Calls `f` with every word that starts with `prefix`, including
+    /// `prefix` itself if it was counted, along with its count and the order
+    /// in which it was first seen, in byte order.
+    ///
+    /// The prefix is lowercased with ASCII rules first, like counted words
+    /// are.
+    pub fn for_each_completion(&self, prefix: &[u8], f: &mut dyn FnMut(&[u8], u64, usize)) {
+        let prefix = prefix.to_ascii_lowercase();
+        if let Some(id) = self.find(&prefix) {
+            self.for_each_from(id, &prefix, &mut |word, count, seen| {
+                f(word, count, seen as usize)
+            });
+        }
+    }
+
+    /// Returns the words that start with `prefix` and their counts, as
+    /// selected by `select`. See `TrieCounter::completions`.
+    pub fn completions(&self, prefix: &[u8], select: &Select) -> Vec<(Vec<u8>, u64)> {
+        select.apply_each(None, |f| self.for_each_completion(prefix, f))
+    }
+
+    /// Returns the total count of every word that starts with `prefix`, and
+    /// how many distinct words that is. The total stops at `u64::MAX`, which
+    /// only a damaged index can reach.
+    pub fn prefix_count(&self, prefix: &[u8]) -> (u64, usize) {
+        let (mut total, mut len) = (0u64, 0);
+        self.for_each_completion(prefix, &mut |_, count, _| {
+            total = total.saturating_add(count);
+            len += 1;
+        });
+        (total, len)
+    }
+
+    /// Returns the number of distinct words in this index.
+    pub fn len(&self) -> usize {
+        self.words
+    }
+
+    /// Returns true if no words were counted.
+    pub fn is_empty(&self) -> bool {
+        self.words == 0
+    }
+
+    /// Returns where the counts start.
+    fn counts_at(&self) -> usize {
+        HEADER_LEN + self.nodes * NODE_SIZE * 4
+    }
+
+    /// Returns where the first seen order starts.
+    fn seen_at(&self) -> usize {
+        self.counts_at() + self.nodes * 8
+    }
+}
+
+impl<B: AsRef<[u8]>> Nodes for TrieIndex<B> {
+    fn child(&self, current_id: TrieNodeID, index: usize) -> Option<TrieNodeID> {
+        debug_assert!(index < NODE_SIZE);
+        let at = HEADER_LEN + (current_id.get() as usize * NODE_SIZE + index) * 4;
+        let child = read_u32(self.bytes.as_ref(), at);
+        TrieNodeID::new(child)
+            .filter(|&child| child > current_id && (child.get() as usize) < self.nodes)
+    }
+
+    fn word(&self, id: TrieNodeID) -> (u64, u32) {
+        let data = self.bytes.as_ref();
+        let id = id.get() as usize;
+        let count = read_u64(data, self.counts_at() + id * 8);
+        let seen = read_u32(data, self.seen_at() + id * 4);
+        (count, seen)
+    }
+}
+
+impl<N: Count> TrieCounter<N> {
+    /// Writes the words counted so far as an index, which `TrieIndex` loads.
+    /// Counts are written as u64, whatever `N` is.
+    ///
+    /// Call `finish` first, or the last word may be missing.
+    pub fn write_index<W: Write>(&self, wtr: W) -> io::Result<()> {
+        let mut checksum = io::BufWriter::new(Checksum(crc32fast::Hasher::new()));
+        self.write_body(&mut checksum)?;
+        let checksum = checksum.into_inner().map_err(|err| err.into_error())?.0;
+
+        let mut wtr = io::BufWriter::new(wtr);
+        wtr.write_all(MAGIC)?;
+        wtr.write_all(&VERSION.to_le_bytes())?;
+        wtr.write_all(&checksum.finalize().to_le_bytes())?;
+        wtr.write_all(&(self.counts.counts.len() as u64).to_le_bytes())?;
+        wtr.write_all(&(self.len as u64).to_le_bytes())?;
+        self.write_body(&mut wtr)?;
+        wtr.flush()
+    }
+
+    /// Writes everything in an index after its header.
+    fn write_body<W: Write>(&self, wtr: &mut W) -> io::Result<()> {
+        let trie = &self.counts;
+        for child in trie.nodes.iter() {
+            let id = child.map_or(0, TrieNodeID::get);
+            wtr.write_all(&id.to_le_bytes())?;
+        }
+        for count in trie.counts.iter() {
+            wtr.write_all(&count.get().to_le_bytes())?;
+        }
+        for seen in trie.seen.iter() {
+            wtr.write_all(&seen.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes the checksum of whatever is written to it.
+struct Checksum(crc32fast::Hasher);
+
+impl Write for Checksum {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    // This unwrap is correct because the slice is always 4 bytes long.
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], at: usize) -> u64 {
+    // This unwrap is correct because the slice is always 8 bytes long.
+    u64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+fn invalid_index(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
@@ -0,0 +1,168 @@
+// This is the Rust program I would write if someone asked for a *versatile*
+// program to find the frequency distribution of all the words, regardless of
+// case, in a stream. This handles any file that is at least conventionally
+// UTF-8 (so latin-1 is fine). It also uses the Unicode-aware word segmentation
+// algorithm and Unicode-aware case folding. This is despite NOT using Rust's
+// default Unicode string, since that requires the data to be valid UTF-8.
+//
+// It runs about as fast as the "simple" Rust and Go variants. We do two perf
+// tricks in this program: 1) we use bstr's `to_lowercase_into` to avoid
+// allocating a new string for every word, and 2) we look for an existing word
+// in our hashmap to avoid an allocation via the more idiomatic 'entry' API.
+// (These combined reflects a ~33% improvement in my ad hoc experiments.)
+// According to profiling, a bit over half the runtime is spent in word
+// segmenting. The rest seems somewhat evenly split between the actual
+// lowercasing and hashmap interactions.
+//
+// Segmenting and lowercasing are the default `Pipeline`, which can be
+// swapped for another to count words split or normalized some other way.
+
+use std::io;
+
+use bstr::{BStr, BString, ByteSlice};
+use fxhash::FxHashMap as HashMap;
+
+use crate::{counts, lines::LineBuffer, pipeline::Pipeline, Count, Feed, WordCounter};
+
+/// Counts the words that make it through a `Pipeline`. By default, that's
+/// words found by Unicode word segmentation, lowercased with Unicode rules.
+/// Invalid UTF-8 is tolerated.
+#[derive(Clone, Debug, Default)]
+pub struct Unicode<N = u64> {
+    /// Each word's count and the order in which it was first seen.
+    counts: HashMap<BString, (N, usize)>,
+    /// If asked for, the surface forms of each word and their counts.
+    forms: Option<HashMap<BString, HashMap<BString, N>>>,
+    lines: LineBuffer,
+    pipeline: Pipeline,
+}
+
+impl Unicode {
+    /// Create a new empty counter with u64 counts.
+    pub fn new() -> Unicode {
+        Unicode::default()
+    }
+}
+
+impl<N: Count> Unicode<N> {
+    /// Create a new empty counter that counts the words `pipeline` yields.
+    pub fn with_pipeline(pipeline: Pipeline) -> Unicode<N> {
+        Unicode {
+            counts: HashMap::default(),
+            forms: None,
+            lines: LineBuffer::default(),
+            pipeline,
+        }
+    }
+
+    /// Also count the surface forms of each word, i.e., what each word was
+    /// before the pipeline stemmed it, so that `Unicode::forms` can report
+    /// them. This costs another hash lookup per word.
+    pub fn keep_forms(mut self) -> Unicode<N> {
+        self.forms = Some(HashMap::default());
+        self
+    }
+
+    /// Returns the surface forms that were counted as `word` and how many
+    /// times each was counted, most frequent first with ties broken
+    /// alphabetically. This is empty unless `Unicode::keep_forms` was called.
+    pub fn forms(&self, word: &[u8]) -> Vec<(Vec<u8>, u64)> {
+        let forms = match self
+            .forms
+            .as_ref()
+            .and_then(|forms| forms.get(word.as_bstr()))
+        {
+            None => return vec![],
+            Some(forms) => forms,
+        };
+        let mut forms: Vec<(Vec<u8>, u64)> = forms
+            .iter()
+            .map(|(form, count)| (form.to_vec(), count.get()))
+            .collect();
+        forms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        forms
+    }
+}
+
+impl<N: Count> Feed for Unicode<N> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let Unicode {
+            counts,
+            forms,
+            lines,
+            pipeline,
+        } = self;
+        lines.feed(chunk, |lines| count_lines(counts, forms, pipeline, lines))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let Unicode {
+            counts,
+            forms,
+            lines,
+            pipeline,
+        } = self;
+        lines.finish(|lines| count_lines(counts, forms, pipeline, lines))
+    }
+}
+
+impl<N: Count> WordCounter for Unicode<N> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        for (word, &(count, seen)) in self.counts.iter() {
+            f(word.as_bytes(), count.get(), seen);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.counts.len()
+    }
+}
+
+fn count_lines<N: Count>(
+    counts: &mut HashMap<BString, (N, usize)>,
+    forms: &mut Option<HashMap<BString, HashMap<BString, N>>>,
+    pipeline: &mut Pipeline,
+    lines: &[u8],
+) -> io::Result<()> {
+    for line in lines.lines() {
+        pipeline.for_each_word(line, |word, form| {
+            increment(counts, word.as_bstr())?;
+            if let Some(ref mut forms) = *forms {
+                increment_form(forms, word.as_bstr(), form.as_bstr())?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+fn increment<N: Count>(counts: &mut HashMap<BString, (N, usize)>, word: &BStr) -> io::Result<()> {
+    // While this will do two hash lookups when 'word' is not in the map, it
+    // will only do one lookup and no allocs in the much more common case of
+    // 'word' being in the map.
+    if let Some(&mut (ref mut count, _)) = counts.get_mut(word) {
+        count.increment()?;
+    } else {
+        let seen = counts.len();
+        counts.insert(BString::from(word), (counts::one()?, seen));
+    }
+    Ok(())
+}
+
+fn increment_form<N: Count>(
+    forms: &mut HashMap<BString, HashMap<BString, N>>,
+    word: &BStr,
+    form: &BStr,
+) -> io::Result<()> {
+    // the same trick as `increment`, at both levels
+    let forms = match forms.get_mut(word) {
+        Some(forms) => forms,
+        None => forms.entry(BString::from(word)).or_default(),
+    };
+    if let Some(count) = forms.get_mut(form) {
+        count.increment()?;
+    } else {
+        forms.insert(BString::from(form), counts::one()?);
+    }
+    Ok(())
+}
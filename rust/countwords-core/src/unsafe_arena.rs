@@ -0,0 +1,105 @@
+// This version is similar to optimized except it uses arena to store the
+// keys rather than having separate allocation for each new key.
+//
+// It would have similar results if one uses bumpalo or typed-arena, we
+// just didn't use any libraries here.
+//
+// The arena is a `ChunkedArena`, which grows without moving the bytes it
+// already holds, so the keys pointing into it never dangle however many
+// distinct words there are. The unsafe code that borrows keys from it lives
+// in `ArenaMap`.
+
+use std::io;
+
+use crate::{chunked::ArenaMap, counts, Count, Feed, WordCounter};
+
+/// Counts words exactly like `Optimized`, but stores the distinct words in
+/// the chunks of an arena instead of allocating each one separately.
+#[derive(Debug)]
+pub struct UnsafeArena<N = u64> {
+    /// Each word's count and the order in which it was first seen.
+    counts: ArenaMap<(N, usize)>,
+    buf: Vec<u8>,
+}
+
+impl<N: Count> Default for UnsafeArena<N> {
+    fn default() -> UnsafeArena<N> {
+        UnsafeArena {
+            counts: ArenaMap::new(),
+            buf: vec![],
+        }
+    }
+}
+
+impl UnsafeArena {
+    /// Create a new empty counter with u64 counts.
+    pub fn new() -> UnsafeArena {
+        UnsafeArena::default()
+    }
+}
+
+impl<N: Count> Feed for UnsafeArena<N> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let offset = self.buf.len();
+        self.buf.extend_from_slice(chunk);
+        let buf = &mut self.buf[..];
+
+        let mut start = if offset > 0 { Some(0) } else { None };
+        for i in offset..buf.len() {
+            let b = buf[i];
+            if b.is_ascii_uppercase() {
+                buf[i] += b'a' - b'A';
+            }
+            if b == b' ' || b == b'\n' {
+                if let Some(start) = start.take() {
+                    increment(&mut self.counts, &buf[start..i])?;
+                }
+            } else if start.is_none() {
+                start = Some(i);
+            }
+        }
+        match start {
+            Some(start) => {
+                self.buf.drain(..start);
+            }
+            None => self.buf.clear(),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            increment(&mut self.counts, &self.buf)?;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<N: Count> WordCounter for UnsafeArena<N> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        for (word, &(count, seen)) in self.counts.iter() {
+            f(word, count.get(), seen);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.counts.len()
+    }
+}
+
+fn increment<N: Count>(counts: &mut ArenaMap<(N, usize)>, word: &[u8]) -> io::Result<()> {
+    // using 'counts.entry' would be more idiomatic here, but doing so requires
+    // allocating a new Vec<u8> because of its API. Instead, we do two hash
+    // lookups, but in the exceptionally common case (we see a word we've
+    // already seen), we only do one and without any allocs.
+    if let Some(&mut (ref mut count, _)) = counts.get_mut(word) {
+        count.increment()?;
+        return Ok(());
+    }
+    // the key is copied into the map's arena, which only allocates once per
+    // chunk rather than once per key
+    let seen = counts.len();
+    counts.insert(word, (counts::one()?, seen));
+    Ok(())
+}
@@ -0,0 +1,102 @@
+// This version is fast-simple again, except that it never converts words to
+// str and so never needs to look at them as anything but bytes. It also splits
+// on spaces and newlines only, just like the optimized C version. Like
+// fast-simple, it stores words in a `KeyMap`.
+
+use std::io;
+
+use crate::{
+    fast_simple::HASHMAP_INITIAL_CAPACITY, key::KeyMap, lines::LineBuffer, Count, Feed, WordCounter,
+};
+
+const NEWLINE_BYTE: u8 = b'\n';
+const SPACE_BYTE: u8 = b' ';
+
+/// Counts words separated by ASCII spaces and newlines, lowercased with ASCII
+/// rules, in a `KeyMap`.
+#[derive(Clone, Debug)]
+pub struct WellFasterThanC<N = u64> {
+    /// Each word's count and the order in which it was first seen.
+    counts: KeyMap<(N, usize)>,
+    lines: LineBuffer,
+}
+
+impl<N: Count> Default for WellFasterThanC<N> {
+    fn default() -> WellFasterThanC<N> {
+        WellFasterThanC::with_capacity(HASHMAP_INITIAL_CAPACITY)
+    }
+}
+
+impl WellFasterThanC {
+    /// Create a new empty counter with u64 counts.
+    pub fn new() -> WellFasterThanC {
+        WellFasterThanC::default()
+    }
+}
+
+impl<N: Count> WellFasterThanC<N> {
+    /// Create a new empty counter that can hold at least `capacity` distinct
+    /// words before its map grows.
+    pub fn with_capacity(capacity: usize) -> WellFasterThanC<N> {
+        WellFasterThanC {
+            counts: KeyMap::with_capacity(capacity),
+            lines: LineBuffer::default(),
+        }
+    }
+}
+
+impl<N: Count> Feed for WellFasterThanC<N> {
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let counts = &mut self.counts;
+        self.lines
+            .feed(chunk, |bytes_buffer| count_words(counts, bytes_buffer))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let counts = &mut self.counts;
+        self.lines
+            .finish(|bytes_buffer| count_words(counts, bytes_buffer))
+    }
+}
+
+impl<N: Count> WordCounter for WellFasterThanC<N> {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        for (word, &(count, seen)) in self.counts.iter() {
+            f(word, count.get(), seen);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.counts.len()
+    }
+}
+
+fn count_words<N: Count>(
+    counts: &mut KeyMap<(N, usize)>,
+    bytes_buffer: &mut [u8],
+) -> io::Result<()> {
+    // make_ascii_lowercase on str requires a call to as_bytes(), so we use directly
+    // on bytes here, but there doesn't seem to be a perf advantage
+    bytes_buffer.make_ascii_lowercase();
+
+    // consecutive separators (and the newline ending every buffer) produce
+    // empty words, which we don't count
+    bytes_buffer
+        .split(|ch| ch == &SPACE_BYTE || ch == &NEWLINE_BYTE)
+        .filter(|word| !word.is_empty())
+        .try_for_each(|word| increment(counts, word))
+}
+
+fn increment<N: Count>(counts: &mut KeyMap<(N, usize)>, word: &[u8]) -> io::Result<()> {
+    // Update, RBS 07/26/2022: insert_unique_unchecked() allows us to avoid
+    // duplicating hashmap lookups, while avoiding the additional alloc of an entry.
+    //
+    // A `KeyMap` does the same without any unsafe code: a word is looked up
+    // once, and only copied when it's new.
+    let seen = counts.len();
+    counts
+        .get_or_insert_with(word, || (N::default(), seen))
+        .0
+        .increment()?;
+    Ok(())
+}
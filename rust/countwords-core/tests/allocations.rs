@@ -0,0 +1,81 @@
+// Measures how often the engines that store words as `Key`s allocate while
+// counting a new vocabulary. Words short enough to fit in a key shouldn't
+// cost an allocation each, like they did when every word was a Vec or a Box,
+// so a few hundred thousand of them should only cost the map's growth.
+//
+// Allocations are counted per thread, since tests run in parallel.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use countwords_core::{FastSimple, Optimized, WellFasterThanC, WordCounter};
+
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Returns the number of allocations made counting `input` with `counter`,
+/// in chunks of 64 KiB.
+fn allocations<C: WordCounter>(mut counter: C, input: &[u8], words: usize) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    for chunk in input.chunks(1 << 16) {
+        counter.feed(chunk).unwrap();
+    }
+    counter.finish().unwrap();
+    let allocations = ALLOCATIONS.with(Cell::get) - before;
+    assert_eq!(counter.len(), words);
+    allocations
+}
+
+#[test]
+fn short_words_dont_allocate() {
+    // 200,000 distinct words of up to four letters
+    let words = 200_000;
+    let mut input = vec![];
+    for mut i in 0..words {
+        loop {
+            input.push(b'a' + (i % 26) as u8);
+            i /= 26;
+            if i == 0 {
+                break;
+            }
+        }
+        input.push(b'\n');
+    }
+    // the map doubles about 20 times, and each engine has a buffer or two
+    // that grows a few times along the way
+    let limit = 100;
+    let optimized = allocations(Optimized::new(), &input, words);
+    let fast_simple = allocations(FastSimple::new(), &input, words);
+    let well_faster = allocations(WellFasterThanC::new(), &input, words);
+    for (engine, n) in [
+        ("optimized", optimized),
+        ("fast-simple", fast_simple),
+        ("well-faster-than-c", well_faster),
+    ] {
+        assert!(n < limit, "{} allocated {} times", engine, n);
+    }
+}
@@ -0,0 +1,92 @@
+// Checks `ChunkedArena` and `ArenaMap`, which are built on unsafe code. These
+// are small enough to run under Miri, which checks that code too:
+//
+//     cargo +nightly miri test -p countwords-core --test chunked
+
+use std::collections::HashMap;
+
+use countwords_core::{
+    chunked::{ArenaMap, ChunkedArena},
+    UnsafeArena, WordCounter,
+};
+
+/// Returns a distinct word for every `i`, of a length that varies with it.
+fn word(i: usize) -> Vec<u8> {
+    let mut word = format!("w{}", i).into_bytes();
+    word.resize(word.len() + i % 7, b'x');
+    word
+}
+
+#[test]
+fn slices_survive_new_chunks() {
+    let arena = ChunkedArena::with_chunk_size(16);
+    let words: Vec<Vec<u8>> = (0..100).map(word).collect();
+    let slices: Vec<&[u8]> = words.iter().map(|w| arena.alloc(w)).collect();
+    assert!(arena.chunks() > 1);
+    for (word, slice) in words.iter().zip(slices.iter()) {
+        assert_eq!(&word[..], *slice);
+    }
+    let total: usize = words.iter().map(|w| w.len()).sum();
+    assert_eq!(arena.allocated(), total);
+}
+
+#[test]
+fn long_and_empty_words() {
+    let arena = ChunkedArena::with_chunk_size(4);
+    let short = arena.alloc(b"abc");
+    let long = arena.alloc(b"abcdefghij");
+    let empty = arena.alloc(b"");
+    let after = arena.alloc(b"xyz");
+    assert_eq!(short, b"abc");
+    assert_eq!(long, b"abcdefghij");
+    assert_eq!(empty, b"");
+    assert_eq!(after, b"xyz");
+    assert_eq!(arena.chunks(), 3);
+}
+
+#[test]
+fn map_matches_hashmap() {
+    let mut map = ArenaMap::with_arena(ChunkedArena::with_chunk_size(32));
+    let mut model = HashMap::new();
+    for i in 0..300 {
+        let key = word(i % 120);
+        let old = model.insert(key.clone(), i);
+        assert_eq!(map.insert(&key, i), old);
+        assert_eq!(map.get(&key), Some(&i));
+        if let Some(value) = map.get_mut(&word(i % 50)) {
+            *value += 1;
+            *model.get_mut(&word(i % 50)).unwrap() += 1;
+        }
+    }
+    assert_eq!(map.len(), model.len());
+    assert_eq!(map.get(b"missing"), None);
+    // Every key is copied into the arena exactly once.
+    let total: usize = model.keys().map(|k| k.len()).sum();
+    assert_eq!(map.arena().allocated(), total);
+    let got: HashMap<Vec<u8>, usize> = map.iter().map(|(k, &v)| (k.to_vec(), v)).collect();
+    assert_eq!(got, model);
+}
+
+#[test]
+fn unsafe_arena_outgrows_a_chunk() {
+    // Far more than the 64 KiB of one chunk, which under Miri is slow, so
+    // that's left to the plain test run.
+    let n = if cfg!(miri) { 2_000 } else { 40_000 };
+    let mut input = vec![];
+    for i in 0..n {
+        input.extend_from_slice(&word(i));
+        input.push(b' ');
+        input.extend_from_slice(&word(i / 2));
+        input.push(b'\n');
+    }
+    let mut counter = UnsafeArena::new();
+    countwords_core::count(&input[..], &mut counter).unwrap();
+    assert_eq!(counter.len(), n);
+    let mut counts = HashMap::new();
+    counter.for_each(&mut |word, count| {
+        counts.insert(word.to_vec(), count);
+    });
+    assert_eq!(counts[&word(0)], 3);
+    assert_eq!(counts[&word(n - 1)], 1);
+    assert_eq!(counts[&word(n / 2 - 1)], 3);
+}
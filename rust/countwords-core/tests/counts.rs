@@ -0,0 +1,121 @@
+// Checks that every engine fails, or saturates, when a word is counted more
+// times than its count type can hold. Overflowing a u32 would take billions of
+// words, so the engines are given a count type of their own that only goes up
+// to 255.
+
+use std::{io, num::Saturating};
+
+use bumpalo::Bump;
+use countwords_core::{
+    Bumpalo, Count, CountOverflow, FastSimple, Optimized, RadixTrieCounter, Simple, TableCounter,
+    Threads, TrieCounter, Unicode, UnsafeArena, WellFasterThanC, WordCounter,
+};
+
+/// A count that fails past 255.
+#[derive(Clone, Copy, Debug, Default)]
+struct Checked(u8);
+
+impl Count for Checked {
+    fn increment(&mut self) -> Result<(), CountOverflow> {
+        self.0 = self.0.checked_add(1).ok_or(CountOverflow::new(255))?;
+        Ok(())
+    }
+
+    fn get(self) -> u64 {
+        u64::from(self.0)
+    }
+}
+
+/// A count that stops at 255.
+#[derive(Clone, Copy, Debug, Default)]
+struct Capped(u8);
+
+impl Count for Capped {
+    fn increment(&mut self) -> Result<(), CountOverflow> {
+        self.0 = self.0.saturating_add(1);
+        Ok(())
+    }
+
+    fn get(self) -> u64 {
+        u64::from(self.0)
+    }
+}
+
+/// Counts `n` copies of "the" and one of "end", and returns each word and
+/// its count, sorted.
+fn count<C: WordCounter>(mut counter: C, n: usize) -> io::Result<Vec<(Vec<u8>, u64)>> {
+    let input = format!("{}end\n", "the ".repeat(n));
+    countwords_core::count(input.as_bytes(), &mut counter)?;
+    Ok(countwords_core::sorted(&counter))
+}
+
+/// Calls `check` with the result of counting with every engine, whose counts
+/// are of type `N`.
+fn each_engine<N: Count>(n: usize, mut check: impl FnMut(&str, io::Result<Vec<(Vec<u8>, u64)>>)) {
+    let bump = Bump::new();
+    check("simple", count(Simple::<N>::default(), n));
+    check("optimized", count(Optimized::<N>::default(), n));
+    check("unsafe-arena", count(UnsafeArena::<N>::default(), n));
+    check("trie", count(TrieCounter::<N>::default(), n));
+    check("radix-trie", count(RadixTrieCounter::<N>::default(), n));
+    check("custom-table", count(TableCounter::<_, N>::default(), n));
+    check("bonus-unicode", count(Unicode::<N>::default(), n));
+    check("fast-simple", count(FastSimple::<N>::default(), n));
+    check("threads", count(Threads::<N>::default(), n));
+    check("bumpalo", count(Bumpalo::<N>::with_counts(&bump), n));
+    check(
+        "well-faster-than-c",
+        count(WellFasterThanC::<N>::default(), n),
+    );
+}
+
+fn counts(the: u64) -> Vec<(Vec<u8>, u64)> {
+    vec![(b"the".to_vec(), the), (b"end".to_vec(), 1)]
+}
+
+#[test]
+fn up_to_the_maximum() {
+    each_engine::<Checked>(255, |engine, result| {
+        assert_eq!(result.unwrap(), counts(255), "{}", engine);
+    });
+}
+
+#[test]
+fn checked_counts_fail() {
+    each_engine::<Checked>(256, |engine, result| {
+        let err = result.expect_err(engine);
+        assert!(CountOverflow::is(&err), "{}: {}", engine, err);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    });
+}
+
+#[test]
+fn saturating_counts_stop() {
+    each_engine::<Capped>(1000, |engine, result| {
+        assert_eq!(result.unwrap(), counts(255), "{}", engine);
+    });
+}
+
+#[test]
+fn std_counts() {
+    let mut count = u32::MAX - 1;
+    count.increment().unwrap();
+    assert_eq!(count.get(), u64::from(u32::MAX));
+    let err = count.increment().unwrap_err();
+    assert_eq!(count, u32::MAX);
+    assert_eq!(
+        err.to_string(),
+        "a word was counted more than 4294967295 times, which overflows its count"
+    );
+
+    let mut count = u64::MAX;
+    assert!(count.increment().is_err());
+    assert_eq!(count, u64::MAX);
+
+    let mut count = Saturating(u32::MAX);
+    count.increment().unwrap();
+    assert_eq!(count.get(), u64::from(u32::MAX));
+    let mut count = Saturating(u64::MAX);
+    count.increment().unwrap();
+    assert_eq!(count.get(), u64::MAX);
+}
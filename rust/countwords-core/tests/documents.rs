@@ -0,0 +1,120 @@
+// Checks that `PerDocument` counts each document, and the total, the same way
+// whichever engine counts the documents, that the counters
+// `Engine::document_counter` creates grow past the room they start with, and
+// that the engine `Engine::per_document` picks doesn't keep a document's
+// memory around once it's been counted. Also checks that totals across
+// documents don't overflow.
+
+use std::io;
+
+use bumpalo::Bump;
+use countwords_core::{
+    CountOverflow, CountType, Engine, Feed, HashFunction, PerDocument, Select, Sort, WordCounter,
+};
+
+const DOCUMENTS: [&str; 3] = ["the cat sat on the mat\n", "The dog\nate the cat", ""];
+
+#[test]
+fn every_engine() {
+    let bump = Bump::new();
+    for engine in Engine::ALL {
+        let mut counter =
+            PerDocument::new(|| engine.document_counter(&bump, HashFunction::default(), None));
+        for document in DOCUMENTS {
+            countwords_core::feed_reader(document.as_bytes(), &mut counter).unwrap();
+        }
+        counter.finish().unwrap();
+        assert_eq!(counter.documents(), 3, "{}", engine);
+
+        let mut all = engine.per_document().counter(&bump);
+        countwords_core::count(DOCUMENTS.join("\n").as_bytes(), &mut *all).unwrap();
+        let select = Select::new(Sort::Count);
+        assert_eq!(select.apply(&counter), select.apply(&*all), "{}", engine);
+
+        assert_eq!(counter.get(b"the"), [(0, 2), (1, 2)], "{}", engine);
+        assert_eq!(counter.get(b"dog"), [(1, 1)], "{}", engine);
+        assert_eq!(counter.get(b"mat"), [(0, 1)], "{}", engine);
+        assert_eq!(counter.get(b"nope"), [], "{}", engine);
+    }
+}
+
+#[test]
+fn document_counters_grow() {
+    // far more distinct words than a document's counter starts with room for
+    let word = |i: usize| -> String {
+        [i / 676, i / 26 % 26, i % 26]
+            .map(|c| (b'a' + c as u8) as char)
+            .iter()
+            .collect()
+    };
+    let document: String = (0..10_000).map(|i| word(i) + " ").collect();
+    let bump = Bump::new();
+    for engine in Engine::ALL {
+        for counts in [None, Some(CountType::SaturatingU32)] {
+            let hash = HashFunction::default();
+            let mut counter = PerDocument::new(|| engine.document_counter(&bump, hash, counts));
+            countwords_core::feed_reader(document.as_bytes(), &mut counter).unwrap();
+            counter.finish().unwrap();
+            assert_eq!(counter.len(), 10_000, "{}", engine);
+            assert_eq!(counter.get(b"zzz"), [], "{}", engine);
+            assert_eq!(counter.get(b"baa"), [(0, 1)], "{}", engine);
+        }
+    }
+}
+
+#[test]
+fn documents_are_freed() {
+    let counted = |engine: Engine, documents: usize| {
+        let bump = Bump::new();
+        let mut counter = PerDocument::new(|| engine.counter(&bump));
+        for i in 0..documents {
+            let document = format!("document {}\n", i);
+            countwords_core::feed_reader(document.as_bytes(), &mut counter).unwrap();
+        }
+        counter.finish().unwrap();
+        assert_eq!(counter.documents(), documents);
+        bump.allocated_bytes()
+    };
+    // Bumpalo's arena grows with every document...
+    assert!(counted(Engine::Bumpalo, 100) > 20 * counted(Engine::Bumpalo, 1));
+    // ... so it isn't what counts documents.
+    let engine = Engine::Bumpalo.per_document();
+    assert_ne!(engine, Engine::Bumpalo);
+    assert_eq!(counted(engine, 100), 0);
+    for engine in Engine::ALL {
+        assert_eq!(counted(engine.per_document(), 10), 0, "{}", engine);
+    }
+}
+
+/// A counter that has counted "big" more than half of `u64::MAX` times in
+/// every document.
+struct Big;
+
+impl Feed for Big {
+    fn feed(&mut self, _: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl WordCounter for Big {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        f(b"big", u64::MAX / 2 + 1, 0);
+    }
+
+    fn len(&self) -> usize {
+        1
+    }
+}
+
+#[test]
+fn total_overflow() {
+    let mut counter = PerDocument::new(|| Big);
+    counter.end_input().unwrap();
+    let err = counter.end_input().unwrap_err();
+    assert!(CountOverflow::is(&err));
+    assert_eq!(counter.get(b"big"), [(0, u64::MAX / 2 + 1)]);
+}
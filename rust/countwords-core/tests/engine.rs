@@ -0,0 +1,68 @@
+// Checks that `Engine::auto` counts the same words on either side of the size
+// at which it stops picking `Engine::Bumpalo`, once the engine it picks is
+// wrapped in `AsciiWhitespace`, and that every engine reports an input it
+// can't count as it's fed, and goes on to count the next.
+
+use bumpalo::Bump;
+use countwords_core::{AsciiWhitespace, Engine, Feed, Select, Sort, WordCounter};
+
+const INPUT: &str = "The\tthe\r\nTHE form\x0Cfeed  end\nthe";
+
+fn counts<C: WordCounter>(mut counter: C) -> Vec<(Vec<u8>, u64)> {
+    // feed a byte at a time, so that replacing bytes can't depend on chunks
+    for chunk in INPUT.as_bytes().chunks(1) {
+        counter.feed(chunk).unwrap();
+    }
+    counter.finish().unwrap();
+    Select::new(Sort::Count).apply(&counter)
+}
+
+#[test]
+fn auto_splits_alike() {
+    let small = Engine::auto(Some(INPUT.len() as u64), INPUT.as_bytes());
+    let large = Engine::auto(Some(1 << 30), INPUT.as_bytes());
+    assert_eq!(small, Engine::Bumpalo);
+    assert_eq!(large, Engine::WellFasterThanC);
+
+    let bump = Bump::new();
+    let expected = vec![
+        (b"the".to_vec(), 4),
+        (b"end".to_vec(), 1),
+        (b"feed".to_vec(), 1),
+        (b"form".to_vec(), 1),
+    ];
+    assert_eq!(counts(small.counter(&bump)), expected);
+    assert_eq!(counts(AsciiWhitespace::new(large.counter(&bump))), expected);
+    // unwrapped, it only splits on spaces and newlines
+    assert_ne!(counts(large.counter(&bump)), expected);
+}
+
+#[test]
+fn errors_stay_with_their_input() {
+    let bump = Bump::new();
+    for engine in Engine::ALL {
+        let mut counter = engine.counter(&bump);
+        countwords_core::feed_reader(&b"one two\n"[..], &mut *counter).unwrap();
+        // a line of its own, and then enough input that a thread would be
+        // sent more after it
+        let mut bad = b"bad \xFF\n".to_vec();
+        bad.extend(b"more\n".repeat(100_000));
+        let result = countwords_core::feed_reader(&bad[..], &mut *counter);
+        let requires_utf8 = matches!(engine, Engine::Simple | Engine::Threads | Engine::Bumpalo);
+        if requires_utf8 {
+            assert!(result.is_err(), "{}", engine);
+            counter.end_input().unwrap();
+        } else {
+            result.unwrap();
+        }
+        countwords_core::feed_reader(&b"two\n"[..], &mut *counter).unwrap();
+        counter.finish().unwrap();
+        let counts = Select::new(Sort::Count).top(2).apply(&*counter);
+        if requires_utf8 {
+            assert_eq!(counts[0], (b"two".to_vec(), 2), "{}", engine);
+        } else {
+            assert_eq!(counts[0], (b"more".to_vec(), 100_000), "{}", engine);
+            assert_eq!(counts[1], (b"two".to_vec(), 2), "{}", engine);
+        }
+    }
+}
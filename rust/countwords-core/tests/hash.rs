@@ -0,0 +1,49 @@
+// Checks that every `HashFunction` gives `TableCounter` the same counts as
+// `Optimized`, which splits words the same way, including when words are cut
+// in two by the end of a chunk, where their hash is computed again from the
+// start.
+
+use bumpalo::Bump;
+use countwords_core::{Engine, Feed, HashFunction};
+use proptest::prelude::*;
+
+/// Returns every word counted in `input`, fed in chunks of `size` bytes, by
+/// `engine` with `hash`, and its count, sorted.
+fn counts(engine: Engine, hash: HashFunction, input: &[u8], size: usize) -> Vec<(Vec<u8>, u64)> {
+    let bump = Bump::new();
+    let mut counter = engine.counter_with_hash(&bump, hash);
+    for chunk in input.chunks(size) {
+        counter.feed(chunk).unwrap();
+    }
+    counter.finish().unwrap();
+    countwords_core::sorted(&counter)
+}
+
+#[test]
+fn same_counts() {
+    let input = b"The the THE\ncat's na\xC3\xAFve\tcaf\xC3\xA9 cat's\n\n  the\xFFend ".repeat(50);
+    let expected = counts(
+        Engine::Optimized,
+        HashFunction::default(),
+        &input,
+        input.len(),
+    );
+    assert_eq!(expected[0], (b"the".to_vec(), 150));
+    for hash in HashFunction::ALL {
+        for size in [1, 3, 64, input.len()] {
+            let got = counts(Engine::CustomTable, hash, &input, size);
+            assert_eq!(got, expected, "{} {}", hash, size);
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn any_input(input in "[abAB \n\t]{0,300}", size in 1usize..20) {
+        let input = input.as_bytes();
+        let expected = counts(Engine::Optimized, HashFunction::default(), input, input.len() + 1);
+        for hash in HashFunction::ALL {
+            prop_assert_eq!(counts(Engine::CustomTable, hash, input, size), expected.clone());
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// Checks that `HyperLogLog` estimates the number of distinct words within a
+// few standard errors, whether there are a handful of them or many thousands,
+// and that merging sketches gives exactly the sketch of both inputs.
+
+use countwords_core::{Feed, HyperLogLog};
+
+/// Returns a sketch with the given precision that has counted `words`.
+fn sketch(precision: u8, words: impl Iterator<Item = String>) -> HyperLogLog {
+    let mut hll = HyperLogLog::new(precision);
+    for word in words {
+        hll.feed(word.as_bytes()).unwrap();
+        hll.feed(b"\n").unwrap();
+    }
+    hll.finish().unwrap();
+    hll
+}
+
+/// Returns the bytes `HyperLogLog::write_to` writes for `hll`.
+fn written(hll: &HyperLogLog) -> Vec<u8> {
+    let mut out = vec![];
+    hll.write_to(&mut out).unwrap();
+    out
+}
+
+/// Returns `n` distinct words, starting at the `start`th, each twice.
+fn words(start: usize, n: usize) -> impl Iterator<Item = String> {
+    (start..start + n).flat_map(|i| [format!("word{}", i), format!("WORD{}", i)])
+}
+
+#[test]
+fn estimate() {
+    for precision in [HyperLogLog::MIN_PRECISION, 10, 14] {
+        for n in [0, 10, 1_000, 100_000] {
+            let hll = sketch(precision, words(0, n));
+            let error = (hll.estimate() - n as f64).abs() / (n as f64).max(1.0);
+            // more than four standard errors off is about a 1 in 15,000 chance
+            assert!(
+                error <= 4.0 * hll.standard_error(),
+                "{} {} {}",
+                precision,
+                n,
+                hll.estimate()
+            );
+        }
+    }
+    assert_eq!(sketch(14, words(0, 10)).estimate().round(), 10.0);
+}
+
+#[test]
+fn merge() {
+    // two overlapping halves
+    let mut merged = sketch(12, words(0, 6_000));
+    merged.merge(&sketch(12, words(4_000, 6_000))).unwrap();
+    let all = sketch(12, words(0, 10_000));
+    assert_eq!(written(&merged), written(&all));
+    assert_eq!(merged.estimate(), all.estimate());
+
+    // merging is idempotent
+    merged.merge(&all).unwrap();
+    assert_eq!(written(&merged), written(&all));
+
+    let err = merged.merge(&HyperLogLog::new(13)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(written(&merged), written(&all));
+}
+
+#[test]
+fn round_trip() {
+    let hll = sketch(10, words(0, 500));
+    let bytes = written(&hll);
+    let read = HyperLogLog::read_from(&bytes[..]).unwrap();
+    assert_eq!(read.precision(), 10);
+    assert_eq!(read.estimate(), hll.estimate());
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(HyperLogLog::read_from(&trailing[..]).is_err());
+    assert!(HyperLogLog::read_from(&bytes[..bytes.len() - 1]).is_err());
+    let mut rank = bytes.clone();
+    *rank.last_mut().unwrap() = 64;
+    assert!(HyperLogLog::read_from(&rank[..]).is_err());
+}
@@ -0,0 +1,100 @@
+// Checks `Key`, `Arena` and `KeyMap` on either side of the longest word a key
+// holds in place, and that a map finds a word whichever way its key stores
+// it, including after growing, which hashes spilled words again from the
+// arena.
+
+use std::collections::HashMap;
+
+use countwords_core::key::{Arena, Key, KeyMap, INLINE_LEN};
+use proptest::prelude::*;
+
+#[test]
+fn inline_boundary() {
+    assert_eq!(INLINE_LEN, 22);
+    let fits = [b'a'; INLINE_LEN];
+    let spills = [b'b'; INLINE_LEN + 1];
+
+    let key = Key::inline(&fits).unwrap();
+    assert!(key.is_inline());
+    assert_eq!(key.len(), INLINE_LEN);
+    assert!(Key::inline(&spills).is_none());
+    assert!(Key::inline(b"").unwrap().is_empty());
+
+    let mut arena = Arena::new();
+    let fits_key = arena.key(&fits);
+    assert!(fits_key.is_inline());
+    assert!(arena.is_empty());
+    let spills_key = arena.key(&spills);
+    assert!(!spills_key.is_inline());
+    assert_eq!(spills_key.len(), INLINE_LEN + 1);
+    assert_eq!(arena.len(), INLINE_LEN + 1);
+
+    assert_eq!(arena.get(&fits_key), fits);
+    assert_eq!(arena.get(&spills_key), spills);
+}
+
+#[test]
+fn arena_get() {
+    let mut arena = Arena::new();
+    let words: Vec<Vec<u8>> = (0..50).map(|i| vec![b'a' + i % 26; i as usize]).collect();
+    let keys: Vec<Key> = words.iter().map(|word| arena.key(word)).collect();
+    for (word, key) in words.iter().zip(keys.iter()) {
+        assert_eq!(arena.get(key), &word[..]);
+        assert_eq!(key.is_inline(), word.len() <= INLINE_LEN);
+    }
+    let spilled: usize = words.iter().map(Vec::len).filter(|&n| n > INLINE_LEN).sum();
+    assert_eq!(arena.len(), spilled);
+
+    arena.clear();
+    assert!(arena.is_empty());
+    // inline keys don't need the arena at all
+    assert_eq!(arena.get(&keys[3]), &words[3][..]);
+}
+
+#[test]
+fn inline_and_spilled_words() {
+    let mut map = KeyMap::new();
+    let long = [b'x'; INLINE_LEN + 1];
+    // the same 22 bytes, once in place and once at the start of a spilled key
+    for word in [
+        &long[..INLINE_LEN],
+        &long[..],
+        &long[..INLINE_LEN],
+        &long[..],
+    ] {
+        *map.get_or_insert_with(word, || 0) += 1;
+    }
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.spilled(), INLINE_LEN + 1);
+    assert_eq!(map.get(&long[..INLINE_LEN]), Some(&2));
+    assert_eq!(map.get(&long), Some(&2));
+    assert_eq!(map.get(&long[..INLINE_LEN - 1]), None);
+    assert_eq!(map.get(&[b'x'; INLINE_LEN + 2]), None);
+    *map.get_mut(&long).unwrap() += 1;
+    assert_eq!(map.get(&long), Some(&3));
+}
+
+proptest! {
+    #[test]
+    fn map_matches_hashmap(
+        words in prop::collection::vec(
+            prop::collection::vec(prop::sample::select(b"ab".to_vec()), 0..30),
+            0..300,
+        ),
+    ) {
+        let mut map = KeyMap::new();
+        let mut model: HashMap<Vec<u8>, usize> = HashMap::new();
+        for word in words.iter() {
+            *map.get_or_insert_with(word, || 0) += 1;
+            *model.entry(word.clone()).or_default() += 1;
+        }
+        prop_assert_eq!(map.len(), model.len());
+        let spilled: usize = model.keys().map(Vec::len).filter(|&n| n > INLINE_LEN).sum();
+        prop_assert_eq!(map.spilled(), spilled);
+        let got: HashMap<Vec<u8>, usize> = map.iter().map(|(w, &c)| (w.to_vec(), c)).collect();
+        prop_assert_eq!(&got, &model);
+        for (word, count) in model.iter() {
+            prop_assert_eq!(map.get(word), Some(count));
+        }
+    }
+}
@@ -0,0 +1,248 @@
+// Checks that `Output` escapes words in every format that needs it, applies
+// each invalid UTF-8 policy, and nests per-file counts and the forms of each
+// stem as documented.
+
+use std::io;
+
+use countwords_core::{Format, InvalidUtf8, Output};
+
+/// Returns what `write` wrote with `output`.
+fn written<F>(output: Output, write: F) -> io::Result<String>
+where
+    F: FnOnce(&Output, &mut Vec<u8>) -> io::Result<()>,
+{
+    let mut out = vec![];
+    write(&output, &mut out)?;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+/// Returns `words`, each counted once more than the one after it, as written
+/// in `format`.
+fn counts(format: Format, words: &[&[u8]]) -> String {
+    let counts: Vec<(Vec<u8>, u64)> = words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| (word.to_vec(), (words.len() - i) as u64))
+        .collect();
+    written(Output::new(format), |output, out| {
+        output.write_counts(out, &counts)
+    })
+    .unwrap()
+}
+
+#[test]
+fn csv_quoting() {
+    let words: &[&[u8]] = &[
+        b"plain",
+        b"a,b",
+        b"say \"hi\"",
+        b"two\nlines",
+        b"cr\r",
+        b"\"",
+    ];
+    assert_eq!(
+        counts(Format::Csv, words),
+        "word,count\n\
+         plain,6\n\
+         \"a,b\",5\n\
+         \"say \"\"hi\"\"\",4\n\
+         \"two\nlines\",3\n\
+         \"cr\r\",2\n\
+         \"\"\"\",1\n"
+    );
+}
+
+#[test]
+fn tsv_escaping() {
+    let words: &[&[u8]] = &[
+        b"plain",
+        b"a\tb",
+        b"two\nlines",
+        b"cr\r",
+        b"back\\slash",
+        b"a,\"b\"",
+    ];
+    assert_eq!(
+        counts(Format::Tsv, words),
+        "word\tcount\n\
+         plain\t6\n\
+         a\\tb\t5\n\
+         two\\nlines\t4\n\
+         cr\\r\t3\n\
+         back\\\\slash\t2\n\
+         a,\"b\"\t1\n"
+    );
+}
+
+#[test]
+fn json_escaping() {
+    let words: &[&[u8]] = &[
+        b"say \"hi\"",
+        b"back\\slash",
+        b"\n\r\t",
+        b"\x01\x1F",
+        "naïve".as_bytes(),
+    ];
+    assert_eq!(
+        counts(Format::Json, words),
+        "[\n  \
+         {\"word\": \"say \\\"hi\\\"\", \"count\": 5},\n  \
+         {\"word\": \"back\\\\slash\", \"count\": 4},\n  \
+         {\"word\": \"\\n\\r\\t\", \"count\": 3},\n  \
+         {\"word\": \"\\u0001\\u001f\", \"count\": 2},\n  \
+         {\"word\": \"naïve\", \"count\": 1}\n\
+         ]\n"
+    );
+    assert_eq!(
+        counts(Format::Ndjson, &words[..2]),
+        "{\"word\": \"say \\\"hi\\\"\", \"count\": 2}\n\
+         {\"word\": \"back\\\\slash\", \"count\": 1}\n"
+    );
+    assert_eq!(
+        counts(Format::JsonObject, &words[..2]),
+        "{\n  \"say \\\"hi\\\"\": 2,\n  \"back\\\\slash\": 1\n}\n"
+    );
+    assert_eq!(counts(Format::Json, &[]), "[]\n");
+    assert_eq!(counts(Format::JsonObject, &[]), "{}\n");
+}
+
+#[test]
+fn invalid_utf8() {
+    let words: &[(Vec<u8>, u64)] = &[(b"bad\xFF".to_vec(), 2), (b"ok".to_vec(), 1)];
+    let write = |format, policy| {
+        let mut out = vec![];
+        let result = Output::new(format)
+            .invalid_utf8(policy)
+            .write_counts(&mut out, words);
+        result.map(|()| out)
+    };
+
+    assert_eq!(
+        write(Format::Plain, InvalidUtf8::Raw).unwrap(),
+        b"bad\xFF 2\nok 1\n"
+    );
+    assert_eq!(
+        write(Format::Plain, InvalidUtf8::Lossy).unwrap(),
+        "bad\u{FFFD} 2\nok 1\n".as_bytes()
+    );
+    assert_eq!(write(Format::Plain, InvalidUtf8::Skip).unwrap(), b"ok 1\n");
+    let err = write(Format::Plain, InvalidUtf8::Error).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // JSON can't hold raw bytes, so they're replaced
+    assert_eq!(
+        write(Format::Ndjson, InvalidUtf8::Raw).unwrap(),
+        "{\"word\": \"bad\u{FFFD}\", \"count\": 2}\n\
+         {\"word\": \"ok\", \"count\": 1}\n"
+            .as_bytes()
+    );
+    assert_eq!(
+        write(Format::Json, InvalidUtf8::Skip).unwrap(),
+        b"[\n  {\"word\": \"ok\", \"count\": 1}\n]\n"
+    );
+
+    // nothing at all is written when a word is an error, even streamed
+    let mut out = vec![];
+    let result = Output::new(Format::Json)
+        .invalid_utf8(InvalidUtf8::Error)
+        .write_each(&mut out, |f| {
+            for (word, count) in words.iter().rev() {
+                f(word, *count);
+            }
+        });
+    assert!(result.is_err());
+    assert!(out.is_empty());
+}
+
+#[test]
+fn per_file() {
+    // the same file twice, as when it's named twice
+    let files = ["a.txt", "b,\"c\".txt", "a.txt"];
+    let rows: &[(&[u8], &[u64])] = &[(b"the", &[4, 2, 0, 2]), (b"end", &[1, 0, 1, 0])];
+    let write = |format| {
+        written(Output::new(format), |output, out| {
+            output.write_per_file(out, &files, rows.iter().copied())
+        })
+        .unwrap()
+    };
+
+    assert_eq!(
+        write(Format::Csv),
+        "word,total,a.txt,\"b,\"\"c\"\".txt\",a.txt\n\
+         the,4,2,0,2\n\
+         end,1,0,1,0\n"
+    );
+    assert_eq!(
+        write(Format::Plain),
+        "word total a.txt b,\"c\".txt a.txt\n\
+         the 4 2 0 2\n\
+         end 1 0 1 0\n"
+    );
+    let the = "\"total\": 4, \"files\": [{\"file\": \"a.txt\", \"count\": 2}, \
+               {\"file\": \"b,\\\"c\\\".txt\", \"count\": 0}, \
+               {\"file\": \"a.txt\", \"count\": 2}]";
+    let end = "\"total\": 1, \"files\": [{\"file\": \"a.txt\", \"count\": 0}, \
+               {\"file\": \"b,\\\"c\\\".txt\", \"count\": 1}, \
+               {\"file\": \"a.txt\", \"count\": 0}]";
+    assert_eq!(
+        write(Format::Json),
+        format!(
+            "[\n  {{\"word\": \"the\", {}}},\n  {{\"word\": \"end\", {}}}\n]\n",
+            the, end
+        )
+    );
+    assert_eq!(
+        write(Format::Ndjson),
+        format!(
+            "{{\"word\": \"the\", {}}}\n{{\"word\": \"end\", {}}}\n",
+            the, end
+        )
+    );
+    assert_eq!(
+        write(Format::JsonObject),
+        format!("{{\n  \"the\": {{{}}},\n  \"end\": {{{}}}\n}}\n", the, end)
+    );
+}
+
+#[test]
+fn forms() {
+    let forms = [
+        (
+            &b"bless"[..],
+            vec![(b"blessed".to_vec(), 2), (b"bless\xFF".to_vec(), 1)],
+        ),
+        (&b"bad\xFF"[..], vec![(b"bad\xFF".to_vec(), 1)]),
+        (&b"run"[..], vec![(b"run".to_vec(), 1)]),
+    ];
+    let write = |format, policy| {
+        written(Output::new(format).invalid_utf8(policy), |output, out| {
+            let forms = forms.iter().map(|(stem, forms)| (*stem, &forms[..]));
+            output.write_forms(out, forms)
+        })
+        .unwrap()
+    };
+
+    assert_eq!(
+        write(Format::JsonObject, InvalidUtf8::Skip),
+        "{\n  \"bless\": {\n    \"blessed\": 2\n  },\n  \"run\": {\n    \"run\": 1\n  }\n}\n"
+    );
+    assert_eq!(
+        write(Format::JsonObject, InvalidUtf8::Lossy),
+        "{\n  \
+         \"bless\": {\n    \"blessed\": 2,\n    \"bless\u{FFFD}\": 1\n  },\n  \
+         \"bad\u{FFFD}\": {\n    \"bad\u{FFFD}\": 1\n  },\n  \
+         \"run\": {\n    \"run\": 1\n  }\n\
+         }\n"
+    );
+    assert_eq!(
+        write(Format::Json, InvalidUtf8::Skip),
+        "[\n  \
+         {\"stem\": \"bless\", \"form\": \"blessed\", \"count\": 2},\n  \
+         {\"stem\": \"run\", \"form\": \"run\", \"count\": 1}\n\
+         ]\n"
+    );
+    assert_eq!(
+        write(Format::Tsv, InvalidUtf8::Skip),
+        "stem\tform\tcount\nbless\tblessed\t2\nrun\trun\t1\n"
+    );
+}
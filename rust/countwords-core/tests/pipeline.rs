@@ -0,0 +1,235 @@
+// Checks that `Unicode` counts what its `Pipeline` yields: by default the same
+// words bonus always counted, and otherwise whatever the built-in stages, or
+// stages written outside this crate, make of them.
+
+use std::{collections::HashMap, io};
+
+use bstr::ByteSlice;
+use countwords_core::{
+    pipeline::{
+        AsciiAlphabetic, AsciiLowercase, Filter, Language, MaxLen, MinLen, Normalization,
+        Normalizer, Stemmer, Tokenization, Tokenizer, UnicodeLowercase, Whitespace,
+    },
+    Feed, Format, Output, Pipeline, Select, Sort, Unicode, WordCounter,
+};
+
+const INPUT: &str = "The the THE Straße STRASSE naïve, café! it's\n\
+                     Στην ΑΡΧΉ ήταν 日本語 a-b\r\n";
+
+fn counts(pipeline: Pipeline, input: &[u8]) -> HashMap<Vec<u8>, u64> {
+    let mut counter = Unicode::<u64>::with_pipeline(pipeline);
+    // feed a byte at a time, so that lines are split across chunks too
+    for chunk in input.chunks(1) {
+        counter.feed(chunk).unwrap();
+    }
+    counter.finish().unwrap();
+    let mut counts = HashMap::new();
+    counter.for_each(&mut |word, count| {
+        counts.insert(word.to_vec(), count);
+    });
+    counts
+}
+
+fn expected(words: &[(&str, u64)]) -> HashMap<Vec<u8>, u64> {
+    words
+        .iter()
+        .map(|&(word, count)| (word.as_bytes().to_vec(), count))
+        .collect()
+}
+
+#[test]
+fn default_is_bonus() {
+    let input = &[INPUT.as_bytes(), b"\xC3(invalid) end"].concat()[..];
+    let mut model: HashMap<Vec<u8>, u64> = HashMap::new();
+    for line in input.lines() {
+        for word in line.words() {
+            *model.entry(word.as_bytes().to_lowercase()).or_insert(0) += 1;
+        }
+    }
+    assert_eq!(counts(Pipeline::default(), input), model);
+
+    let mut unicode = Unicode::new();
+    countwords_core::count(input, &mut unicode).unwrap();
+    assert_eq!(unicode.len(), model.len());
+}
+
+#[test]
+fn built_in_stages() {
+    let input = b"The the THE Stra\xC3\x9Fe naive, a-b x\n";
+    let whitespace = Pipeline::new(Whitespace).normalize(AsciiLowercase);
+    assert_eq!(
+        counts(whitespace, input),
+        expected(&[
+            ("the", 3),
+            ("straße", 1),
+            ("naive,", 1),
+            ("a-b", 1),
+            ("x", 1)
+        ]),
+    );
+
+    let alphabetic = Pipeline::new(AsciiAlphabetic)
+        .normalize(UnicodeLowercase)
+        .filter(MinLen(2))
+        .filter(MaxLen(4));
+    assert_eq!(
+        counts(alphabetic, input),
+        expected(&[("the", 3), ("stra", 1)]),
+    );
+
+    let unchanged = Pipeline::new(Tokenization::UnicodeWords).normalize(Normalization::Unchanged);
+    assert_eq!(
+        counts(unchanged, input),
+        expected(&[
+            ("The", 1),
+            ("the", 1),
+            ("THE", 1),
+            ("Straße", 1),
+            ("naive", 1),
+            ("a", 1),
+            ("b", 1),
+            ("x", 1)
+        ]),
+    );
+}
+
+#[test]
+fn names() {
+    for tokenizer in Tokenization::ALL {
+        assert_eq!(tokenizer.name().parse::<Tokenization>().unwrap(), tokenizer);
+    }
+    for normalizer in Normalization::ALL {
+        assert_eq!(
+            normalizer.name().parse::<Normalization>().unwrap(),
+            normalizer
+        );
+    }
+    assert_eq!(
+        "nope".parse::<Tokenization>().unwrap_err().to_string(),
+        "unknown tokenizer 'nope', expected one of: unicode-words, whitespace, ascii-alphabetic"
+    );
+}
+
+/// Splits on commas, like a library user might for CSV-ish input.
+#[derive(Debug)]
+struct Commas;
+
+impl Tokenizer for Commas {
+    fn tokenize(&self, text: &[u8], f: &mut dyn FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+        for word in text.split(|&b| b == b',' || b == b'\n') {
+            let word = word.trim();
+            if !word.is_empty() {
+                f(word)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reverses each word, which only makes sense in a test: normalizers are
+/// applied in the order they're added.
+#[derive(Debug)]
+struct Reverse;
+
+impl Normalizer for Reverse {
+    fn normalize(&self, word: &[u8], out: &mut Vec<u8>) {
+        out.extend(word.iter().rev());
+    }
+}
+
+/// Drops words that start with a given byte.
+#[derive(Debug)]
+struct NotStartingWith(u8);
+
+impl Filter for NotStartingWith {
+    fn keep(&self, word: &[u8]) -> bool {
+        word.first() != Some(&self.0)
+    }
+}
+
+#[test]
+fn user_stages() {
+    let input = b"New York, new york,Boston\nDallas , nEW yORK\n";
+    let pipeline = Pipeline::new(Commas)
+        .normalize(AsciiLowercase)
+        .normalize(Reverse)
+        .normalize(UnicodeLowercase)
+        .filter(NotStartingWith(b's'));
+    assert_eq!(
+        counts(pipeline, input),
+        expected(&[("kroy wen", 3), ("notsob", 1)]),
+    );
+}
+
+#[test]
+fn stems() {
+    let cases = [
+        (Language::English, "Bless blessed blessing BLESSED", "bless"),
+        (Language::German, "Häuser Haus HAUSES", "haus"),
+        (
+            Language::French,
+            "continuer continuait Continuation",
+            "continu",
+        ),
+        (Language::Spanish, "cantar cantaba CANTANDO", "cant"),
+    ];
+    for (language, input, stem) in cases {
+        let pipeline = Pipeline::default().stem(Stemmer::new(language));
+        let counts = counts(pipeline, input.as_bytes());
+        let n = input.split(' ').count() as u64;
+        assert_eq!(counts, expected(&[(stem, n)]), "{}", language);
+    }
+}
+
+#[test]
+fn forms() {
+    let input = "Bless blessed the blessing, BLESSED x the\n";
+    let pipeline = Pipeline::default()
+        .filter(MinLen(2))
+        .stem(Stemmer::new(Language::English));
+    let mut counter = Unicode::<u64>::with_pipeline(pipeline).keep_forms();
+    countwords_core::count(input.as_bytes(), &mut counter).unwrap();
+    let stems = Select::new(Sort::Count).apply(&counter);
+    assert_eq!(stems, vec![(b"bless".to_vec(), 4), (b"the".to_vec(), 2)]);
+    assert_eq!(
+        counter.forms(b"bless"),
+        vec![
+            (b"blessed".to_vec(), 2),
+            (b"bless".to_vec(), 1),
+            (b"blessing".to_vec(), 1)
+        ]
+    );
+    assert_eq!(counter.forms(b"blessed"), vec![]);
+    assert_eq!(Unicode::new().forms(b"bless"), vec![]);
+
+    let forms: Vec<_> = stems
+        .into_iter()
+        .map(|(stem, _)| {
+            let forms = counter.forms(&stem);
+            (stem, forms)
+        })
+        .collect();
+    let written = |format| {
+        let mut out = vec![];
+        let forms = forms.iter().map(|(stem, forms)| (&stem[..], &forms[..]));
+        Output::new(format).write_forms(&mut out, forms).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(
+        written(Format::Plain),
+        "stem form count\n\
+         bless blessed 2\n\
+         bless bless 1\n\
+         bless blessing 1\n\
+         the the 2\n"
+    );
+    assert_eq!(
+        written(Format::JsonObject),
+        "{\n  \"bless\": {\n    \"blessed\": 2,\n    \"bless\": 1,\n    \"blessing\": 1\n  },\n  \
+         \"the\": {\n    \"the\": 2\n  }\n}\n"
+    );
+    assert_eq!(
+        written(Format::Ndjson).lines().next(),
+        Some(r#"{"stem": "bless", "form": "blessed", "count": 2}"#)
+    );
+}
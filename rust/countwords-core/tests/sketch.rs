@@ -0,0 +1,119 @@
+// Checks that `CountMinSketch` never underestimates a word, with or without
+// conservative update and after merging, that merging refuses sketches of
+// other dimensions and counts that would overflow, and that a sketch survives
+// being written and read back. Widths are tiny so that words share counters.
+
+use std::collections::HashMap;
+
+use countwords_core::{CountMinSketch, CountOverflow, Feed};
+use proptest::prelude::*;
+
+/// Returns a sketch of the given dimensions that has counted `words`.
+fn sketch(width: usize, depth: usize, conservative: bool, words: &[String]) -> CountMinSketch {
+    let mut sketch = CountMinSketch::new(width, depth).conservative(conservative);
+    sketch.feed(words.join(" ").as_bytes()).unwrap();
+    sketch.finish().unwrap();
+    sketch
+}
+
+/// Returns the bytes `CountMinSketch::write_to` writes for `sketch`.
+fn written(sketch: &CountMinSketch) -> Vec<u8> {
+    let mut out = vec![];
+    sketch.write_to(&mut out).unwrap();
+    out
+}
+
+proptest! {
+    #[test]
+    fn never_undercounts(
+        first in prop::collection::vec("[a-f]{1,2}", 0..100),
+        second in prop::collection::vec("[a-f]{1,2}", 0..100),
+        width in 1usize..8,
+        depth in 1usize..4,
+        conservative in any::<bool>(),
+    ) {
+        let mut exact: HashMap<&str, u64> = HashMap::new();
+        for word in first.iter().chain(second.iter()) {
+            *exact.entry(word).or_default() += 1;
+        }
+
+        let mut merged = sketch(width, depth, conservative, &first);
+        merged.merge(&sketch(width, depth, conservative, &second)).unwrap();
+        let all: Vec<String> = first.iter().chain(second.iter()).cloned().collect();
+        let all = sketch(width, depth, conservative, &all);
+        prop_assert_eq!(merged.total(), (first.len() + second.len()) as u64);
+        prop_assert_eq!(all.total(), merged.total());
+        for (word, &exact) in exact.iter() {
+            let word = word.as_bytes();
+            prop_assert!(all.estimate(word) >= exact);
+            prop_assert!(merged.estimate(word) >= exact);
+            prop_assert!(all.estimate(&word.to_ascii_uppercase()) >= exact);
+            if !conservative {
+                // adding counters is exactly what counting does
+                prop_assert_eq!(merged.estimate(word), all.estimate(word));
+            }
+        }
+    }
+}
+
+#[test]
+fn round_trip() {
+    let words: Vec<String> = "the cat and the dog and the end"
+        .split(' ')
+        .map(String::from)
+        .collect();
+    for conservative in [false, true] {
+        let sketch = sketch(5, 3, conservative, &words);
+        let bytes = written(&sketch);
+        let read = CountMinSketch::read_from(&bytes[..]).unwrap();
+        assert_eq!((read.width(), read.depth()), (5, 3));
+        assert_eq!(read.total(), 8);
+        for word in ["the", "and", "cat", "nope"] {
+            assert_eq!(
+                read.estimate(word.as_bytes()),
+                sketch.estimate(word.as_bytes())
+            );
+        }
+        // including whether it's conservative
+        assert_eq!(written(&read), bytes);
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(CountMinSketch::read_from(&trailing[..]).is_err());
+        assert!(CountMinSketch::read_from(&bytes[..bytes.len() - 1]).is_err());
+        let mut magic = bytes.clone();
+        magic[0] = b'x';
+        assert!(CountMinSketch::read_from(&magic[..]).is_err());
+    }
+}
+
+#[test]
+fn merge_dimensions() {
+    let mut sketch = CountMinSketch::new(10, 2);
+    for (width, depth) in [(10, 3), (11, 2), (20, 1)] {
+        let err = sketch
+            .merge(&CountMinSketch::new(width, depth))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    sketch.merge(&CountMinSketch::new(10, 2)).unwrap();
+}
+
+#[test]
+fn merge_overflow() {
+    let mut full = CountMinSketch::new(2, 1);
+    full.feed(b"word").unwrap();
+    full.finish().unwrap();
+    // a sketch whose every counter, and total, is at its maximum
+    let mut bytes = written(&full);
+    let counters = bytes.len() - 3 * 8;
+    bytes[counters..].fill(0xFF);
+    let max = CountMinSketch::read_from(&bytes[..]).unwrap();
+    assert_eq!(max.estimate(b"word"), u64::MAX);
+
+    let err = full.merge(&max).unwrap_err();
+    assert!(CountOverflow::is(&err));
+    // and nothing was added
+    assert_eq!(full.total(), 1);
+    assert_eq!(full.estimate(b"word"), 1);
+}
@@ -0,0 +1,205 @@
+// Checks that every `Sort` is a total order that breaks ties as documented,
+// so that the same counts come out in the same order whichever engine counted
+// them and whatever order it reports them in, and that `Select` takes the
+// same words with or without `Select::top`, from a heap, a counting sort or
+// a counter that's already in order, as a plain sort would.
+
+use std::io;
+
+use bumpalo::Bump;
+use countwords_core::{Engine, Feed, Select, Sort, WordCounter};
+use proptest::prelude::*;
+
+/// A counter that reports the words it was made with, in that order.
+#[derive(Clone, Debug)]
+struct Fixed {
+    /// Each word with its count and the order in which it was first seen.
+    words: Vec<(Vec<u8>, u64, usize)>,
+    /// Whether the words are in byte order, and say so.
+    ordered: bool,
+}
+
+impl Fixed {
+    /// Create a counter of the given words and counts, where each word was
+    /// first seen in the order given.
+    fn new(words: &[(&str, u64)]) -> Fixed {
+        let words = words
+            .iter()
+            .enumerate()
+            .map(|(seen, &(word, count))| (word.as_bytes().to_vec(), count, seen))
+            .collect();
+        Fixed {
+            words,
+            ordered: false,
+        }
+    }
+
+    /// Returns this counter with its words reported in reverse.
+    fn reversed(&self) -> Fixed {
+        let mut words = self.words.clone();
+        words.reverse();
+        Fixed {
+            words,
+            ordered: false,
+        }
+    }
+
+    /// Returns this counter with its words reported in byte order, which
+    /// `WordCounter::is_ordered` says, like the trie does.
+    fn in_byte_order(&self) -> Fixed {
+        let mut words = self.words.clone();
+        words.sort();
+        Fixed {
+            words,
+            ordered: true,
+        }
+    }
+}
+
+impl Feed for Fixed {
+    fn feed(&mut self, _: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl WordCounter for Fixed {
+    fn for_each_seen(&self, f: &mut dyn FnMut(&[u8], u64, usize)) {
+        for (word, count, seen) in self.words.iter() {
+            f(word, *count, *seen);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+}
+
+/// Returns the words and counts in `counter` in the given order, sorted
+/// without `Sort`.
+fn model(sort: Sort, counter: &Fixed) -> Vec<(Vec<u8>, u64)> {
+    let mut words = counter.words.clone();
+    match sort {
+        Sort::Count => words.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0))),
+        Sort::CountAsc => words.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0))),
+        Sort::Alpha => words.sort_by(|a, b| a.0.cmp(&b.0)),
+        Sort::Length => words.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(&b.0))),
+        Sort::FirstSeen => words.sort_by_key(|w| w.2),
+    }
+    words
+        .into_iter()
+        .map(|(word, count, _)| (word, count))
+        .collect()
+}
+
+/// Returns the words that `select` takes from `counter`, in order.
+fn words<C: WordCounter>(select: Select, counter: &C) -> Vec<String> {
+    select
+        .apply(counter)
+        .into_iter()
+        .map(|(word, _)| String::from_utf8(word).unwrap())
+        .collect()
+}
+
+#[test]
+fn ties() {
+    let counter = Fixed::new(&[("b", 2), ("ccc", 1), ("dd", 2), ("a", 2), ("e", 1)]);
+    let cases = [
+        (Sort::Count, ["a", "b", "dd", "ccc", "e"]),
+        (Sort::CountAsc, ["ccc", "e", "a", "b", "dd"]),
+        (Sort::Alpha, ["a", "b", "ccc", "dd", "e"]),
+        (Sort::Length, ["ccc", "dd", "a", "b", "e"]),
+        (Sort::FirstSeen, ["b", "ccc", "dd", "a", "e"]),
+    ];
+    for (sort, expected) in cases {
+        assert_eq!(words(Select::new(sort), &counter), expected, "{}", sort);
+        assert_eq!(
+            words(Select::new(sort), &counter.reversed()),
+            expected,
+            "{}",
+            sort
+        );
+    }
+}
+
+#[test]
+fn bytes_not_codepoints() {
+    // "é" is 2 bytes and "z" is 1, and uppercase ASCII comes before
+    // lowercase
+    let counter = Fixed::new(&[("z", 1), ("é", 1), ("Z", 1), ("zz", 1)]);
+    assert_eq!(
+        words(Select::new(Sort::Alpha), &counter),
+        ["Z", "z", "zz", "é"]
+    );
+    assert_eq!(
+        words(Select::new(Sort::Length), &counter),
+        ["zz", "é", "Z", "z"]
+    );
+}
+
+#[test]
+fn every_engine() {
+    let input = "the cat and the dog and the bird saw a cat\nbird dog the\n";
+    let bump = Bump::new();
+    for sort in Sort::ALL {
+        let mut expected = None;
+        for engine in Engine::ALL {
+            let mut counter = engine.counter(&bump);
+            countwords_core::count(input.as_bytes(), &mut *counter).unwrap();
+            let words = words(Select::new(sort), &counter);
+            let expected = expected.get_or_insert_with(|| words.clone());
+            assert_eq!(&words, expected, "{} {}", engine, sort);
+        }
+    }
+}
+
+#[test]
+fn top_zero() {
+    let counter = Fixed::new(&[("b", 2), ("a", 1)]);
+    for sort in Sort::ALL {
+        for counter in [&counter, &counter.in_byte_order()] {
+            let select = Select::new(sort).top(0);
+            assert_eq!(select.apply(counter), vec![], "{}", sort);
+            select.for_each(counter, &mut |word, _| panic!("{:?}", word));
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn top_is_a_prefix(
+        counts in prop::collection::hash_map("[a-d]{1,3}", 1u64..5, 0..30),
+        n in 0usize..35,
+        min_count in 0u64..3,
+        max_len in 1usize..4,
+    ) {
+        let counts: Vec<(&str, u64)> = counts.iter().map(|(w, &c)| (&w[..], c)).collect();
+        let counter = Fixed::new(&counts);
+        for sort in Sort::ALL {
+            let all: Vec<(Vec<u8>, u64)> = model(sort, &counter)
+                .into_iter()
+                .filter(|(word, count)| *count >= min_count && word.len() <= max_len)
+                .collect();
+            let top = &all[..n.min(all.len())];
+            let select = Select::new(sort).min_count(min_count).max_len(max_len);
+            // in byte order, Count and CountAsc are bucketed, and Alpha is
+            // streamed
+            for counter in [&counter, &counter.reversed(), &counter.in_byte_order()] {
+                prop_assert_eq!(&select.apply(counter), &all, "{}", sort);
+                prop_assert_eq!(&select.clone().top(n).apply(counter), top, "{}", sort);
+                let mut each = vec![];
+                select.clone().top(n).for_each(counter, &mut |word, count| {
+                    each.push((word.to_vec(), count));
+                });
+                prop_assert_eq!(&each[..], top, "{}", sort);
+            }
+        }
+    }
+}
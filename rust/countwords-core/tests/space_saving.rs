@@ -0,0 +1,106 @@
+// Checks the guarantees `SpaceSaving` documents against exact counts: an
+// estimate never undercounts a word, and overcounts it by at most its error,
+// and a word that isn't reported occurred at most `max_unreported` times.
+// Words come from a tiny alphabet, and there are few counters, so that words
+// keep taking each other's counters. Also checks that stop words don't take
+// counters from the words that are reported.
+
+use std::collections::HashMap;
+
+use countwords_core::{
+    pipeline::Language, FastSimple, Feed, Select, Sort, SpaceSaving, StopWords, WordCounter,
+};
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn never_undercounts(
+        words in prop::collection::vec("[a-f]{1,2}", 0..200),
+        k in 1usize..10,
+    ) {
+        let mut counter = SpaceSaving::new(k);
+        counter.feed(words.join(" ").as_bytes()).unwrap();
+        counter.finish().unwrap();
+
+        let mut exact: HashMap<&str, u64> = HashMap::new();
+        for word in words.iter() {
+            *exact.entry(word).or_default() += 1;
+        }
+        let total = words.len() as u64;
+        prop_assert_eq!(counter.total(), total);
+        prop_assert!(counter.len() <= k);
+        prop_assert!(counter.max_unreported() <= total / k as u64);
+
+        let mut counted = 0;
+        counter.for_each_estimate(|word, count, error| {
+            counted += count;
+            let word = std::str::from_utf8(word).unwrap();
+            let exact = exact.get(word).copied().unwrap_or(0);
+            assert!(count >= exact, "{} {} {}", word, count, exact);
+            assert!(count - error <= exact, "{} {} {} {}", word, count, error, exact);
+        });
+        // every word counted goes to exactly one counter
+        prop_assert_eq!(counted, total);
+
+        for (word, &exact) in exact.iter() {
+            match counter.estimate(word.as_bytes()) {
+                Some((count, error)) => {
+                    prop_assert!(exact <= count && count - error <= exact, "{}", word);
+                }
+                None => prop_assert!(exact <= counter.max_unreported(), "{}", word),
+            }
+        }
+    }
+}
+
+#[test]
+fn exact_with_room() {
+    // with a counter for every word, it's an ordinary count
+    let mut counter = SpaceSaving::new(10);
+    counter.feed(b"the cat and the dog").unwrap();
+    counter.finish().unwrap();
+    assert_eq!(counter.max_unreported(), 0);
+    assert_eq!(
+        countwords_core::sorted(&counter),
+        [
+            (b"the".to_vec(), 2),
+            (b"and".to_vec(), 1),
+            (b"cat".to_vec(), 1),
+            (b"dog".to_vec(), 1),
+        ]
+    );
+}
+
+#[test]
+fn stop_words_take_no_counters() {
+    let mut input = "The and of\n".repeat(100);
+    input.push_str(&"cat dog bird\n".repeat(15));
+    input.push_str(&"cat dog\n".repeat(5));
+    input.push_str(&"cat\n".repeat(10));
+    input.push_str("alpha beta gamma delta epsilon\n");
+    let stop = StopWords::builtin(Language::English);
+    let select = Select::new(Sort::Count).top(3).stopwords(stop.clone());
+
+    let mut exact = FastSimple::new();
+    countwords_core::count(input.as_bytes(), &mut exact).unwrap();
+    let expected = select.apply(&exact);
+    assert_eq!(
+        expected,
+        [
+            (b"cat".to_vec(), 30),
+            (b"dog".to_vec(), 20),
+            (b"bird".to_vec(), 15)
+        ]
+    );
+
+    let mut counter = SpaceSaving::new(5).stopwords(stop);
+    countwords_core::count(input.as_bytes(), &mut counter).unwrap();
+    assert_eq!(select.apply(&counter), expected);
+    assert_eq!(counter.total(), 70);
+    assert_eq!(counter.estimate(b"the"), None);
+
+    // counted, stop words crowd the others out, however they're selected
+    let mut counter = SpaceSaving::new(5);
+    countwords_core::count(input.as_bytes(), &mut counter).unwrap();
+    assert_ne!(select.apply(&counter), expected);
+}
@@ -0,0 +1,88 @@
+// Checks that stop words are left out of what every engine's counts select,
+// and only them, and that a stemmed pipeline leaves them out before
+// stemming.
+
+use bumpalo::Bump;
+use countwords_core::{
+    pipeline::{Language, Stemmer},
+    Engine, Pipeline, Select, Sort, StopWords, TrieCounter, Unicode,
+};
+
+const INPUT: &str = "The LORD is my shepherd I shall not want\n\
+                     And the earth was without form and void\n\
+                     In the beginning God created the heaven and the earth\n";
+
+#[test]
+fn every_engine() {
+    let stop = StopWords::builtin(Language::English);
+    let bump = Bump::new();
+    for engine in Engine::ALL {
+        let mut counter = engine.counter(&bump);
+        countwords_core::count(INPUT.as_bytes(), &mut *counter).unwrap();
+        let expected: Vec<(Vec<u8>, u64)> = Select::new(Sort::Count)
+            .apply(&*counter)
+            .into_iter()
+            .filter(|(word, _)| !stop.contains(word))
+            .collect();
+        assert_eq!(expected[0], (b"earth".to_vec(), 2), "{}", engine);
+        for sort in [Sort::Count, Sort::Alpha] {
+            let select = Select::new(sort).stopwords(stop.clone());
+            let mut selected = select.apply(&*counter);
+            selected.sort_by(|a, b| Sort::Count.compare((&a.0, a.1, 0), (&b.0, b.1, 0)));
+            assert_eq!(selected, expected, "{} {}", engine, sort);
+        }
+    }
+}
+
+#[test]
+fn streamed() {
+    let mut counter = TrieCounter::new();
+    countwords_core::count(INPUT.as_bytes(), &mut counter).unwrap();
+    let select = Select::new(Sort::Alpha).stopwords(StopWords::builtin(Language::English));
+    assert!(select.is_streamed(&counter));
+    let mut words = vec![];
+    select.for_each(&counter, &mut |word, _| words.push(word.to_vec()));
+    assert_eq!(words.first().map(|w| &w[..]), Some(&b"beginning"[..]));
+    assert!(!words.iter().any(|word| word == b"the" || word == b"and"));
+    assert_eq!(words.len(), 12);
+}
+
+#[test]
+fn lists() {
+    for language in Language::ALL {
+        let stop = StopWords::builtin(language);
+        assert!(stop.len() > 100, "{}", language);
+    }
+    assert!(StopWords::builtin(Language::German).contains("für".as_bytes()));
+    assert!(StopWords::builtin(Language::French).contains("été".as_bytes()));
+    assert!(StopWords::builtin(Language::Spanish).contains("él".as_bytes()));
+    assert!(!StopWords::builtin(Language::English).contains(b"#"));
+
+    let mut stop = StopWords::new();
+    let list = "# a comment\nLORD\n\n  STRASSE Straße \r\nx";
+    stop.add_list(list.as_bytes()).unwrap();
+    assert_eq!(stop.len(), 3);
+    assert!(stop.contains(b"lord"));
+    assert!(!stop.contains(b"LORD"));
+    assert!(stop.contains("strasse straße".as_bytes()));
+    assert!(stop.contains(b"x"));
+}
+
+#[test]
+fn before_stemming() {
+    // "doings" stems to "do", which is a stop word, but "doings" isn't.
+    let input = "The doings of the deed, done doings\n";
+    let pipeline = Pipeline::default()
+        .filter(StopWords::builtin(Language::English))
+        .stem(Stemmer::new(Language::English));
+    let mut counter = Unicode::<u64>::with_pipeline(pipeline);
+    countwords_core::count(input.as_bytes(), &mut counter).unwrap();
+    assert_eq!(
+        Select::new(Sort::Count).apply(&counter),
+        vec![
+            (b"do".to_vec(), 2),
+            (b"deed".to_vec(), 1),
+            (b"done".to_vec(), 1),
+        ]
+    );
+}
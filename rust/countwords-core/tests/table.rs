@@ -0,0 +1,232 @@
+// Checks `Table` against std's `HashMap` under random sequences of operations.
+// Words come from a tiny alphabet so that the same ones come up again and
+// again, and tables start small so that they grow along the way. Also checks
+// that tables grow rather than fill up, and that counts don't overflow.
+
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, BuildHasherDefault, Hasher},
+};
+
+use countwords_core::{
+    hash::{Fnv1a, SipHash},
+    table::Entry,
+    CountOverflow, Feed, Table, TableCounter, WordCounter,
+};
+use proptest::prelude::*;
+
+/// A terrible hash: the number of bytes hashed. Every word of the same length
+/// collides, which makes for long runs of occupied slots that wrap around the
+/// end of the table.
+#[derive(Clone, Debug, Default)]
+struct LengthHasher(u64);
+
+impl Hasher for LengthHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 += bytes.len() as u64;
+    }
+}
+
+type Length = BuildHasherDefault<LengthHasher>;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Get(Vec<u8>),
+    Add(Vec<u8>, u64),
+    Remove(Vec<u8>),
+    Entry(Vec<u8>, u64),
+    RemoveEntry(Vec<u8>),
+    Clear,
+}
+
+fn word() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(prop::sample::select(b"abc\xFF".to_vec()), 0..5)
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => word().prop_map(Op::Get),
+        8 => (word(), 0..10u64).prop_map(|(w, n)| Op::Add(w, n)),
+        3 => word().prop_map(Op::Remove),
+        4 => (word(), 0..10u64).prop_map(|(w, n)| Op::Entry(w, n)),
+        2 => word().prop_map(Op::RemoveEntry),
+        1 => Just(Op::Clear),
+    ]
+}
+
+fn check<S: BuildHasher>(mut table: Table<S>, ops: Vec<Op>) -> Result<(), TestCaseError> {
+    let mut model: HashMap<Vec<u8>, u64> = HashMap::new();
+    for op in ops {
+        match op {
+            Op::Get(w) => {
+                prop_assert_eq!(table.get(&w), model.get(&w).copied());
+            }
+            Op::Add(w, n) => {
+                let count = model.entry(w.clone()).or_insert(0);
+                *count += n;
+                prop_assert_eq!(table.add(&w, n).unwrap(), *count);
+            }
+            Op::Remove(w) => {
+                prop_assert_eq!(table.remove(&w), model.remove(&w));
+            }
+            Op::Entry(w, n) => {
+                let hash = table.hash(&w);
+                let entry = table.entry(&w, hash);
+                prop_assert_eq!(entry.key(), &w[..]);
+                let count = entry.and_modify(|c| *c *= 2).or_insert(n);
+                let expected = model.entry(w).and_modify(|c| *c *= 2).or_insert(n);
+                prop_assert_eq!(*count, *expected);
+            }
+            Op::RemoveEntry(w) => {
+                let hash = table.hash(&w);
+                match table.entry(&w, hash) {
+                    Entry::Occupied(e) => {
+                        prop_assert_eq!(e.get(), model[&w]);
+                        prop_assert_eq!(e.remove(), model.remove(&w).unwrap());
+                    }
+                    Entry::Vacant(_) => prop_assert!(!model.contains_key(&w)),
+                }
+            }
+            Op::Clear => {
+                let capacity = table.capacity();
+                table.clear();
+                model.clear();
+                prop_assert_eq!(table.capacity(), capacity);
+            }
+        }
+        prop_assert_eq!(table.len(), model.len());
+        prop_assert_eq!(table.is_empty(), model.is_empty());
+        prop_assert!(table.len() <= table.capacity());
+    }
+
+    let iter = table.iter();
+    prop_assert_eq!(iter.len(), model.len());
+    let got: HashMap<Vec<u8>, u64> = iter.map(|(w, c)| (w.to_vec(), c)).collect();
+    prop_assert_eq!(got, model.clone());
+    for (w, &count) in model.iter() {
+        prop_assert_eq!(table.get(w), Some(count));
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn fnv1(ops in prop::collection::vec(op(), 0..200), capacity in 0..8usize) {
+        check(Table::with_capacity(capacity), ops)?;
+    }
+
+    #[test]
+    fn fnv1a_full(ops in prop::collection::vec(op(), 0..200), capacity in 0..8usize) {
+        let table = Table::with_capacity_and_hasher(capacity, Fnv1a::default());
+        check(table.load_factor(0.99), ops)?;
+    }
+
+    #[test]
+    fn siphash(ops in prop::collection::vec(op(), 0..200)) {
+        check(Table::with_capacity_and_hasher(0, SipHash::new()), ops)?;
+    }
+
+    #[test]
+    fn colliding(ops in prop::collection::vec(op(), 0..200), load in 0.1..0.99f64) {
+        let table = Table::with_capacity_and_hasher(0, Length::default());
+        check(table.load_factor(load), ops)?;
+    }
+}
+
+#[test]
+fn capacity_is_kept() {
+    let table = Table::with_capacity(1000);
+    assert!(table.capacity() >= 1000);
+    let table = table.load_factor(0.9);
+    assert!(table.capacity() >= 1000);
+    let table = Table::with_capacity(1000).load_factor(0.05);
+    assert!(table.capacity() >= 1000);
+}
+
+#[test]
+fn entry_with_precomputed_hash() {
+    let mut table = Table::new();
+    let words: &[&[u8]] = &[b"in", b"the", b"beginning", b"the"];
+    for &word in words {
+        let mut hasher = table.hasher().build_hasher();
+        for &b in word {
+            hasher.write_u8(b);
+        }
+        let hash = hasher.finish();
+        assert_eq!(hash, table.hash(word));
+        *table.entry(word, hash).or_insert(0) += 1;
+    }
+    assert_eq!(table.get(b"the"), Some(2));
+    assert_eq!(table.get(b"in"), Some(1));
+    assert_eq!(table.get(b"god"), None);
+    assert_eq!(table.len(), 3);
+}
+
+#[test]
+fn long_probe_sequences() {
+    // Every word collides, so that some end up hundreds of slots from home.
+    let mut table = Table::with_capacity_and_hasher(0, Length::default()).load_factor(0.9);
+    let words: Vec<Vec<u8>> = (0..1000u32).map(|i| i.to_be_bytes().to_vec()).collect();
+    for (i, word) in words.iter().enumerate() {
+        assert_eq!(table.add(word, i as u64).unwrap(), i as u64);
+    }
+    for word in words.iter().step_by(3) {
+        assert!(table.remove(word).is_some());
+    }
+    for (i, word) in words.iter().enumerate() {
+        let expected = if i % 3 == 0 { None } else { Some(i as u64) };
+        assert_eq!(table.get(word), expected);
+    }
+    assert_eq!(table.get(b"\xFF\xFF\xFF\xFF"), None);
+}
+
+#[test]
+fn grows_past_the_c_table() {
+    // The C program's table has 64K slots, and never stops probing for a free
+    // one once they're all taken.
+    let n = 100_000;
+    let mut counter = TableCounter::new();
+    let capacity = counter.capacity();
+    for i in 0..n {
+        counter
+            .feed(format!("w{} w{}\n", i, i % 10).as_bytes())
+            .unwrap();
+    }
+    counter.finish().unwrap();
+    assert_eq!(counter.len(), n);
+    assert!(counter.capacity() >= n && counter.capacity() > capacity);
+    let table = counter.table();
+    assert_eq!(table.get(b"w5"), Some(n as u64 / 10 + 1));
+    assert_eq!(table.get(b"w99999"), Some(1));
+    assert_eq!(table.get(b"w100000"), None);
+}
+
+#[test]
+fn never_full() {
+    // Even at the highest load factor, there's always a free slot to stop
+    // probing at, so looking up a word that isn't there always ends.
+    let mut table = Table::with_capacity_and_hasher(0, Length::default()).load_factor(0.999);
+    for i in 0..2000u32 {
+        table.add(&i.to_be_bytes(), 1).unwrap();
+        assert!(table.len() <= table.capacity());
+        assert_eq!(table.get(b"\xFF\xFF\xFF\xFF"), None);
+        assert_eq!(table.get(b"absent"), None);
+    }
+}
+
+#[test]
+fn add_overflow() {
+    let mut table = Table::new();
+    assert_eq!(table.add(b"the", u64::MAX - 1).unwrap(), u64::MAX - 1);
+    assert_eq!(table.add(b"the", 1).unwrap(), u64::MAX);
+    let err = table.add(b"the", 1).unwrap_err();
+    assert!(CountOverflow::is(&err.into()));
+    assert_eq!(table.get(b"the"), Some(u64::MAX));
+    assert_eq!(table.add(b"the", 0).unwrap(), u64::MAX);
+}
@@ -0,0 +1,263 @@
+// Checks `TrieCounter` and `RadixTrieCounter` on input that isn't ASCII,
+// against a model that splits and lowercases words the same way with a
+// `HashMap`. Each input is fed in chunks of every size up to a few bytes, so
+// that multi-byte characters are split across chunks too.
+
+use std::collections::HashMap;
+
+use countwords_core::{
+    Format, InvalidUtf8, Output, RadixTrieCounter, Select, Sort, TrieCounter, TrieIndex,
+    WordCounter,
+};
+use proptest::prelude::*;
+
+/// Returns each word's count and the order in which it was first seen.
+fn model(input: &[u8]) -> HashMap<Vec<u8>, (u64, usize)> {
+    let mut counts: HashMap<Vec<u8>, (u64, usize)> = HashMap::new();
+    let words = input
+        .split(|&b| b == b' ' || b == b'\n' || b == b'\r')
+        .filter(|w| !w.is_empty());
+    for word in words {
+        let seen = counts.len();
+        counts
+            .entry(word.to_ascii_lowercase())
+            .or_insert((0, seen))
+            .0 += 1;
+    }
+    counts
+}
+
+fn trie<C: WordCounter>(
+    mut counter: C,
+    input: &[u8],
+    chunk_size: usize,
+) -> HashMap<Vec<u8>, (u64, usize)> {
+    for chunk in input.chunks(chunk_size) {
+        counter.feed(chunk).unwrap();
+    }
+    counter.finish().unwrap();
+    let mut counts = HashMap::new();
+    counter.for_each_seen(&mut |word, count, seen| {
+        assert!(counts.insert(word.to_vec(), (count, seen)).is_none());
+    });
+    assert_eq!(counter.len(), counts.len());
+    counts
+}
+
+fn check(input: &[u8]) {
+    let expected = model(input);
+    let whole = input.len().max(1);
+    for chunk_size in (1..=5).chain([whole]) {
+        let dense = trie(TrieCounter::new(), input, chunk_size);
+        assert_eq!(dense, expected, "dense, chunk size {}", chunk_size);
+        let radix = trie(RadixTrieCounter::new(), input, chunk_size);
+        assert_eq!(radix, expected, "radix, chunk size {}", chunk_size);
+    }
+    check_order(input, &expected);
+}
+
+/// Checks that the trie's words come out in byte order, and that selecting
+/// them in the orders that rely on that matches sorting the model.
+fn check_order(input: &[u8], expected: &HashMap<Vec<u8>, (u64, usize)>) {
+    let mut counter = TrieCounter::new();
+    countwords_core::count(input, &mut counter).unwrap();
+    let mut sorted: Vec<(Vec<u8>, u64)> = expected
+        .iter()
+        .map(|(word, &(count, _))| (word.clone(), count))
+        .collect();
+    sorted.sort();
+
+    let mut words = counter.words();
+    let mut walked = vec![];
+    while let Some((word, count)) = words.next_word() {
+        walked.push((word.to_vec(), count));
+    }
+    assert_eq!(walked, sorted);
+
+    for sort in [Sort::Alpha, Sort::Count, Sort::CountAsc] {
+        let mut ordered = sorted.clone();
+        ordered.sort_by(|a, b| sort.compare((&a.0, a.1, 0), (&b.0, b.1, 0)));
+        let selects = [
+            (Select::new(sort), usize::MAX),
+            (Select::new(sort).top(3).min_count(2), 3),
+        ];
+        for (select, top) in selects {
+            let expected: Vec<(Vec<u8>, u64)> = ordered
+                .iter()
+                .filter(|(word, count)| select.keeps(word, *count))
+                .take(top)
+                .cloned()
+                .collect();
+            assert_eq!(select.apply(&counter), expected, "{:?}", select);
+        }
+    }
+}
+
+#[test]
+fn utf8() {
+    check(
+        "Ünïcödé naïve café CAFÉ Café café\n\
+         Στην αρχή ήταν ο Λόγος στην ΑΡΧΉ\r\n\
+         日本語 の テキスト 日本語 日本\n\
+         emoji 🦀 🦀🦀 🦀 ÿ ÿ\n"
+            .as_bytes(),
+    );
+}
+
+#[test]
+fn every_byte() {
+    // Every byte on its own, then next to every other byte, so that each
+    // escape is followed by each byte it can be followed by.
+    let mut input = vec![];
+    for a in 0..=255u8 {
+        input.extend_from_slice(&[a, b' ']);
+    }
+    for a in 0..=255u8 {
+        for b in (0..=255u8).step_by(17) {
+            input.extend_from_slice(&[a, b, a, b'\n']);
+        }
+    }
+    check(&input);
+}
+
+#[test]
+fn escapes_are_not_words() {
+    // The bytes that share a node's children with the escapes, and prefixes
+    // of one another that go through the same escape.
+    check(b"\x00 \x01 \x00\x01 \x80 \x81 \x80\x80 \x80\x00 \x00\x80 \xFF\x01\xFF \x80");
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn arbitrary_bytes(input in prop::collection::vec(prop::sample::select(
+        b"ab \n\r\x00\x01\x7F\x80\x81\xC3\xA9\xFF".to_vec()), 0..200)) {
+        check(&input);
+    }
+
+    #[test]
+    fn arbitrary_text(input in "[a-zA-Zé日本 \n]{0,100}") {
+        check(input.as_bytes());
+    }
+}
+
+#[test]
+fn prefixes() {
+    let input = "bless blessed blessing Bless blessed BLESSED bliss blés blés blé b\n";
+    let mut counter = TrieCounter::new();
+    countwords_core::count(input.as_bytes(), &mut counter).unwrap();
+    let counts = model(input.as_bytes());
+    for prefix in [
+        "", "b", "bl", "BLE", "bless", "blessed", "blé", "blés", "blx", "x",
+    ] {
+        let lower = prefix.to_ascii_lowercase();
+        let mut expected: Vec<(Vec<u8>, u64)> = counts
+            .iter()
+            .filter(|(word, _)| word.starts_with(lower.as_bytes()))
+            .map(|(word, &(count, _))| (word.clone(), count))
+            .collect();
+        expected.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let all = counter.completions(prefix.as_bytes(), &Select::new(Sort::Count));
+        assert_eq!(all, expected, "prefix {:?}", prefix);
+        let top = counter.completions(prefix.as_bytes(), &Select::new(Sort::Count).top(2));
+        assert_eq!(top, expected.iter().take(2).cloned().collect::<Vec<_>>());
+        let total = expected.iter().map(|&(_, count)| count).sum();
+        assert_eq!(
+            counter.prefix_count(prefix.as_bytes()),
+            (total, expected.len())
+        );
+    }
+}
+
+#[test]
+fn streamed_output() {
+    let input = "the cat The dog a cat b\x01 \x00 é\n";
+    let mut counter = TrieCounter::new();
+    countwords_core::count(input.as_bytes(), &mut counter).unwrap();
+    let select = Select::new(Sort::Alpha);
+    assert!(select.is_streamed(&counter));
+    assert!(!Select::new(Sort::Count).is_streamed(&counter));
+    for format in Format::ALL {
+        for policy in InvalidUtf8::ALL {
+            let output = Output::new(format).invalid_utf8(policy);
+            let mut streamed = vec![];
+            output
+                .write_each(&mut streamed, |f| select.for_each(&counter, f))
+                .unwrap();
+            let mut collected = vec![];
+            output
+                .write_counts(&mut collected, &select.apply(&counter))
+                .unwrap();
+            assert_eq!(streamed, collected, "{} {}", format, policy);
+        }
+    }
+}
+
+#[test]
+fn index() {
+    let input = "bless blessed blessing Bless blessed BLESSED bliss blés blés blé b\n\
+                 \x00 \x01 ÿ\x01ÿ\n";
+    let mut counter = TrieCounter::new();
+    countwords_core::count(input.as_bytes(), &mut counter).unwrap();
+    let mut bytes = vec![];
+    counter.write_index(&mut bytes).unwrap();
+    let index = TrieIndex::new(&bytes[..]).unwrap();
+    assert_eq!(index.len(), counter.len());
+
+    for (word, &(count, _)) in model(input.as_bytes()).iter() {
+        assert_eq!(index.count(word), count, "word {:?}", word);
+    }
+    assert_eq!(index.count(b"BLESSED"), 3);
+    assert_eq!(index.count(b"bles"), 0);
+    assert_eq!(index.count(b"nope"), 0);
+    for prefix in ["", "b", "BLE", "blessed", "blé", "ÿ", "x"] {
+        let prefix = prefix.as_bytes();
+        let select = Select::new(Sort::FirstSeen);
+        assert_eq!(
+            index.completions(prefix, &select),
+            counter.completions(prefix, &select),
+            "prefix {:?}",
+            prefix
+        );
+        assert_eq!(index.prefix_count(prefix), counter.prefix_count(prefix));
+    }
+}
+
+#[test]
+fn corrupt_index() {
+    let mut counter = TrieCounter::new();
+    countwords_core::count(&b"a b c\n"[..], &mut counter).unwrap();
+    let mut bytes = vec![];
+    counter.write_index(&mut bytes).unwrap();
+
+    let err = |bytes: &[u8]| TrieIndex::new(bytes).unwrap_err().to_string();
+    TrieIndex::new(&bytes[..]).unwrap().verify().unwrap();
+    // only verifying reads far enough to find a flipped bit
+    let mut flipped = bytes.clone();
+    *flipped.last_mut().unwrap() ^= 1;
+    let index = TrieIndex::new(&flipped[..]).unwrap();
+    let verified = index.verify().unwrap_err().to_string();
+    assert_eq!(verified, "index checksum mismatch");
+    assert_eq!(err(&bytes[..bytes.len() - 1]), "invalid index size");
+    assert_eq!(err(b"cwcmsk\0\0"), "not a trie index");
+    let mut version = bytes.clone();
+    version[8] = 2;
+    assert_eq!(err(&version), "unsupported index version 2");
+}
+
+#[test]
+fn index_totals_saturate() {
+    let mut counter = TrieCounter::new();
+    countwords_core::count(&b"ab ac\n"[..], &mut counter).unwrap();
+    let mut bytes = vec![];
+    counter.write_index(&mut bytes).unwrap();
+    // every count in the index at its maximum, which makes a word of "a" too
+    let nodes = (bytes.len() - 32) / (128 * 4 + 8 + 4);
+    let counts = 32 + nodes * 128 * 4;
+    bytes[counts..counts + nodes * 8].fill(0xFF);
+    let index = TrieIndex::new(&bytes[..]).unwrap();
+    assert_eq!(index.count(b"ab"), u64::MAX);
+    assert_eq!(index.prefix_count(b"a"), (u64::MAX, 3));
+}
@@ -0,0 +1,113 @@
+// --distinct only answers "how many different words are there?", which every
+// engine answers exactly, but only by keeping every one of them in memory. By
+// default it's estimated with HyperLogLog instead, in a few kilobytes. The
+// sketch can be saved and merged with sketches of other inputs later, so that
+// shards can be counted separately. --exact counts with --engine as usual and
+// prints only the size of its map, which is there to check the estimate
+// against, so by default its engine is custom-table, which splits words
+// exactly like the sketch does.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use countwords_core::{HyperLogLog, WordCounter};
+
+use crate::input::{with_path, Errors, Input};
+
+/// The flags that control --distinct.
+#[derive(Debug, clap::Args)]
+pub(crate) struct DistinctArgs {
+    /// Print the number of distinct words instead of their counts. It's
+    /// estimated with HyperLogLog, in fixed memory, and printed along with
+    /// its relative standard error. Words are split like custom-table does,
+    /// and --engine is ignored.
+    #[arg(long, conflicts_with_all = ["per_file", "approx_top"])]
+    pub(crate) distinct: bool,
+
+    /// The HyperLogLog precision, from 4 to 18. The sketch has 2^P registers
+    /// of one byte each, and the standard error is 1.04/sqrt(2^P).
+    #[arg(
+        long,
+        default_value_t = 14,
+        value_name = "P",
+        value_parser = clap::value_parser!(u8).range(4..=18),
+        requires = "distinct",
+    )]
+    precision: u8,
+
+    /// Count distinct words exactly with --engine and print only how many
+    /// there are. This needs memory for every word, and is meant for
+    /// checking estimates. Unless --engine is given, words are split like the
+    /// estimate splits them.
+    #[arg(long, requires = "distinct", conflicts_with_all = ["save_sketch", "merge_sketch"])]
+    pub(crate) exact: bool,
+
+    /// Also write the HyperLogLog sketch to FILE, so that it can be merged
+    /// with --merge-sketch later.
+    #[arg(long, value_name = "FILE", requires = "distinct")]
+    save_sketch: Option<PathBuf>,
+
+    /// Merge a sketch written by --save-sketch into the estimate. May be
+    /// given more than once. Sketches must have the same --precision. When
+    /// no paths are given, stdin isn't counted.
+    #[arg(long, value_name = "FILE", requires = "distinct")]
+    merge_sketch: Vec<PathBuf>,
+}
+
+impl DistinctArgs {
+    /// Estimates the number of distinct words in the inputs and any merged
+    /// sketches, and prints it. `stdin_only` is whether the inputs are stdin
+    /// because no paths were given. Returns false if an error was reported
+    /// but skipped over.
+    pub(crate) fn estimate(
+        &self,
+        inputs: &[Input],
+        stdin_only: bool,
+        errors: &mut Errors,
+    ) -> Result<bool, Box<dyn Error>> {
+        let mut hll = HyperLogLog::new(self.precision);
+        // merging saved sketches shouldn't wait on stdin
+        let only_merging = stdin_only && !self.merge_sketch.is_empty();
+        if !only_merging {
+            crate::count_inputs(inputs, None, &mut hll, errors)?;
+        }
+        for path in self.merge_sketch.iter() {
+            hll.merge(&read(path)?)
+                .map_err(|err| with_path(path, err))?;
+        }
+        if let Some(ref path) = self.save_sketch {
+            File::create(path)
+                .and_then(|file| hll.write_to(file))
+                .map_err(|err| with_path(path, err))?;
+        }
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "estimate {}", hll.estimate().round())?;
+        writeln!(stdout, "standard-error {:.6}", hll.standard_error())?;
+        Ok(!errors.any())
+    }
+
+    /// Counts every distinct word in the inputs with `counter`, and prints
+    /// how many there are. `first` is the already opened first input, if
+    /// any. Returns false if an error was reported but skipped over.
+    pub(crate) fn exact(
+        &self,
+        counter: &mut dyn WordCounter,
+        inputs: &[Input],
+        first: Option<io::Result<Box<dyn Read>>>,
+        errors: &mut Errors,
+    ) -> Result<bool, Box<dyn Error>> {
+        crate::count_inputs(inputs, first, &mut *counter, errors)?;
+        writeln!(io::stdout().lock(), "{}", counter.len())?;
+        Ok(!errors.any())
+    }
+}
+
+fn read(path: &Path) -> io::Result<HyperLogLog> {
+    File::open(path)
+        .and_then(HyperLogLog::read_from)
+        .map_err(|err| with_path(path, err))
+}
@@ -0,0 +1,122 @@
+// `countwords index` saves the trie that counting built, so that a fixed
+// corpus only has to be counted once. Queries memory map the saved trie and
+// only read the nodes on the way to the words they ask about, which makes
+// them about as quick as a lookup in the trie before it was saved. That's why
+// an index is only checked against its checksum when --verify asks for it.
+
+use std::{error::Error, fs::File, io, path::PathBuf};
+
+use clap::Subcommand;
+use countwords_core::{Output, Select, Sort, TrieCounter, TrieIndex};
+
+use crate::input::{with_path, InputArgs};
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum IndexCommand {
+    /// Count the words in the given files, or stdin, with the trie engine,
+    /// and save the trie as an index.
+    Build {
+        /// Where to write the index.
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Print the count of each word in an index. Words are lowercased like
+    /// counted words are.
+    Query {
+        /// The index to query.
+        #[arg(long, value_name = "FILE")]
+        index: PathBuf,
+
+        /// Treat each word as a prefix, and print the words that start with
+        /// it, most frequent first.
+        #[arg(long)]
+        prefix: bool,
+
+        /// With --prefix, only print the N most frequent words for each
+        /// prefix.
+        #[arg(long, value_name = "N", requires = "prefix")]
+        top: Option<usize>,
+
+        /// With --prefix, print the total count of the words that start with
+        /// each prefix, and how many distinct words that is, instead of the
+        /// words.
+        #[arg(long, requires = "prefix", conflicts_with = "top")]
+        totals: bool,
+
+        /// Check the index against its checksum before querying it. This
+        /// reads all of it, which queries otherwise don't.
+        #[arg(long)]
+        verify: bool,
+
+        /// The words, or prefixes, to look up.
+        #[arg(value_name = "WORD", required = true)]
+        words: Vec<String>,
+    },
+}
+
+/// Runs an index command. Returns false if an error was reported but skipped
+/// over.
+pub(crate) fn run(cmd: IndexCommand) -> Result<bool, Box<dyn Error>> {
+    match cmd {
+        IndexCommand::Build { output, input } => {
+            let (inputs, mut errors) = input.collect()?;
+            // Counts are always u64 in an index, so there's no reason to
+            // count with anything narrower.
+            let mut counter = TrieCounter::<u64>::default();
+            crate::count_inputs(&inputs, None, &mut counter, &mut errors)?;
+            File::create(&output)
+                .and_then(|file| counter.write_index(file))
+                .map_err(|err| with_path(&output, err))?;
+            Ok(!errors.any())
+        }
+        IndexCommand::Query {
+            index,
+            prefix,
+            top,
+            totals,
+            verify,
+            words,
+        } => {
+            let trie = TrieIndex::open(&index).map_err(|err| with_path(&index, err))?;
+            if verify {
+                trie.verify().map_err(|err| with_path(&index, err))?;
+            }
+            let output = Output::default();
+            let stdout = io::stdout().lock();
+            if totals {
+                let rows: Vec<(&[u8], [u64; 2])> = words
+                    .iter()
+                    .map(|prefix| {
+                        let (total, len) = trie.prefix_count(prefix.as_bytes());
+                        (prefix.as_bytes(), [total, len as u64])
+                    })
+                    .collect();
+                let rows = rows.iter().map(|&(prefix, ref row)| (prefix, &row[..]));
+                output.write_table(stdout, &["prefix", "total", "words"], rows)?;
+            } else if prefix {
+                let mut select = Select::new(Sort::Count);
+                if let Some(n) = top {
+                    select = select.top(n);
+                }
+                let mut completions = vec![];
+                for prefix in words.iter() {
+                    completions.extend(trie.completions(prefix.as_bytes(), &select));
+                }
+                output.write_counts(stdout, &completions)?;
+            } else {
+                let counts: Vec<(Vec<u8>, u64)> = words
+                    .into_iter()
+                    .map(|word| {
+                        let count = trie.count(word.as_bytes());
+                        (word.into_bytes(), count)
+                    })
+                    .collect();
+                output.write_counts(stdout, &counts)?;
+            }
+            Ok(true)
+        }
+    }
+}
@@ -0,0 +1,233 @@
+// Where the words come from: stdin when no paths are given, otherwise every
+// file named on the command line plus every file found by recursively walking
+// the directories named there.
+//
+// Files named explicitly are always counted. The --include/--exclude globs
+// and --gitignore only decide which files a directory walk turns up, which is
+// how ripgrep treats them too.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+
+/// A single input to count.
+#[derive(Clone, Debug)]
+pub(crate) enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    /// Opens this input for reading.
+    pub(crate) fn open(&self) -> io::Result<Box<dyn Read>> {
+        match *self {
+            Input::Stdin => Ok(Box::new(io::stdin().lock())),
+            Input::File(ref path) => Ok(Box::new(File::open(path)?)),
+        }
+    }
+
+    /// Returns the name of this input as it should be shown to the user.
+    pub(crate) fn name(&self) -> std::borrow::Cow<'_, str> {
+        match *self {
+            Input::Stdin => "-".into(),
+            Input::File(ref path) => path.to_string_lossy(),
+        }
+    }
+
+    /// Returns the size of this input, if it can be known without reading it.
+    /// Stdin only has a size when it's redirected from a regular file.
+    pub(crate) fn size(&self) -> Option<u64> {
+        let path = match *self {
+            Input::Stdin => Path::new("/dev/stdin"),
+            Input::File(ref path) => path,
+        };
+        let md = std::fs::metadata(path).ok()?;
+        if md.is_file() {
+            Some(md.len())
+        } else {
+            None
+        }
+    }
+
+    /// Attaches the name of this input to an error that occurred while
+    /// reading it.
+    pub(crate) fn error(&self, err: io::Error) -> io::Error {
+        match *self {
+            Input::Stdin => err,
+            Input::File(ref path) => with_path(path, err),
+        }
+    }
+}
+
+/// The flags that choose which inputs to count.
+#[derive(Debug, clap::Args)]
+pub(crate) struct InputArgs {
+    /// Files to count. Directories are searched recursively. With no paths,
+    /// or when a path is -, stdin is counted.
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    /// Only count files in directories whose path matches this glob. May be
+    /// given more than once.
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files and directories in directories whose path matches this
+    /// glob. May be given more than once, and wins over --include.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Skip files in directories that are ignored by .gitignore files, the
+    /// repository's exclude file or the global gitignore.
+    #[arg(long)]
+    gitignore: bool,
+
+    /// Report files that can't be read and keep going, instead of stopping
+    /// at the first one. The exit status is still non-zero.
+    #[arg(long)]
+    keep_going: bool,
+}
+
+impl InputArgs {
+    /// Returns true if no paths were given, i.e., only stdin would be
+    /// counted.
+    pub(crate) fn is_stdin_only(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Returns every input to count, along with what to do about the errors
+    /// that happen while counting them.
+    pub(crate) fn collect(&self) -> Result<(Vec<Input>, Errors), Box<dyn Error>> {
+        let walk = WalkOptions {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            gitignore: self.gitignore,
+        };
+        let mut errors = Errors::new(self.keep_going);
+        let inputs = collect(&self.paths, &walk, &mut errors)?;
+        Ok((inputs, errors))
+    }
+}
+
+/// Options that control which files a directory walk yields.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct WalkOptions {
+    pub(crate) include: Vec<String>,
+    pub(crate) exclude: Vec<String>,
+    pub(crate) gitignore: bool,
+}
+
+/// Decides whether an error about one input stops the whole run.
+#[derive(Clone, Debug)]
+pub(crate) struct Errors {
+    keep_going: bool,
+    reported: usize,
+}
+
+impl Errors {
+    pub(crate) fn new(keep_going: bool) -> Errors {
+        Errors {
+            keep_going,
+            reported: 0,
+        }
+    }
+
+    /// Returns the error if we're aborting on errors. Otherwise, it's printed
+    /// to stderr and the run carries on.
+    pub(crate) fn report<E>(&mut self, err: E) -> Result<(), Box<dyn Error>>
+    where
+        E: Into<Box<dyn Error>>,
+    {
+        let err = err.into();
+        if !self.keep_going {
+            return Err(err);
+        }
+        eprintln!("{}", err);
+        self.reported += 1;
+        Ok(())
+    }
+
+    /// Returns true if any error was reported and skipped over.
+    pub(crate) fn any(&self) -> bool {
+        self.reported > 0
+    }
+}
+
+/// Returns every input to count for the given paths, in the order given, with
+/// the contents of each directory sorted by file name.
+pub(crate) fn collect(
+    paths: &[PathBuf],
+    opts: &WalkOptions,
+    errors: &mut Errors,
+) -> Result<Vec<Input>, Box<dyn Error>> {
+    if paths.is_empty() {
+        return Ok(vec![Input::Stdin]);
+    }
+    let mut inputs = vec![];
+    for path in paths {
+        if path == Path::new("-") {
+            inputs.push(Input::Stdin);
+            continue;
+        }
+        match std::fs::metadata(path) {
+            Err(err) => errors.report(with_path(path, err))?,
+            Ok(md) if md.is_dir() => walk(path, opts, errors, &mut inputs)?,
+            Ok(_) => inputs.push(Input::File(path.clone())),
+        }
+    }
+    Ok(inputs)
+}
+
+fn walk(
+    dir: &Path,
+    opts: &WalkOptions,
+    errors: &mut Errors,
+    inputs: &mut Vec<Input>,
+) -> Result<(), Box<dyn Error>> {
+    let mut overrides = OverrideBuilder::new(dir);
+    for glob in opts.include.iter() {
+        overrides.add(glob)?;
+    }
+    for glob in opts.exclude.iter() {
+        overrides.add(&format!("!{}", glob))?;
+    }
+
+    let mut builder = WalkBuilder::new(dir);
+    builder
+        .standard_filters(false)
+        .overrides(overrides.build()?)
+        .sort_by_file_name(|a, b| a.cmp(b));
+    if opts.gitignore {
+        builder
+            .git_ignore(true)
+            .git_exclude(true)
+            .git_global(true)
+            .parents(true)
+            .require_git(false)
+            // .git isn't in anyone's .gitignore, but it may as well be
+            .filter_entry(|entry| entry.file_name() != ".git");
+    }
+    for result in builder.build() {
+        let entry = match result {
+            Ok(entry) => entry,
+            Err(err) => {
+                errors.report(err)?;
+                continue;
+            }
+        };
+        if entry.file_type().is_some_and(|ft| ft.is_file()) {
+            inputs.push(Input::File(entry.into_path()));
+        }
+    }
+    Ok(())
+}
+
+/// Attaches a path to an error that occurred while reading it.
+pub(crate) fn with_path(path: &Path, err: io::Error) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+}
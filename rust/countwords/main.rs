@@ -0,0 +1,506 @@
+// This program runs any of the strategies in countwords-core, chosen at
+// runtime with --engine. Benchmarking one strategy against another is then a
+// matter of changing a flag instead of building another crate.
+//
+// By default, output is the same "word count" lines the other Rust programs
+// print, most frequent first, but with ties broken alphabetically so that no
+// normalizing is needed to compare two runs. Words are written as the raw bytes
+// they were counted as, so that engines which don't require UTF-8 never fail
+// while printing. --format and --invalid-utf8 choose something else.
+//
+// `countwords sketch` builds and queries Count-Min Sketches instead, for when
+// only estimates of a few words' counts are needed from a huge input,
+// `countwords index` saves a counted trie to query later,
+// --distinct counts how many different words there are, without keeping them,
+// and --prefix only writes the words that start with given prefixes.
+// --tokenizer, --normalizer, --filter, --stem and --pipeline change what
+// bonus-unicode counts as a word, and --stopwords leaves out the words that
+// say nothing about the text in any engine.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufReader, Read},
+    num::NonZeroUsize,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use bumpalo::Bump;
+use clap::{Parser, Subcommand};
+use countwords_core::{
+    pipeline::Language, AsciiWhitespace, CountOverflow, CountType, Engine, Feed, Format,
+    HashFunction, InvalidUtf8, Output, PerDocument, Select, Sort, SpaceSaving, StopWords,
+    UnknownEngine, WordCounter,
+};
+
+use crate::{
+    distinct::DistinctArgs,
+    index::IndexCommand,
+    input::{with_path, Errors, Input, InputArgs},
+    pipeline::PipelineArgs,
+    prefix::PrefixArgs,
+    sketch::SketchCommand,
+};
+
+mod distinct;
+mod index;
+mod input;
+mod pipeline;
+mod prefix;
+mod sketch;
+
+// how much of the first input --engine auto looks at before choosing
+const SAMPLE_SIZE: u64 = 64 * (1 << 10);
+
+/// Count the frequency of every word in the given files, or stdin.
+#[derive(Debug, Parser)]
+#[command(name = "countwords", args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The counting strategy: auto, simple, optimized, unsafe-arena, trie,
+    /// radix-trie, custom-table, bonus-unicode, fast-simple, threads, bumpalo
+    /// or well-faster-than-c.
+    ///
+    /// auto picks one based on the size of the input and whether it looks
+    /// like UTF-8, or bonus-unicode if any of the pipeline flags are
+    /// given. Whichever of bumpalo and well-faster-than-c it picks, words are
+    /// split on all ASCII whitespace, as bumpalo does.
+    #[arg(long, default_value = "auto", value_name = "ENGINE")]
+    engine: EngineChoice,
+
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+
+    /// The hash function --engine custom-table hashes words with: fnv1 (the
+    /// default), fnv1a, fx (the fastest) or siphash. siphash is keyed with a
+    /// random key, so that text crafted to collide can't slow counting down.
+    #[arg(long, value_name = "FUNCTION")]
+    hash: Option<HashFunction>,
+
+    /// How each word's count is kept: u32, u64, saturating-u32 or
+    /// saturating-u64. A word counted more times than a u32 or u64 holds is
+    /// an error, while the saturating ones stop at their maximum. By default,
+    /// trie and radix-trie use u32, since they keep a count for every node,
+    /// and the other engines use u64.
+    #[arg(long, value_name = "TYPE", conflicts_with = "approx_top")]
+    counts: Option<CountType>,
+
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Also show each word's count in each input, with one column per input
+    /// after the total. In JSON, they're an array of objects instead, each
+    /// with an input's "file" name and "count". With bumpalo, inputs are
+    /// counted by threads instead, which splits words the same way but frees
+    /// what it allocated for each input once it's been added to the total.
+    #[arg(long)]
+    per_file: bool,
+
+    /// Estimate the counts of the most frequent words using only K counters,
+    /// so that memory stays fixed however many distinct words there are.
+    /// Words are split like fast-simple does. Each count comes with an error:
+    /// the true count is at least the count minus the error. Stop words are
+    /// left out before they're counted, so they don't take any counters.
+    #[arg(long, value_name = "K", conflicts_with_all = ["engine", "per_file", "PipelineArgs"])]
+    approx_top: Option<NonZeroUsize>,
+
+    #[command(flatten)]
+    distinct: DistinctArgs,
+
+    #[command(flatten)]
+    prefix: PrefixArgs,
+
+    /// How to write the counts: plain, tsv, csv, json, ndjson or
+    /// json-object.
+    #[arg(long, default_value = "plain", value_name = "FORMAT")]
+    format: Format,
+
+    /// What to do with words that aren't valid UTF-8: raw (write them as
+    /// they are, which JSON formats can't and so treat as lossy), lossy
+    /// (replace invalid bytes with U+FFFD), skip or error.
+    #[arg(long, default_value = "raw", value_name = "POLICY")]
+    invalid_utf8: InvalidUtf8,
+
+    /// The order to write words in: count (most frequent first), count-asc,
+    /// alpha, length (longest first) or first-seen. Ties are broken
+    /// alphabetically, so output is the same on every run.
+    #[arg(long, default_value = "count", value_name = "ORDER")]
+    sort: Sort,
+
+    /// Only write the first N words in --sort order. This is much cheaper
+    /// than sorting everything when N is small.
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Only write words counted at least this many times.
+    #[arg(long, value_name = "N")]
+    min_count: Option<u64>,
+
+    /// Only write words counted at most this many times.
+    #[arg(long, value_name = "N")]
+    max_count: Option<u64>,
+
+    /// Only write words that are at least this many bytes long.
+    #[arg(long, value_name = "N")]
+    min_len: Option<usize>,
+
+    /// Only write words that are at most this many bytes long.
+    #[arg(long, value_name = "N")]
+    max_len: Option<usize>,
+
+    /// Leave out the built-in stop words for LANG: en, de, fr or es. They're
+    /// matched against words as the engine normalized them. May be given
+    /// more than once.
+    #[arg(long = "stopwords", value_name = "LANG", conflicts_with = "distinct")]
+    stopwords: Vec<Language>,
+
+    /// Leave out the words in FILE, which has one per line. Blank lines and
+    /// lines starting with # are skipped, and words are lowercased. May be
+    /// given more than once.
+    #[arg(
+        long = "stopwords-file",
+        value_name = "FILE",
+        conflicts_with = "distinct"
+    )]
+    stopwords_files: Vec<PathBuf>,
+}
+
+impl Args {
+    /// Returns the words to write, as chosen by --sort, --top and the
+    /// thresholds.
+    fn select(&self) -> Select {
+        let mut select = Select::new(self.sort);
+        if let Some(n) = self.top {
+            select = select.top(n);
+        }
+        if let Some(n) = self.min_count {
+            select = select.min_count(n);
+        }
+        if let Some(n) = self.max_count {
+            select = select.max_count(n);
+        }
+        if let Some(n) = self.min_len {
+            select = select.min_len(n);
+        }
+        if let Some(n) = self.max_len {
+            select = select.max_len(n);
+        }
+        select
+    }
+
+    /// Returns the stop words given by --stopwords and --stopwords-file, if
+    /// any were.
+    fn stopwords(&self) -> io::Result<Option<StopWords>> {
+        if self.stopwords.is_empty() && self.stopwords_files.is_empty() {
+            return Ok(None);
+        }
+        let mut stop = StopWords::new();
+        for &language in self.stopwords.iter() {
+            stop.add_builtin(language);
+        }
+        for path in self.stopwords_files.iter() {
+            File::open(path)
+                .and_then(|file| stop.add_list(BufReader::new(file)))
+                .map_err(|err| with_path(path, err))?;
+        }
+        Ok(Some(stop))
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Build, query or merge Count-Min Sketches, which estimate the count of
+    /// any word in a fixed amount of memory.
+    #[command(subcommand)]
+    Sketch(SketchCommand),
+    /// Save the counts of a corpus as a trie index, or look up words and
+    /// prefixes in one without counting again.
+    #[command(subcommand)]
+    Index(IndexCommand),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum EngineChoice {
+    Auto,
+    Fixed(Engine),
+}
+
+impl FromStr for EngineChoice {
+    type Err = UnknownEngine;
+
+    fn from_str(s: &str) -> Result<EngineChoice, UnknownEngine> {
+        if s == "auto" {
+            return Ok(EngineChoice::Auto);
+        }
+        s.parse().map(EngineChoice::Fixed)
+    }
+}
+
+fn main() {
+    // Rust blocks the broken pipe signal by default, and instead returns it as
+    // an error from `write` if the consumer hangs up. So we look for it here
+    // and exit gracefully, as an end user would expect.
+    match try_main() {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(err) => {
+            if let Some(ioerr) = err.downcast_ref::<io::Error>() {
+                if ioerr.kind() == io::ErrorKind::BrokenPipe {
+                    std::process::exit(0);
+                }
+            }
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns false if an error was reported but skipped over.
+fn try_main() -> Result<bool, Box<dyn Error>> {
+    let args = Args::parse();
+    match args.command {
+        Some(Command::Sketch(cmd)) => return sketch::run(cmd),
+        Some(Command::Index(cmd)) => return index::run(cmd),
+        None => {}
+    }
+    let stop = args.stopwords()?;
+    let mut select = args.select();
+    // A pipeline leaves stop words out itself, before they can be stemmed.
+    if let (Some(stop), false) = (&stop, args.pipeline.is_enabled()) {
+        select = select.stopwords(stop.clone());
+    }
+    let (inputs, mut errors) = args.input.collect()?;
+    if args.distinct.distinct && !args.distinct.exact {
+        let stdin_only = args.input.is_stdin_only();
+        return args.distinct.estimate(&inputs, stdin_only, &mut errors);
+    }
+    if args.prefix.is_enabled() {
+        if !matches!(
+            args.engine,
+            EngineChoice::Auto | EngineChoice::Fixed(Engine::Trie)
+        ) {
+            return Err("--prefix only applies to --engine trie".into());
+        }
+        let output = Output::new(args.format).invalid_utf8(args.invalid_utf8);
+        args.prefix
+            .run(args.counts, &inputs, &output, &select, &mut errors)?;
+        return Ok(!errors.any());
+    }
+
+    // When choosing an engine automatically, the first input has already been
+    // opened (or failed to open) by the time we know which engine to feed it
+    // to. The reader returned replays the sample that was read from it.
+    let mut first = None;
+    let engine = match args.engine {
+        EngineChoice::Fixed(engine) => engine,
+        EngineChoice::Auto if args.pipeline.is_enabled() => Engine::BonusUnicode,
+        // --exact checks the estimate, so it splits words like the estimate
+        EngineChoice::Auto if args.distinct.exact => Engine::CustomTable,
+        EngineChoice::Auto => {
+            let mut sample = vec![];
+            if let Some(input) = inputs.first() {
+                first = Some(sample_input(input).map(|(start, rdr)| {
+                    sample = start;
+                    rdr
+                }));
+            }
+            let mut size = inputs.iter().map(Input::size).sum::<Option<u64>>();
+            // a short sample of the only input means we've seen all of it
+            if inputs.len() == 1 && (sample.len() as u64) < SAMPLE_SIZE {
+                size = Some(sample.len() as u64);
+            }
+            Engine::auto(size, &sample)
+        }
+    };
+
+    if args.hash.is_some() && engine != Engine::CustomTable {
+        return Err("--hash only applies to --engine custom-table".into());
+    }
+    let hash = args.hash.unwrap_or_default();
+    let pipeline = if args.pipeline.is_enabled() {
+        if engine != Engine::BonusUnicode {
+            return Err(
+                "--tokenizer, --normalizer, --filter, --stem and --pipeline only apply \
+                 to --engine bonus-unicode"
+                    .into(),
+            );
+        }
+        let pipeline = args.pipeline.build()?;
+        Some(match &stop {
+            Some(stop) => pipeline.filter(stop.clone()),
+            None => pipeline,
+        })
+    } else {
+        None
+    };
+    if let (Some(pipeline), true) = (&pipeline, args.pipeline.stem_forms) {
+        let output = Output::new(args.format).invalid_utf8(args.invalid_utf8);
+        args.pipeline.write_forms(
+            pipeline,
+            args.counts,
+            &inputs,
+            &output,
+            &select,
+            &mut errors,
+        )?;
+        return Ok(!errors.any());
+    }
+    // With --per-file, every input gets a counter of its own, which mustn't
+    // leave what it allocated behind in `bump` once it's dropped.
+    let engine = if args.per_file {
+        engine.per_document()
+    } else {
+        engine
+    };
+    let bump = Bump::new();
+    // auto splits words like bumpalo whichever engine it picks, so that the
+    // size of the input doesn't change what counts as a word.
+    let split_whitespace =
+        matches!(args.engine, EngineChoice::Auto) && engine == Engine::WellFasterThanC;
+    let new_counter = || {
+        let counter = match (&pipeline, args.counts) {
+            (Some(pipeline), counts) => pipeline::counter(pipeline, counts),
+            (None, counts) if args.per_file => engine.document_counter(&bump, hash, counts),
+            (None, None) => engine.counter_with_hash(&bump, hash),
+            (None, Some(counts)) => engine.counter_with_counts(&bump, hash, counts),
+        };
+        if split_whitespace {
+            Box::new(AsciiWhitespace::new(counter))
+        } else {
+            counter
+        }
+    };
+
+    if args.distinct.exact {
+        let mut counter = new_counter();
+        return args
+            .distinct
+            .exact(&mut *counter, &inputs, first, &mut errors);
+    }
+
+    let output = Output::new(args.format).invalid_utf8(args.invalid_utf8);
+    if let Some(k) = args.approx_top {
+        let mut counter = SpaceSaving::new(k.get());
+        // Stop words mustn't take counters from the words being estimated.
+        if let Some(stop) = stop {
+            counter = counter.stopwords(stop);
+        }
+        count_inputs(&inputs, first, &mut counter, &mut errors)?;
+        write_estimates(&output, &select, &counter)?;
+    } else if args.per_file {
+        let mut counter = PerDocument::new(new_counter);
+        count_inputs(&inputs, first, &mut counter, &mut errors)?;
+        write_per_file(&output, &select, &counter, &inputs)?;
+    } else {
+        let mut counter = new_counter();
+        count_inputs(&inputs, first, &mut *counter, &mut errors)?;
+        if select.is_streamed(&counter) {
+            // The trie's words are already in order, so they're written as
+            // they're walked rather than collected first.
+            output.write_each(io::stdout().lock(), |f| select.for_each(&counter, f))?;
+        } else {
+            let ordered = select.apply(&counter);
+            output.write_counts(io::stdout().lock(), &ordered)?;
+        }
+    }
+    Ok(!errors.any())
+}
+
+/// Opens the given input and reads up to `SAMPLE_SIZE` bytes from it. The
+/// reader returned yields the entire input, starting with the sample.
+fn sample_input(input: &Input) -> io::Result<(Vec<u8>, Box<dyn Read>)> {
+    let mut rdr = input.open()?;
+    let mut sample = vec![];
+    (&mut rdr).take(SAMPLE_SIZE).read_to_end(&mut sample)?;
+    let replay = io::Cursor::new(sample.clone()).chain(rdr);
+    Ok((sample, Box::new(replay)))
+}
+
+/// Feeds every input to `counter` as its own input and finishes it. `first`
+/// is the already opened first input, if any.
+///
+/// A count that overflows is always an error, even with --keep-going, since
+/// it would be wrong from then on.
+fn count_inputs(
+    inputs: &[Input],
+    mut first: Option<io::Result<Box<dyn Read>>>,
+    counter: &mut dyn Feed,
+    errors: &mut Errors,
+) -> Result<(), Box<dyn Error>> {
+    for input in inputs {
+        let result = first
+            .take()
+            .unwrap_or_else(|| input.open())
+            .and_then(|rdr| countwords_core::feed_reader(rdr, &mut *counter));
+        if let Err(err) = result {
+            if CountOverflow::is(&err) {
+                return Err(with_counts_hint(err));
+            }
+            errors.report(input.error(err))?;
+            // Either way, the input has to end: a word cut short by the error
+            // shouldn't be joined with the next input, and with --per-file,
+            // every input gets a column.
+            counter.end_input()?;
+        }
+    }
+    counter.finish().map_err(with_counts_hint)
+}
+
+/// Points an error from a count that overflowed at the flag that avoids it.
+fn with_counts_hint(err: io::Error) -> Box<dyn Error> {
+    if CountOverflow::is(&err) {
+        format!("{} (see --counts)", err).into()
+    } else {
+        err.into()
+    }
+}
+
+/// Writes one row per word with its estimated count and the most by which
+/// that may overcount it.
+fn write_estimates(output: &Output, select: &Select, counter: &SpaceSaving) -> io::Result<()> {
+    let rows: Vec<(Vec<u8>, [u64; 2])> = select
+        .apply(counter)
+        .into_iter()
+        .map(|(word, count)| {
+            // Every word we're given comes from the counter.
+            let (_, error) = counter.estimate(&word).unwrap();
+            (word, [count, error])
+        })
+        .collect();
+    let rows = rows.iter().map(|(word, row)| (&word[..], &row[..]));
+    output.write_table(io::stdout().lock(), &["word", "count", "error"], rows)
+}
+
+/// Writes one row per word with its total count and its count in each input,
+/// under a header naming each input.
+fn write_per_file<C, F>(
+    output: &Output,
+    select: &Select,
+    counter: &PerDocument<C, F>,
+    inputs: &[Input],
+) -> io::Result<()>
+where
+    C: WordCounter,
+    F: FnMut() -> C,
+{
+    let names: Vec<_> = inputs.iter().map(Input::name).collect();
+    let names: Vec<&str> = names.iter().map(|name| &**name).collect();
+
+    let rows: Vec<(Vec<u8>, Vec<u64>)> = select
+        .apply(counter)
+        .into_iter()
+        .map(|(word, total)| {
+            let mut row = vec![0; inputs.len() + 1];
+            row[0] = total;
+            for &(document, count) in counter.get(&word) {
+                row[document as usize + 1] = count;
+            }
+            (word, row)
+        })
+        .collect();
+    let rows = rows.iter().map(|(word, row)| (&word[..], &row[..]));
+    output.write_per_file(io::stdout().lock(), &names, rows)
+}
@@ -0,0 +1,272 @@
+// --tokenizer, --normalizer and --filter assemble the `Pipeline` that
+// --engine bonus-unicode counts words with, and --pipeline reads the same
+// choices from a file, so that a pipeline that's used often doesn't have to
+// be spelled out every time. The file has one `key = value` per line, with the
+// same keys and values as the flags:
+//
+//   # split like the other variants, but lowercase with Unicode rules
+//   tokenizer = whitespace
+//   normalizer = unicode-lowercase
+//   filter = min-len:3
+//   stem = en
+//
+// Blank lines and lines starting with # are ignored.
+//
+// With a stemmer, --stem-forms writes which words were counted as each stem
+// instead of the counts, so that what stemming lumped together can be checked.
+
+use std::{error::Error, fmt, io, num::Saturating, path::PathBuf, str::FromStr};
+
+use countwords_core::{
+    pipeline::{Language, MaxLen, MinLen, Normalization, Stemmer, Tokenization},
+    Count, CountType, Output, Pipeline, Select, Unicode, WordCounter,
+};
+
+use crate::input::{with_path, Errors, Input};
+
+/// The flags that assemble a pipeline.
+#[derive(Debug, clap::Args)]
+pub(crate) struct PipelineArgs {
+    /// How --engine bonus-unicode finds words: unicode-words (the default,
+    /// Unicode word segmentation), whitespace (split on ASCII whitespace) or
+    /// ascii-alphabetic (runs of ASCII letters).
+    #[arg(long, value_name = "NAME")]
+    tokenizer: Option<Tokenization>,
+
+    /// How --engine bonus-unicode normalizes each word: unicode-lowercase
+    /// (the default), ascii-lowercase or none. May be given more than once,
+    /// in which case each is applied in turn.
+    #[arg(long = "normalizer", value_name = "NAME")]
+    normalizers: Vec<Normalization>,
+
+    /// Only count the normalized words that pass FILTER: min-len:N or
+    /// max-len:N, in bytes. May be given more than once. Unlike --min-len and
+    /// --max-len, words that don't pass are never counted at all.
+    #[arg(long = "filter", value_name = "FILTER")]
+    filters: Vec<FilterSpec>,
+
+    /// Count the stem of each word that passes the filters, as found by the
+    /// Snowball stemmer for LANG: en, de, fr or es. E.g., "blessed" and
+    /// "blessing" are both counted as "bless".
+    #[arg(long, value_name = "LANG")]
+    stem: Option<Language>,
+
+    /// Instead of the counts, write each stem chosen by --sort, --top and
+    /// the thresholds, with every word that was counted as it and how many
+    /// times. Needs a stemmer, from --stem or --pipeline.
+    #[arg(long, conflicts_with_all = ["per_file", "distinct"])]
+    pub(crate) stem_forms: bool,
+
+    /// Read the tokenizer, normalizers, filters and stemmer from FILE, which
+    /// has one `key = value` per line, e.g., `normalizer = ascii-lowercase`.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["tokenizer", "normalizers", "filters", "stem"],
+    )]
+    pipeline: Option<PathBuf>,
+}
+
+impl PipelineArgs {
+    /// Returns true if any part of a pipeline was given.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.tokenizer.is_some()
+            || !self.normalizers.is_empty()
+            || !self.filters.is_empty()
+            || self.stem.is_some()
+            || self.stem_forms
+            || self.pipeline.is_some()
+    }
+
+    /// Returns the pipeline the flags ask for, read from --pipeline if it was
+    /// given.
+    pub(crate) fn build(&self) -> Result<Pipeline, Box<dyn Error>> {
+        let pipeline = match self.pipeline {
+            None => assemble(
+                self.tokenizer.unwrap_or_default(),
+                &self.normalizers,
+                &self.filters,
+                self.stem,
+            ),
+            Some(ref path) => {
+                let config = std::fs::read_to_string(path).map_err(|err| with_path(path, err))?;
+                parse_config(&config).map_err(|err| format!("{}: {}", path.display(), err))?
+            }
+        };
+        if self.stem_forms && !pipeline.is_stemmed() {
+            return Err("--stem-forms needs a stemmer, from --stem or --pipeline".into());
+        }
+        Ok(pipeline)
+    }
+
+    /// Counts the inputs with `pipeline`, keeping counts as `counts`, or u64
+    /// by default, and writes the forms of each stem to stdout.
+    pub(crate) fn write_forms(
+        &self,
+        pipeline: &Pipeline,
+        counts: Option<CountType>,
+        inputs: &[Input],
+        output: &Output,
+        select: &Select,
+        errors: &mut Errors,
+    ) -> Result<(), Box<dyn Error>> {
+        match counts.unwrap_or(CountType::U64) {
+            CountType::U32 => forms::<u32>(pipeline, inputs, output, select, errors),
+            CountType::U64 => forms::<u64>(pipeline, inputs, output, select, errors),
+            CountType::SaturatingU32 => {
+                forms::<Saturating<u32>>(pipeline, inputs, output, select, errors)
+            }
+            CountType::SaturatingU64 => {
+                forms::<Saturating<u64>>(pipeline, inputs, output, select, errors)
+            }
+        }
+    }
+}
+
+fn forms<N: Count>(
+    pipeline: &Pipeline,
+    inputs: &[Input],
+    output: &Output,
+    select: &Select,
+    errors: &mut Errors,
+) -> Result<(), Box<dyn Error>> {
+    let mut counter = Unicode::<N>::with_pipeline(pipeline.clone()).keep_forms();
+    crate::count_inputs(inputs, None, &mut counter, errors)?;
+    let forms: Vec<_> = select
+        .apply(&counter)
+        .into_iter()
+        .map(|(stem, _)| {
+            let forms = counter.forms(&stem);
+            (stem, forms)
+        })
+        .collect();
+    let forms = forms.iter().map(|(stem, forms)| (&stem[..], &forms[..]));
+    output.write_forms(io::stdout().lock(), forms)?;
+    Ok(())
+}
+
+/// A filter given by --filter or in a config file.
+#[derive(Clone, Copy, Debug)]
+enum FilterSpec {
+    MinLen(usize),
+    MaxLen(usize),
+}
+
+impl FromStr for FilterSpec {
+    type Err = InvalidFilter;
+
+    fn from_str(s: &str) -> Result<FilterSpec, InvalidFilter> {
+        let invalid = || InvalidFilter(s.to_string());
+        let (name, n) = s.split_once(':').ok_or_else(invalid)?;
+        let n = n.parse().map_err(|_| invalid())?;
+        match name {
+            "min-len" => Ok(FilterSpec::MinLen(n)),
+            "max-len" => Ok(FilterSpec::MaxLen(n)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The error returned when parsing a filter that isn't min-len:N or
+/// max-len:N.
+#[derive(Clone, Debug)]
+struct InvalidFilter(String);
+
+impl fmt::Display for InvalidFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid filter '{}', expected min-len:N or max-len:N",
+            self.0
+        )
+    }
+}
+
+impl Error for InvalidFilter {}
+
+/// Returns a pipeline with the given stages. No normalizers means lowercasing
+/// with Unicode rules, like bonus does.
+fn assemble(
+    tokenizer: Tokenization,
+    normalizers: &[Normalization],
+    filters: &[FilterSpec],
+    stem: Option<Language>,
+) -> Pipeline {
+    let mut pipeline = Pipeline::new(tokenizer);
+    if normalizers.is_empty() {
+        pipeline = pipeline.normalize(Normalization::UnicodeLowercase);
+    }
+    for &normalizer in normalizers {
+        pipeline = pipeline.normalize(normalizer);
+    }
+    for &filter in filters {
+        pipeline = match filter {
+            FilterSpec::MinLen(n) => pipeline.filter(MinLen(n)),
+            FilterSpec::MaxLen(n) => pipeline.filter(MaxLen(n)),
+        };
+    }
+    if let Some(language) = stem {
+        pipeline = pipeline.stem(Stemmer::new(language));
+    }
+    pipeline
+}
+
+/// Parses a config file given by --pipeline.
+fn parse_config(config: &str) -> Result<Pipeline, Box<dyn Error>> {
+    let (mut tokenizer, mut stem) = (None, None);
+    let (mut normalizers, mut filters) = (vec![], vec![]);
+    for (i, line) in config.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at_line = |err: &dyn fmt::Display| format!("line {}: {}", i + 1, err);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| at_line(&"expected key = value"))?;
+        let value = value.trim();
+        match key.trim() {
+            "tokenizer" => {
+                if tokenizer.is_some() {
+                    return Err(at_line(&"only one tokenizer may be given").into());
+                }
+                tokenizer = Some(value.parse().map_err(|err| at_line(&err))?);
+            }
+            "normalizer" => normalizers.push(value.parse().map_err(|err| at_line(&err))?),
+            "filter" => filters.push(value.parse().map_err(|err| at_line(&err))?),
+            "stem" => {
+                if stem.is_some() {
+                    return Err(at_line(&"only one stemmer may be given").into());
+                }
+                stem = Some(value.parse().map_err(|err| at_line(&err))?);
+            }
+            key => {
+                let msg = format!(
+                    "unknown key '{}', expected one of: tokenizer, normalizer, filter, stem",
+                    key
+                );
+                return Err(at_line(&msg).into());
+            }
+        }
+    }
+    Ok(assemble(
+        tokenizer.unwrap_or_default(),
+        &normalizers,
+        &filters,
+        stem,
+    ))
+}
+
+/// Create a counter like --engine bonus-unicode's that counts the words
+/// `pipeline` yields, keeping its counts as `counts`, or u64 by default.
+pub(crate) fn counter(pipeline: &Pipeline, counts: Option<CountType>) -> Box<dyn WordCounter> {
+    fn of<N: Count>(pipeline: &Pipeline) -> Box<dyn WordCounter> {
+        Box::new(Unicode::<N>::with_pipeline(pipeline.clone()))
+    }
+    match counts.unwrap_or(CountType::U64) {
+        CountType::U32 => of::<u32>(pipeline),
+        CountType::U64 => of::<u64>(pipeline),
+        CountType::SaturatingU32 => of::<Saturating<u32>>(pipeline),
+        CountType::SaturatingU64 => of::<Saturating<u64>>(pipeline),
+    }
+}
@@ -0,0 +1,109 @@
+// --prefix answers the questions behind autocompletion: which words start
+// with a prefix, which of them are the most frequent, and how often the
+// prefix is used at all. The trie already keeps every word that starts with a
+// prefix under the same node, so counting is always done with it, and each
+// prefix is answered by walking only the words under its node.
+
+use std::{error::Error, io, num::Saturating};
+
+use countwords_core::{Count, CountType, Output, Select, TrieCounter};
+
+use crate::input::{Errors, Input};
+
+/// The flags that control --prefix.
+#[derive(Debug, clap::Args)]
+pub(crate) struct PrefixArgs {
+    /// After counting, only write the words that start with PREFIX, as
+    /// chosen by --sort, --top and the thresholds. May be given more than
+    /// once, in which case the words for each prefix are written in turn.
+    /// Counting is done with the trie, so --engine must be auto or trie.
+    /// Prefixes are lowercased like words are.
+    #[arg(
+        long = "prefix",
+        value_name = "PREFIX",
+        conflicts_with_all = ["per_file", "approx_top", "distinct", "hash", "PipelineArgs"],
+    )]
+    pub(crate) prefixes: Vec<String>,
+
+    /// With --prefix, write the total count of the words that start with
+    /// each prefix, and how many distinct words that is, instead of the
+    /// words. Only the words kept by the thresholds and stop words are
+    /// totalled.
+    #[arg(long, requires = "prefixes")]
+    prefix_totals: bool,
+}
+
+impl PrefixArgs {
+    /// Returns true if any prefix was given.
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.prefixes.is_empty()
+    }
+
+    /// Counts the inputs with a trie that keeps its counts as `counts`, or
+    /// u32 by default, and writes what was asked about each prefix to stdout.
+    pub(crate) fn run(
+        &self,
+        counts: Option<CountType>,
+        inputs: &[Input],
+        output: &Output,
+        select: &Select,
+        errors: &mut Errors,
+    ) -> Result<(), Box<dyn Error>> {
+        match counts.unwrap_or(CountType::U32) {
+            CountType::U32 => self.count::<u32>(inputs, output, select, errors),
+            CountType::U64 => self.count::<u64>(inputs, output, select, errors),
+            CountType::SaturatingU32 => {
+                self.count::<Saturating<u32>>(inputs, output, select, errors)
+            }
+            CountType::SaturatingU64 => {
+                self.count::<Saturating<u64>>(inputs, output, select, errors)
+            }
+        }
+    }
+
+    fn count<N: Count>(
+        &self,
+        inputs: &[Input],
+        output: &Output,
+        select: &Select,
+        errors: &mut Errors,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut counter = TrieCounter::<N>::default();
+        crate::count_inputs(inputs, None, &mut counter, errors)?;
+        self.write(output, select, &counter)?;
+        Ok(())
+    }
+
+    /// Writes what was asked about each prefix to stdout.
+    fn write<N: Count>(
+        &self,
+        output: &Output,
+        select: &Select,
+        counter: &TrieCounter<N>,
+    ) -> io::Result<()> {
+        let stdout = io::stdout().lock();
+        if self.prefix_totals {
+            let rows: Vec<(&[u8], [u64; 2])> = self
+                .prefixes
+                .iter()
+                .map(|prefix| {
+                    let (mut total, mut len) = (0u64, 0u64);
+                    counter.for_each_completion(prefix.as_bytes(), &mut |word, count, _| {
+                        if select.keeps(word, count) {
+                            total = total.saturating_add(count);
+                            len += 1;
+                        }
+                    });
+                    (prefix.as_bytes(), [total, len])
+                })
+                .collect();
+            let rows = rows.iter().map(|&(prefix, ref row)| (prefix, &row[..]));
+            return output.write_table(stdout, &["prefix", "total", "words"], rows);
+        }
+        let mut completions = vec![];
+        for prefix in self.prefixes.iter() {
+            completions.extend(counter.completions(prefix.as_bytes(), select));
+        }
+        output.write_counts(stdout, &completions)
+    }
+}
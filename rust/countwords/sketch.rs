@@ -0,0 +1,134 @@
+// `countwords sketch` builds a Count-Min Sketch of its inputs instead of
+// counting every word exactly, which keeps memory fixed no matter how big the
+// vocabulary is. Sketches are written to disk, so that they can be built on
+// separate shards, merged and queried later.
+
+use std::{
+    error::Error,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use countwords_core::{CountMinSketch, Output};
+
+use crate::input::{with_path, InputArgs};
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum SketchCommand {
+    /// Build a sketch of the words in the given files, or stdin. Words are
+    /// split like the custom-table engine does.
+    Build {
+        /// Where to write the sketch.
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+
+        /// The largest error in an estimate, as a fraction of the number of
+        /// words counted. Smaller is more accurate, but makes for a wider
+        /// sketch.
+        #[arg(long, default_value_t = 0.0001, value_name = "FRACTION")]
+        epsilon: f64,
+
+        /// The probability that an estimate's error is larger than that.
+        /// Smaller is more reliable, but makes for a deeper sketch.
+        #[arg(long, default_value_t = 0.01, value_name = "PROBABILITY")]
+        delta: f64,
+
+        /// Only raise the counters that need it when counting a word, which
+        /// makes estimates more accurate. Merging such sketches still works.
+        #[arg(long)]
+        conservative: bool,
+
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Print the estimated count of each word, in sketches merged together.
+    /// Estimates are never too low.
+    Query {
+        /// A sketch to query. Given more than once, the sketches are merged
+        /// first. They must all have been built with the same --epsilon and
+        /// --delta.
+        #[arg(long = "sketch", value_name = "FILE", required = true)]
+        sketches: Vec<PathBuf>,
+
+        /// The words to estimate counts for.
+        #[arg(value_name = "WORD", required = true)]
+        words: Vec<String>,
+    },
+    /// Merge sketches of different inputs into a sketch of all of them.
+    Merge {
+        /// Where to write the merged sketch.
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+
+        /// The sketches to merge. They must all have been built with the same
+        /// --epsilon and --delta.
+        #[arg(value_name = "FILE", required = true)]
+        sketches: Vec<PathBuf>,
+    },
+}
+
+/// Runs a sketch command. Returns false if an error was reported but skipped
+/// over.
+pub(crate) fn run(cmd: SketchCommand) -> Result<bool, Box<dyn Error>> {
+    match cmd {
+        SketchCommand::Build {
+            output,
+            epsilon,
+            delta,
+            conservative,
+            input,
+        } => {
+            let in_range = |x: f64| 0.0 < x && x < 1.0;
+            if !in_range(epsilon) || !in_range(delta) {
+                return Err("--epsilon and --delta must be between 0 and 1".into());
+            }
+            let (inputs, mut errors) = input.collect()?;
+            let mut sketch = CountMinSketch::with_error(epsilon, delta).conservative(conservative);
+            crate::count_inputs(&inputs, None, &mut sketch, &mut errors)?;
+            write(&sketch, &output)?;
+            Ok(!errors.any())
+        }
+        SketchCommand::Query { sketches, words } => {
+            let sketch = read_merged(&sketches)?;
+            let estimates: Vec<(Vec<u8>, u64)> = words
+                .into_iter()
+                .map(|word| {
+                    let estimate = sketch.estimate(word.as_bytes());
+                    (word.into_bytes(), estimate)
+                })
+                .collect();
+            Output::default().write_counts(io::stdout().lock(), &estimates)?;
+            Ok(true)
+        }
+        SketchCommand::Merge { output, sketches } => {
+            write(&read_merged(&sketches)?, &output)?;
+            Ok(true)
+        }
+    }
+}
+
+/// Reads every sketch at the given paths, which mustn't be empty, and merges
+/// them into one.
+fn read_merged(paths: &[PathBuf]) -> io::Result<CountMinSketch> {
+    let mut merged = read(&paths[0])?;
+    for path in paths[1..].iter() {
+        merged
+            .merge(&read(path)?)
+            .map_err(|err| with_path(path, err))?;
+    }
+    Ok(merged)
+}
+
+fn read(path: &Path) -> io::Result<CountMinSketch> {
+    File::open(path)
+        .and_then(CountMinSketch::read_from)
+        .map_err(|err| with_path(path, err))
+}
+
+fn write(sketch: &CountMinSketch, path: &Path) -> io::Result<()> {
+    File::create(path)
+        .and_then(|file| sketch.write_to(file))
+        .map_err(|err| with_path(path, err))
+}
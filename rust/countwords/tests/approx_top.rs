@@ -0,0 +1,35 @@
+// Checks that --approx-top finds the same most frequent words as an exact
+// count when stop words are left out, rather than losing counters to them.
+
+use std::path::Path;
+
+use common::countwords;
+
+mod common;
+
+#[test]
+fn stopwords() {
+    let mut input = "the and of a\n".repeat(500);
+    for (i, word) in ["shepherd", "earth", "heaven", "void"].iter().enumerate() {
+        input.push_str(&format!("{}\n", word).repeat(40 - 5 * i));
+    }
+    for i in 0..100 {
+        input.push_str(&format!("noise{}\n", "x".repeat(i)));
+    }
+
+    let stdout = |args: &[&str]| {
+        let (ok, stdout, stderr) = countwords(Path::new("."), args, input.as_bytes());
+        assert!(ok, "{}", stderr);
+        stdout
+    };
+    let exact = stdout(&["--engine", "fast-simple", "--stopwords", "en", "--top", "4"]);
+    assert_eq!(exact, "shepherd 40\nearth 35\nheaven 30\nvoid 25\n");
+    let approx = stdout(&["--approx-top", "10", "--stopwords", "en", "--top", "4"]);
+    let approx: Vec<String> = approx
+        .lines()
+        .skip(1)
+        .map(|line| line.rsplit_once(' ').unwrap().0.to_string())
+        .collect();
+    let exact: Vec<&str> = exact.lines().collect();
+    assert_eq!(approx, exact);
+}
@@ -0,0 +1,30 @@
+// Runs the countwords binary, for the tests that check what it does with its
+// flags and inputs rather than what the engines behind it count.
+
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Runs countwords in `dir` with `args`, giving it `input` on stdin, and
+/// returns whether it succeeded, along with what it wrote to stdout and
+/// stderr. stdout is decoded lossily, since words needn't be UTF-8.
+pub fn countwords(dir: &Path, args: &[&str], input: &[u8]) -> (bool, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_countwords"))
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // countwords may fail, or not read stdin at all, before it's taken all
+    // of the input, which then can't be written. What it did is what's
+    // checked.
+    let _ = child.stdin.take().unwrap().write_all(input);
+    let out = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout).into_owned();
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    (out.status.success(), stdout, stderr)
+}
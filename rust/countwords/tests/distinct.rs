@@ -0,0 +1,27 @@
+// Checks that --distinct --exact splits words like the estimate it's there to
+// check, unless it's told which engine to count with.
+
+use std::path::Path;
+
+use common::countwords;
+
+mod common;
+
+#[test]
+fn exact_splits_like_the_estimate() {
+    // the estimate splits on spaces and newlines only, so "the\tcat" is one
+    // word, where bumpalo splits it in two
+    let stdout = |args: &[&str]| {
+        let input = b"The the\tcat THE cat 42\ncat\tdog\n";
+        let (ok, stdout, stderr) = countwords(Path::new("."), args, input);
+        assert!(ok, "{}", stderr);
+        stdout
+    };
+    let estimate = stdout(&["--distinct"]);
+    assert_eq!(estimate.lines().next(), Some("estimate 5"));
+    assert_eq!(stdout(&["--distinct", "--exact"]), "5\n");
+    assert_eq!(
+        stdout(&["--distinct", "--exact", "--engine", "bumpalo"]),
+        "4\n"
+    );
+}
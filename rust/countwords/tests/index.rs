@@ -0,0 +1,38 @@
+// Checks that queries don't read a whole index unless --verify asks them to,
+// and that --verify finds an index that was damaged.
+
+use std::fs;
+
+use common::countwords;
+
+mod common;
+
+#[test]
+fn verify() {
+    let dir = tempfile::tempdir().unwrap();
+    let build = ["index", "build", "--output", "words.idx"];
+    let (ok, _, stderr) = countwords(dir.path(), &build, b"the cat and the dog\n");
+    assert!(ok, "{}", stderr);
+
+    let query = ["index", "query", "--index", "words.idx", "the"];
+    let verified = ["index", "query", "--verify", "--index", "words.idx", "the"];
+    for args in [&query[..], &verified[..]] {
+        let (ok, stdout, stderr) = countwords(dir.path(), args, b"");
+        assert!(ok, "{}", stderr);
+        assert_eq!(stdout, "the 2\n");
+    }
+
+    // a flipped bit in the last node's first seen order, which no query
+    // here reads
+    let path = dir.path().join("words.idx");
+    let mut bytes = fs::read(&path).unwrap();
+    *bytes.last_mut().unwrap() ^= 1;
+    fs::write(&path, bytes).unwrap();
+    let (ok, stdout, _) = countwords(dir.path(), &query, b"");
+    assert!(ok);
+    assert_eq!(stdout, "the 2\n");
+    let (ok, stdout, stderr) = countwords(dir.path(), &verified, b"");
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert_eq!(stderr, "words.idx: index checksum mismatch\n");
+}
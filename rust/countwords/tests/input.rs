@@ -0,0 +1,139 @@
+// Checks which inputs countwords finds for the paths it's given, by running it
+// with --per-file, which names every input it counted in its header, and what
+// it does with the inputs it can't read.
+
+use std::{fs, path::Path};
+
+use common::countwords;
+
+mod common;
+
+/// Returns the name of every input counted for `args`, in the order they
+/// were counted.
+fn inputs(dir: &Path, args: &[&str]) -> Vec<String> {
+    let mut args = args.to_vec();
+    args.extend(["--per-file", "--format", "tsv"]);
+    let (ok, stdout, stderr) = countwords(dir, &args, b"");
+    assert!(ok, "{}", stderr);
+    let header = stdout.lines().next().unwrap();
+    header.split('\t').skip(2).map(str::to_string).collect()
+}
+
+/// Creates every file in `files`, with its parent directories, under `dir`.
+fn create(dir: &Path, files: &[(&str, &[u8])]) {
+    for &(path, contents) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+#[test]
+fn walks_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    create(
+        dir.path(),
+        &[
+            ("docs/b.txt", b"two\n"),
+            ("docs/a/1.txt", b"one two\n"),
+            ("docs/a/2.md", b"two three\n"),
+            ("top.txt", b"top\n"),
+        ],
+    );
+    assert_eq!(
+        inputs(dir.path(), &["top.txt", "docs"]),
+        ["top.txt", "docs/a/1.txt", "docs/a/2.md", "docs/b.txt"]
+    );
+    let (ok, stdout, _) = countwords(dir.path(), &["docs", "top.txt"], b"");
+    assert!(ok);
+    assert_eq!(stdout, "two 3\none 1\nthree 1\ntop 1\n");
+}
+
+#[test]
+fn globs() {
+    let dir = tempfile::tempdir().unwrap();
+    create(
+        dir.path(),
+        &[
+            ("docs/a/1.txt", b"one\n"),
+            ("docs/a/2.md", b"two\n"),
+            ("docs/b/3.txt", b"three\n"),
+            ("docs/4.txt", b"four\n"),
+        ],
+    );
+    assert_eq!(
+        inputs(dir.path(), &["--include", "*.txt", "docs"]),
+        ["docs/4.txt", "docs/a/1.txt", "docs/b/3.txt"]
+    );
+    assert_eq!(
+        inputs(
+            dir.path(),
+            &["--include", "*.txt", "--exclude", "b", "docs"]
+        ),
+        ["docs/4.txt", "docs/a/1.txt"]
+    );
+    // globs only filter what a walk turns up
+    assert_eq!(
+        inputs(dir.path(), &["--exclude", "*.md", "docs/a/2.md"]),
+        ["docs/a/2.md"]
+    );
+}
+
+#[test]
+fn gitignore() {
+    let dir = tempfile::tempdir().unwrap();
+    create(
+        dir.path(),
+        &[
+            ("docs/.gitignore", b"build/\n*.log\n"),
+            ("docs/build/out.txt", b"out\n"),
+            ("docs/run.log", b"log\n"),
+            ("docs/src.txt", b"src\n"),
+        ],
+    );
+    assert_eq!(
+        inputs(dir.path(), &["docs"]),
+        [
+            "docs/.gitignore",
+            "docs/build/out.txt",
+            "docs/run.log",
+            "docs/src.txt"
+        ]
+    );
+    assert_eq!(
+        inputs(dir.path(), &["--gitignore", "docs"]),
+        ["docs/.gitignore", "docs/src.txt"]
+    );
+}
+
+#[test]
+fn keep_going() {
+    let dir = tempfile::tempdir().unwrap();
+    create(
+        dir.path(),
+        &[
+            ("1.txt", b"one two\n"),
+            ("bad.txt", b"bad \xFF\n"),
+            ("2.txt", b"two\n"),
+        ],
+    );
+    let paths = ["1.txt", "missing.txt", "bad.txt", "2.txt"];
+    // each of these requires UTF-8, so bad.txt is an error too
+    for engine in ["threads", "bumpalo", "simple"] {
+        let mut args = vec!["--engine", engine];
+        args.extend(paths);
+        let (ok, stdout, stderr) = countwords(dir.path(), &args, b"");
+        assert!(!ok, "{}", engine);
+        assert!(stdout.is_empty(), "{}", engine);
+        assert!(stderr.starts_with("missing.txt: "), "{}", engine);
+
+        args.push("--keep-going");
+        let (ok, stdout, stderr) = countwords(dir.path(), &args, b"");
+        assert!(!ok, "{}", engine);
+        assert_eq!(stdout, "two 2\none 1\n", "{}", engine);
+        let lines: Vec<&str> = stderr.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", engine);
+        assert!(lines[0].starts_with("missing.txt: "), "{}", engine);
+        assert!(lines[1].starts_with("bad.txt: "), "{}", engine);
+    }
+}
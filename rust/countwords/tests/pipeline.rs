@@ -0,0 +1,71 @@
+// Checks that --pipeline reads a pipeline from a file, and points at the line
+// of anything in it that isn't one.
+
+use std::fs;
+
+use common::countwords;
+
+mod common;
+
+/// Runs countwords with --pipeline `config` on `input`, and returns whether
+/// it succeeded, along with what it wrote to stdout and stderr.
+fn pipeline(config: &str, input: &[u8]) -> (bool, String, String) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("pipeline.conf"), config).unwrap();
+    countwords(dir.path(), &["--pipeline", "pipeline.conf"], input)
+}
+
+/// Returns the error countwords reports for `config`.
+fn error(config: &str) -> String {
+    let (ok, stdout, stderr) = pipeline(config, b"the end\n");
+    assert!(!ok, "{}", config);
+    assert!(stdout.is_empty(), "{}", config);
+    stderr
+}
+
+#[test]
+fn parses() {
+    let config = "# counts words of three letters or more\n\
+                  tokenizer = ascii-alphabetic\n\
+                  \n\
+                  normalizer=ascii-lowercase\n  \
+                  filter = min-len:3  \n";
+    let (ok, stdout, stderr) = pipeline(config, b"The cat's mat, a THE\n");
+    assert!(ok, "{}", stderr);
+    assert_eq!(stdout, "the 2\ncat 1\nmat 1\n");
+}
+
+#[test]
+fn unknown_key() {
+    assert_eq!(
+        error("tokenizer = whitespace\nbogus = 1\n"),
+        "pipeline.conf: line 2: unknown key 'bogus', \
+         expected one of: tokenizer, normalizer, filter, stem\n"
+    );
+    assert_eq!(
+        error("# no value\ntokenizer\n"),
+        "pipeline.conf: line 2: expected key = value\n"
+    );
+}
+
+#[test]
+fn duplicate_tokenizer() {
+    assert_eq!(
+        error("tokenizer = whitespace\n\ntokenizer = ascii-alphabetic\n"),
+        "pipeline.conf: line 3: only one tokenizer may be given\n"
+    );
+    assert_eq!(
+        error("stem = en\nstem = en\n"),
+        "pipeline.conf: line 2: only one stemmer may be given\n"
+    );
+}
+
+#[test]
+fn bad_filter() {
+    assert_eq!(
+        error("filter = min-len:3\nfilter = nonsense\n"),
+        "pipeline.conf: line 2: invalid filter 'nonsense', \
+         expected min-len:N or max-len:N\n"
+    );
+    assert!(error("filter = min-len:x\n").starts_with("pipeline.conf: line 1: "));
+}
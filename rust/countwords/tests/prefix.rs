@@ -0,0 +1,28 @@
+// Checks that --prefix-totals leaves out stop words and words outside the
+// thresholds, like --prefix does when it writes the words.
+
+use std::path::Path;
+
+use common::countwords;
+
+mod common;
+
+#[test]
+fn totals_select() {
+    let input = b"the then then them they they they theory\n";
+    let totals = |args: &[&str]| {
+        let mut all = vec!["--prefix", "the", "--prefix-totals"];
+        all.extend(args);
+        let (ok, stdout, stderr) = countwords(Path::new("."), &all, input);
+        assert!(ok, "{}", stderr);
+        // skip the header
+        stdout.lines().nth(1).unwrap().to_string()
+    };
+
+    assert_eq!(totals(&[]), "the 8 5");
+    assert_eq!(totals(&["--min-count", "2"]), "the 5 2");
+    assert_eq!(totals(&["--max-count", "1"]), "the 3 3");
+    assert_eq!(totals(&["--max-len", "4"]), "the 7 4");
+    assert_eq!(totals(&["--min-len", "5"]), "the 1 1");
+    assert_eq!(totals(&["--stopwords", "en"]), "the 1 1");
+}
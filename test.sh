@@ -32,34 +32,44 @@ git diff --exit-code output.txt
 
 echo Rust simple
 cargo build --release --manifest-path rust/simple/Cargo.toml
-./rust/simple/target/release/countwords <kjvbible_x10.txt | python3 normalize.py >output.txt
+./rust/target/release/countwords-simple <kjvbible_x10.txt | python3 normalize.py >output.txt
 git diff --exit-code output.txt
 
 echo Rust optimized
 cargo build --release --manifest-path rust/optimized/Cargo.toml
-./rust/optimized/target/release/countwords <kjvbible_x10.txt | python3 normalize.py >output.txt
+./rust/target/release/countwords-optimized <kjvbible_x10.txt | python3 normalize.py >output.txt
 git diff --exit-code output.txt
 
 echo Rust optimized-unsafe
 cargo build --release --manifest-path rust/optimized-unsafe/Cargo.toml
-./rust/optimized-unsafe/target/release/countwords <kjvbible_x10.txt | python3 normalize.py >output.txt
+./rust/target/release/countwords-optimized-unsafe <kjvbible_x10.txt | python3 normalize.py >output.txt
 git diff --exit-code output.txt
 
 echo Rust optimized trie
 cargo build --release --manifest-path rust/optimized-trie/Cargo.toml
-./rust/optimized-trie/target/release/countwords <kjvbible_x10.txt | python3 normalize.py >output.txt
+./rust/target/release/countwords-optimized-trie <kjvbible_x10.txt | python3 normalize.py >output.txt
 git diff --exit-code output.txt
 
 echo Rust optimized custom hashmap
 cargo build --release --manifest-path rust/optimized-customhashmap/Cargo.toml
-./rust/optimized-customhashmap/target/release/countwords <kjvbible_x10.txt | python3 normalize.py >output.txt
+./rust/target/release/countwords-optimized-customhashmap <kjvbible_x10.txt | python3 normalize.py >output.txt
 git diff --exit-code output.txt
 
 echo Rust bonus '(Unicode word segmentation)'
 cargo build --release --manifest-path rust/bonus/Cargo.toml
-./rust/bonus/target/release/countwords <kjvbible_x10.txt | python3 normalize.py >output.txt
+./rust/target/release/countwords-bonus <kjvbible_x10.txt | python3 normalize.py >output.txt
 # We don't test its output since it uses a different segmenter.
 
+echo Rust countwords
+cargo build --release --manifest-path rust/countwords/Cargo.toml
+# countwords breaks ties the same way normalize.py does, so its output is
+# compared as is.
+for engine in simple optimized unsafe-arena trie radix-trie custom-table threads bumpalo well-faster-than-c auto; do
+  echo "  --engine $engine"
+  ./rust/target/release/countwords --engine $engine <kjvbible_x10.txt >output.txt
+  git diff --exit-code output.txt
+done
+
 echo C++ simple
 g++ -O2 simple.cpp -o simple-cpp
 ./simple-cpp <kjvbible_x10.txt | python3 normalize.py >output.txt